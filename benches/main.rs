@@ -1,7 +1,11 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, Criterion};
+use lsi::FatIstr;
 use lsi::GLOBAL_TABLE;
-use lsi::{Istr, InternedData};
-use string_interner::StringInterner;
+use lsi::InternTable;
+use lsi::Istr;
+use lsi::IstrMap;
+use std::collections::HashMap;
+use std::time::Duration;
 use ustr::ustr;
 
 static DATA_64X10K: &str = include_str!("../data/64x10k.txt");
@@ -18,6 +22,7 @@ fn bench_intern_strings(c: &mut Criterion) {
         }
     }));
     println!("lsi::Istr::new: {} interned", GLOBAL_TABLE.len());
+    c.bench_function("lsi::Istr::intern_all", |b| b.iter(|| Istr::intern_all(&data).unwrap().len()));
     // c.bench_function("string_interner::StringInterner::get_or_intern", |b| b.iter(|| {
     //     let mut interner: StringInterner = StringInterner::new();
     //     for &s in &data {
@@ -31,5 +36,123 @@ fn bench_intern_strings(c: &mut Criterion) {
     }));
 }
 
-criterion_group!(create_strings, bench_intern_strings);
+/// Lookups of `data` drawn from a zipfian-like distribution: the string of rank `r` is looked
+/// up with probability proportional to `1 / r`. Compare with and without `--features hot-tier`.
+fn bench_zipfian(c: &mut Criterion) {
+    let data = data_64x10k();
+    let table = InternTable::new();
+    for &s in &data {
+        table.get_or_intern(s);
+    }
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let lookups: Vec<&str> = (0..100_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+            data[(data.len() as f64).powf(u) as usize - 1]
+        })
+        .collect();
+    c.bench_function("lsi::InternTable::get_or_intern zipfian", |b| b.iter(|| {
+        for &s in &lookups {
+            table.get_or_intern(s);
+        }
+    }));
+}
+
+/// Building and probing a map keyed by interned strings, against the same map keyed by
+/// `String`s, which hashes every key's contents.
+fn bench_istr_map(c: &mut Criterion) {
+    let data = data_64x10k();
+    let table = InternTable::new();
+    let keys: Vec<Istr> = data.iter().map(|&s| table.get_or_intern(s)).collect();
+    let strings: Vec<String> = data.iter().map(|&s| s.to_owned()).collect();
+    c.bench_function("lsi::IstrMap insert+get", |b| b.iter(|| {
+        let mut map = IstrMap::default();
+        for (i, &key) in keys.iter().enumerate() {
+            map.insert(key, i);
+        }
+        keys.iter().map(|key| map[key]).sum::<usize>()
+    }));
+    c.bench_function("std::HashMap<String, _> insert+get", |b| b.iter(|| {
+        let mut map = HashMap::new();
+        for (i, key) in strings.iter().enumerate() {
+            map.insert(key.clone(), i);
+        }
+        strings.iter().map(|key| map[key]).sum::<usize>()
+    }));
+}
+
+/// Sorting 10k handles with `lsi::sort`, against comparing their strings directly.
+fn bench_sort(c: &mut Criterion) {
+    let data = data_64x10k();
+    let table = InternTable::new();
+    let keys: Vec<Istr> = data.iter().map(|&s| table.get_or_intern(s)).collect();
+    c.bench_function("lsi::sort", |b| b.iter(|| {
+        let mut v = keys.clone();
+        lsi::sort(&mut v);
+        v
+    }));
+    c.bench_function("sort_by as_str", |b| b.iter(|| {
+        let mut v = keys.clone();
+        v.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        v
+    }));
+}
+
+/// Reading 10k strings, through their handles' headers or from `FatIstr`s' own fields.
+fn bench_as_str(c: &mut Criterion) {
+    let data = data_64x10k();
+    let table = InternTable::new();
+    let keys: Vec<Istr> = data.iter().map(|&s| table.get_or_intern(s)).collect();
+    let fat: Vec<FatIstr> = keys.iter().map(|&s| s.into()).collect();
+    c.bench_function("lsi::Istr::as_str", |b| b.iter(|| {
+        keys.iter().map(|s| s.as_str().as_bytes().first().map_or(0, |&b| b as usize)).sum::<usize>()
+    }));
+    c.bench_function("lsi::FatIstr::as_str", |b| b.iter(|| {
+        fat.iter().map(|s| s.as_str().as_bytes().first().map_or(0, |&b| b as usize)).sum::<usize>()
+    }));
+}
+
+/// The cold start of a compiler reading its first file: every string is new. Each iteration
+/// interns into a fresh table, and tables leak their strings, so the bench is kept short.
+fn bench_cold_start(c: &mut Criterion) {
+    let data = data_64x10k();
+    let mut group = c.benchmark_group("cold start");
+    group
+        .sample_size(10)
+        .warm_up_time(Duration::from_millis(200))
+        .measurement_time(Duration::from_secs(1));
+    group.bench_function("lsi::InternTable::get_or_intern", |b| b.iter(|| {
+        let table = InternTable::new();
+        for &s in &data {
+            table.get_or_intern(s);
+        }
+        table.len()
+    }));
+    // The miss path alone, without growing the shards.
+    group.bench_function("lsi::InternTable::get_or_intern reserved", |b| b.iter(|| {
+        let table = InternTable::new();
+        table.reserve(data.len());
+        for &s in &data {
+            table.get_or_intern(s);
+        }
+        table.len()
+    }));
+    group.bench_function("lsi::InternTable::bulk_load_unique", |b| b.iter(|| {
+        InternTable::new().bulk_load_unique(&data).len()
+    }));
+    group.finish();
+}
+
+criterion_group!(
+    create_strings,
+    bench_intern_strings,
+    bench_zipfian,
+    bench_istr_map,
+    bench_sort,
+    bench_as_str,
+    bench_cold_start
+);
 criterion_main!(create_strings);
\ No newline at end of file
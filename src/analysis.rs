@@ -0,0 +1,98 @@
+//! Estimating whether interning a column of strings is worthwhile, without interning it.
+use std::collections::HashSet;
+use std::hash::BuildHasher;
+use std::mem::size_of;
+
+use hashbrown::hash_map::DefaultHashBuilder;
+
+use crate::{Header, Istr};
+
+/// Scan `strings` and report how much interning them would save.
+///
+/// Strings are deduplicated by a 64-bit hash, so counts are estimates: two distinct strings
+/// with colliding hashes are counted once. Nothing is interned.
+pub fn analyze<I>(strings: I) -> Report
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let hasher = DefaultHashBuilder::default();
+    let mut seen = HashSet::new();
+    let mut report = Report::default();
+    for s in strings {
+        let s = s.as_ref();
+        report.total += 1;
+        report.total_bytes += s.len();
+        if !s.is_empty() && seen.insert(hasher.hash_one(s)) {
+            report.unique += 1;
+            report.unique_bytes += s.len();
+        }
+    }
+    report
+}
+
+/// The result of [`analyze`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The number of strings scanned.
+    pub total: usize,
+    /// The estimated number of distinct non-empty strings.
+    pub unique: usize,
+    /// The combined length of all strings scanned.
+    pub total_bytes: usize,
+    /// The combined length of the distinct strings.
+    pub unique_bytes: usize,
+}
+
+impl Report {
+    /// How many times each distinct string occurs, on average.
+    pub fn duplication_factor(&self) -> f64 {
+        if self.unique == 0 {
+            return 0.0;
+        }
+        self.total as f64 / self.unique as f64
+    }
+
+    /// Memory used when every string is an owned `String`.
+    pub fn owned_bytes(&self) -> usize {
+        self.total_bytes + self.total * size_of::<String>()
+    }
+
+    /// Memory used when every string is an [`Istr`], including the leaked table entries.
+    pub fn interned_bytes(&self) -> usize {
+        self.unique_bytes + self.unique * size_of::<Header>() + self.total * size_of::<Istr>()
+    }
+
+    /// Bytes saved by interning. Negative when interning would use more memory.
+    pub fn byte_savings(&self) -> isize {
+        self.owned_bytes() as isize - self.interned_bytes() as isize
+    }
+
+    /// The number of strings to make room for, as the `capacity` of `lsi::Config` or with
+    /// [`InternTable::reserve`](crate::InternTable::reserve), so that interning the column
+    /// doesn't rehash: the estimated number of distinct strings. The table rounds it up to the
+    /// buckets it needs itself.
+    ///
+    /// There is no shard count to recommend alongside it, as it is fixed at
+    /// [`SHARDS`](crate::SHARDS), see `lsi::Config`.
+    pub fn recommended_capacity(&self) -> usize {
+        self.unique
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        let column = ["GET", "POST", "GET", "GET", "", "PUT"];
+        let report = analyze(column);
+        assert_eq!(report.total, 6);
+        assert_eq!(report.unique, 3);
+        assert_eq!(report.unique_bytes, 10);
+        assert_eq!(report.duplication_factor(), 2.0);
+        assert_eq!(report.recommended_capacity(), 3);
+        assert!(analyze(vec!["x".to_string(); 1000]).byte_savings() > 0);
+    }
+}
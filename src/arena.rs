@@ -0,0 +1,161 @@
+//! Bump allocation of entries.
+//!
+//! Entries are never freed, so each shard carves them out of chunks instead of making an
+//! allocator call per string: an insert is then a pointer bump, there is no per-allocation
+//! bookkeeping, and entries interned one after the other sit next to each other in memory.
+//! Chunks are leaked along with their entries, unless the table is
+//! [reset](crate::InternTable::reset). A shard's first chunk is 1 KiB, so that small
+//! tables stay small, and each next one is twice as large, up to 64 KiB. An entry larger than a
+//! quarter of the largest chunk gets an allocation of its own, so that a long string doesn't
+//! waste the rest of a chunk.
+//!
+//! With the `checkpoint` feature every entry gets an allocation of its own, as restoring a
+//! checkpoint frees entries one by one.
+use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use std::mem::align_of;
+#[cfg(not(feature = "checkpoint"))]
+use std::ptr::null_mut;
+
+use crate::{Header, InternedData, Istr};
+
+/// The size of a shard's first chunk.
+#[cfg(not(feature = "checkpoint"))]
+const MIN_CHUNK: usize = 1024;
+
+/// The size of a shard's largest chunks.
+#[cfg(not(feature = "checkpoint"))]
+const CHUNK: usize = 64 * 1024;
+
+/// The largest entry bump-allocated from a chunk.
+#[cfg(not(feature = "checkpoint"))]
+const MAX_BUMPED: usize = CHUNK / 4;
+
+/// The unused end of a shard's current chunk. Only accessed with the shard's write lock held.
+#[derive(Debug)]
+pub(crate) struct Arena {
+    #[cfg(not(feature = "checkpoint"))]
+    next: *mut u8,
+    #[cfg(not(feature = "checkpoint"))]
+    end: *mut u8,
+    /// The size of the next chunk.
+    #[cfg(not(feature = "checkpoint"))]
+    chunk: usize,
+    /// Every chunk allocated, for [`Arena::free`].
+    #[cfg(not(feature = "checkpoint"))]
+    chunks: Vec<(*mut u8, Layout)>,
+}
+
+// SAFETY: the pointers are into leaked chunks, and only used through `&mut Arena`.
+unsafe impl Send for Arena {}
+
+unsafe impl Sync for Arena {}
+
+impl Arena {
+    pub(crate) const fn new() -> Self {
+        Arena {
+            #[cfg(not(feature = "checkpoint"))]
+            next: null_mut(),
+            #[cfg(not(feature = "checkpoint"))]
+            end: null_mut(),
+            #[cfg(not(feature = "checkpoint"))]
+            chunk: MIN_CHUNK,
+            #[cfg(not(feature = "checkpoint"))]
+            chunks: Vec::new(),
+        }
+    }
+
+    /// Allocate the memory of an entry, `layout`, which is padded to the alignment of
+    /// [`Header`]. The memory is only freed by [`Arena::free`], or by restoring a checkpoint.
+    pub(crate) fn alloc(&mut self, layout: Layout) -> *mut u8 {
+        debug_assert!(layout.align() == align_of::<Header>());
+        debug_assert!(layout == layout.pad_to_align());
+        #[cfg(not(feature = "checkpoint"))]
+        if layout.size() <= MAX_BUMPED {
+            if (self.end as usize - self.next as usize) < layout.size() {
+                let size = self.chunk.max(layout.size());
+                #[cfg(feature = "tracing")]
+                tracing::debug!(size, chunks = self.chunks.len(), "allocating arena chunk");
+                self.chunk = (self.chunk * 2).min(CHUNK);
+                let chunk = Layout::from_size_align(size, align_of::<Header>()).unwrap();
+                self.next = global_alloc(chunk);
+                self.chunks.push((self.next, chunk));
+                // SAFETY: the chunk is `size` bytes long.
+                self.end = unsafe { self.next.add(size) };
+            }
+            let ptr = self.next;
+            // SAFETY: there are at least `layout.size()` bytes left in the chunk. Entry sizes
+            // are multiples of the alignment, so `next` stays aligned.
+            self.next = unsafe { ptr.add(layout.size()) };
+            return ptr;
+        }
+        global_alloc(layout)
+    }
+
+    /// Free every entry allocated from the arena, `entries`, and start over.
+    ///
+    /// # Safety
+    ///
+    /// `entries` must be exactly the entries allocated from this arena, and none of them may be
+    /// used afterwards.
+    pub(crate) unsafe fn free(&mut self, entries: impl Iterator<Item = Istr>) {
+        for s in entries {
+            free_entry(s);
+        }
+        #[cfg(not(feature = "checkpoint"))]
+        for (chunk, layout) in self.chunks.drain(..) {
+            dealloc(chunk, layout);
+        }
+        *self = Arena::new();
+    }
+}
+
+/// Free the allocation of `s`, if it has one of its own rather than being part of a chunk,
+/// which is the case for every entry with the `checkpoint` feature.
+///
+/// # Safety
+///
+/// `s` must have been allocated by an arena, and may not be used afterwards.
+pub(crate) unsafe fn free_entry(s: Istr) {
+    let layout = InternedData::layout_of(s.len()).unwrap();
+    #[cfg(not(feature = "checkpoint"))]
+    if layout.size() <= MAX_BUMPED {
+        // Freed with its chunk.
+        return;
+    }
+    dealloc(s.0.as_ptr() as *mut u8, layout);
+}
+
+fn global_alloc(layout: Layout) -> *mut u8 {
+    // SAFETY: entry layouts have a nonzero size, as the header does.
+    let ptr = unsafe { alloc(layout) };
+    if ptr.is_null() {
+        handle_alloc_error(layout);
+    }
+    ptr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arena() {
+        let mut arena = Arena::new();
+        let small = InternedData::layout_of(10).unwrap();
+        let a = arena.alloc(small);
+        let b = arena.alloc(small);
+        assert_eq!(a as usize % align_of::<Header>(), 0);
+        #[cfg(not(feature = "checkpoint"))]
+        assert_eq!(b as usize - a as usize, small.size());
+        let large = InternedData::layout_of(100_000).unwrap();
+        let c = arena.alloc(large);
+        assert!(c != a && c != b);
+        // SAFETY: each allocation is at least `small.size()` bytes long.
+        unsafe {
+            a.write_bytes(1, small.size());
+            b.write_bytes(2, small.size());
+            c.write_bytes(3, large.size());
+            assert_eq!(*a.add(small.size() - 1), 1);
+        }
+    }
+}
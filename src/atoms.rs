@@ -0,0 +1,402 @@
+//! Preset atoms: fixed vocabularies known at compile time.
+//!
+//! [`define_atoms!`](crate::define_atoms) declares a vocabulary once and generates everything
+//! derived from it: the list of names, a lazily interned [`AtomSet`], one [`Atom`] static per
+//! name, and (with the `phf` feature) a compile-time `phf::Set` of the same names.
+//!
+//! ```
+//! lsi::define_atoms! {
+//!     pub mod keywords {
+//!         FN = "fn",
+//!         LET = "let",
+//!     }
+//! }
+//!
+//! let s = lsi::Istr::new("let");
+//! assert!(keywords::is_static(s));
+//! assert_eq!(keywords::LET.get(), s);
+//! keywords::ATOMS.verify().unwrap();
+//! ```
+//!
+//! [`literal_set!`](crate::literal_set) is the same without the per-atom statics, for large
+//! lists of literals like route segments or GraphQL field names. Its `get` function looks a
+//! string up among the literals without interning it, so matching request input against the
+//! set never grows the table.
+//!
+//! ```
+//! lsi::literal_set! {
+//!     pub mod routes = ["users", "posts", "comments"];
+//! }
+//!
+//! assert_eq!(routes::get("posts"), Some(lsi::Istr::new("posts")));
+//! assert_eq!(routes::get("admin"), None);
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::{Error, Istr};
+
+/// The runtime side of a preset vocabulary. Names are interned on first use, which panics if
+/// one can't be, e.g. over the [budget](crate::budget): call [`AtomSet::verify`] first to get
+/// the error instead.
+pub struct AtomSet {
+    names: &'static [&'static str],
+    #[cfg(feature = "phf")]
+    phf: Option<&'static phf::Set<&'static str>>,
+    table: OnceLock<Table>,
+}
+
+struct Table {
+    atoms: Box<[Istr]>,
+    index: HashMap<Istr, usize>,
+    by_name: HashMap<&'static str, usize>,
+}
+
+impl AtomSet {
+    pub const fn new(names: &'static [&'static str]) -> Self {
+        AtomSet {
+            names,
+            #[cfg(feature = "phf")]
+            phf: None,
+            table: OnceLock::new(),
+        }
+    }
+
+    /// Create a set whose names are also available as a build-time `phf` set, which
+    /// [`AtomSet::verify`] checks against the runtime table.
+    #[cfg(feature = "phf")]
+    pub const fn with_phf(
+        names: &'static [&'static str],
+        phf: &'static phf::Set<&'static str>,
+    ) -> Self {
+        AtomSet {
+            names,
+            phf: Some(phf),
+            table: OnceLock::new(),
+        }
+    }
+
+    fn table(&self) -> &Table {
+        match self.try_table() {
+            Ok(table) => table,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Intern the names on first use. A failure isn't cached, so a later call tries again.
+    fn try_table(&self) -> Result<&Table, Error> {
+        if let Some(table) = self.table.get() {
+            return Ok(table);
+        }
+        let atoms = self
+            .names
+            .iter()
+            .map(|s| Istr::try_new(s))
+            .collect::<Result<Box<[Istr]>, Error>>()?;
+        let index = atoms.iter().enumerate().map(|(i, &a)| (a, i)).collect();
+        let by_name = self.names.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+        Ok(self.table.get_or_init(|| Table {
+            atoms,
+            index,
+            by_name,
+        }))
+    }
+
+    pub fn names(&self) -> &'static [&'static str] {
+        self.names
+    }
+
+    /// The interned atoms, in declaration order.
+    pub fn atoms(&self) -> &[Istr] {
+        &self.table().atoms
+    }
+
+    pub fn get(&self, index: usize) -> Istr {
+        self.table().atoms[index]
+    }
+
+    /// The declaration index of `s`, if it is one of the atoms of this set.
+    pub fn index_of(&self, s: Istr) -> Option<usize> {
+        self.table().index.get(&s).copied()
+    }
+
+    /// The atom named `s`, if there is one. Unlike [`Istr::new`], this never interns `s`.
+    pub fn lookup(&self, s: &str) -> Option<Istr> {
+        let table = self.table();
+        table.by_name.get(s).map(|&i| table.atoms[i])
+    }
+
+    /// Whether `s` is one of the atoms known at compile time, as opposed to a string interned
+    /// dynamically.
+    pub fn is_static(&self, s: Istr) -> bool {
+        self.table().index.contains_key(&s)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Check that the runtime table agrees with the declared names and, with the `phf`
+    /// feature, the build-time `phf` set. Intended to be called once at startup.
+    ///
+    /// Fails with [`Error::Atoms`] on a mismatch, or with the error of [`Istr::try_new`] if a
+    /// name can't be interned.
+    pub fn verify(&self) -> Result<(), Error> {
+        Ok(self.verify_table(self.try_table()?)?)
+    }
+
+    fn verify_table(&self, table: &Table) -> Result<(), AtomMismatch> {
+        for (&name, atom) in self.names.iter().zip(table.atoms.iter()) {
+            if atom.as_str() != name {
+                return Err(AtomMismatch::Interned(name));
+            }
+        }
+        if table.index.len() != self.names.len() {
+            return Err(AtomMismatch::Duplicate);
+        }
+        #[cfg(feature = "phf")]
+        if let Some(phf) = self.phf {
+            if phf.len() != self.names.len() {
+                return Err(AtomMismatch::Len {
+                    names: self.names.len(),
+                    phf: phf.len(),
+                });
+            }
+            if let Some(&name) = self.names.iter().find(|name| !phf.contains(*name)) {
+                return Err(AtomMismatch::MissingFromPhf(name));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AtomSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomSet")
+            .field("names", &self.names)
+            .finish()
+    }
+}
+
+/// A single named atom of an [`AtomSet`].
+#[derive(Debug)]
+pub struct Atom {
+    set: &'static AtomSet,
+    index: usize,
+}
+
+impl Atom {
+    pub const fn new(set: &'static AtomSet, index: usize) -> Self {
+        Atom { set, index }
+    }
+
+    pub fn get(&self) -> Istr {
+        self.set.get(self.index)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.set.names[self.index]
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl PartialEq<Istr> for Atom {
+    fn eq(&self, other: &Istr) -> bool {
+        self.get() == *other
+    }
+}
+
+impl PartialEq<Atom> for Istr {
+    fn eq(&self, other: &Atom) -> bool {
+        *self == other.get()
+    }
+}
+
+/// Returned by [`AtomSet::verify`], in [`Error::Atoms`], when the sources of an atom set
+/// disagree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AtomMismatch {
+    /// The interned atom doesn't match the declared name.
+    Interned(&'static str),
+    /// The same name was declared more than once.
+    Duplicate,
+    /// The `phf` set and the declared names have different lengths.
+    Len { names: usize, phf: usize },
+    /// A declared name is missing from the `phf` set.
+    MissingFromPhf(&'static str),
+}
+
+impl fmt::Display for AtomMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtomMismatch::Interned(name) => write!(f, "interned atom does not match {:?}", name),
+            AtomMismatch::Duplicate => f.write_str("atom declared more than once"),
+            AtomMismatch::Len { names, phf } => {
+                write!(
+                    f,
+                    "{} names declared but phf set has {} entries",
+                    names, phf
+                )
+            }
+            AtomMismatch::MissingFromPhf(name) => {
+                write!(f, "{:?} is missing from the phf set", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AtomMismatch {}
+
+/// Declare a module of preset atoms. See the [`atoms`](crate::atoms) module for an example.
+///
+/// The generated module contains `NAMES`, the `ATOMS` [`AtomSet`], an [`Atom`] static for
+/// each name, and an `is_static` function. With the `phf` feature it also contains `PHF`, a
+/// `phf::Set` of the names.
+#[macro_export]
+macro_rules! define_atoms {
+    (
+        $(#[$meta:meta])*
+        $vis:vis mod $module:ident {
+            $($(#[$atom_meta:meta])* $atom:ident = $name:literal),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis mod $module {
+            pub static NAMES: &[&str] = &[$($name),*];
+
+            $crate::__atom_set!($($name),*);
+
+            #[allow(dead_code, non_camel_case_types, clippy::upper_case_acronyms)]
+            #[repr(usize)]
+            enum __Index {
+                $($atom),*
+            }
+
+            $(
+                $(#[$atom_meta])*
+                pub static $atom: $crate::atoms::Atom =
+                    $crate::atoms::Atom::new(&ATOMS, __Index::$atom as usize);
+            )*
+
+            /// Whether `s` is one of the atoms of this module.
+            pub fn is_static(s: $crate::Istr) -> bool {
+                ATOMS.is_static(s)
+            }
+        }
+    };
+}
+
+/// Declare a module of preset literals. See the [`atoms`](crate::atoms) module for an example.
+///
+/// The generated module contains `NAMES`, the `ATOMS` [`AtomSet`], and `get` and `is_static`
+/// functions. With the `phf` feature it also contains `PHF`, a `phf::Set` of the literals.
+#[macro_export]
+macro_rules! literal_set {
+    (
+        $(#[$meta:meta])*
+        $vis:vis mod $module:ident = [$($name:literal),* $(,)?];
+    ) => {
+        $(#[$meta])*
+        $vis mod $module {
+            pub static NAMES: &[&str] = &[$($name),*];
+
+            $crate::__atom_set!($($name),*);
+
+            /// The interned literal equal to `s`, if there is one. Never interns `s`.
+            pub fn get(s: &str) -> Option<$crate::Istr> {
+                ATOMS.lookup(s)
+            }
+
+            /// Whether `s` is one of the literals of this module.
+            pub fn is_static(s: $crate::Istr) -> bool {
+                ATOMS.is_static(s)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "phf")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __atom_set {
+    ($($name:literal),*) => {
+        use $crate::__private::phf;
+
+        pub static PHF: phf::Set<&'static str> = $crate::__private::phf::phf_set! { $($name),* };
+        pub static ATOMS: $crate::atoms::AtomSet = $crate::atoms::AtomSet::with_phf(NAMES, &PHF);
+    };
+}
+
+#[cfg(not(feature = "phf"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __atom_set {
+    ($($name:literal),*) => {
+        pub static ATOMS: $crate::atoms::AtomSet = $crate::atoms::AtomSet::new(NAMES);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::define_atoms! {
+        mod http {
+            GET = "GET",
+            POST = "POST",
+            /// Not a real method.
+            BREW = "BREW",
+        }
+    }
+
+    #[test]
+    fn test_atoms() {
+        http::ATOMS.verify().unwrap();
+        assert_eq!(http::GET.as_str(), "GET");
+        assert_eq!(http::POST.get(), "POST");
+        assert_eq!(http::BREW.index(), 2);
+        assert!(http::is_static(Istr::new("GET")));
+        assert!(!http::is_static(Istr::new("PATCH")));
+        assert_eq!(http::ATOMS.index_of(Istr::new("POST")), Some(1));
+        #[cfg(feature = "phf")]
+        assert!(http::PHF.contains("BREW"));
+    }
+
+    crate::literal_set! {
+        mod fields = ["id", "name", "created_at"];
+    }
+
+    #[test]
+    fn test_literal_set() {
+        fields::ATOMS.verify().unwrap();
+        assert_eq!(fields::get("name"), Some(Istr::new("name")));
+        assert_eq!(fields::get("not a field literal"), None);
+        assert!(fields::is_static(crate::istr!("created_at")));
+        assert!(!crate::GLOBAL_TABLE
+            .to_vec()
+            .iter()
+            .any(|s| s.as_str() == "not a field literal"));
+    }
+
+    #[test]
+    fn test_verify_duplicate() {
+        static NAMES: &[&str] = &["a", "b", "a"];
+        static SET: AtomSet = AtomSet::new(NAMES);
+        assert_eq!(
+            SET.verify_table(&Table {
+                atoms: NAMES.iter().map(|s| Istr::new(s)).collect(),
+                index: NAMES.iter().map(|s| (Istr::new(s), 0)).collect(),
+                by_name: NAMES.iter().map(|&s| (s, 0)).collect(),
+            }),
+            Err(AtomMismatch::Duplicate)
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! [`InternerBackend`], the operations shared by the crate's tables, for code that should
+//! work with any of them.
+//!
+//! [`InternTable`] and [`LocalInterner`](crate::LocalInterner) implement it, and so can
+//! alternative tables outside the crate: a frozen table built once at startup, one backed by
+//! a memory-mapped file, or a test double counting lookups. Code written against the trait,
+//! like [`InternExt::interned_in`](crate::InternExt::interned_in), takes any of them, or a
+//! `&dyn InternerBackend` chosen at runtime.
+//!
+//! [`Istr::new`](crate::Istr::new) is unaffected: it always interns into the
+//! [`active_table`](crate::active_table). [`ActiveTable`] is the backend doing the same, with
+//! the checks of [`Istr::new`](crate::Istr::new) that [`InternTable`]'s own methods skip.
+//!
+//! ```
+//! use lsi::{InternTable, InternerBackend, Istr, LocalInterner};
+//!
+//! fn keywords(backend: &dyn InternerBackend) -> Vec<Istr> {
+//!     backend.get_or_intern_many(&["fn", "let", "fn"])
+//! }
+//! let table = InternTable::new();
+//! let local = LocalInterner::new();
+//! for backend in [&table as &dyn InternerBackend, &local] {
+//!     let found = keywords(backend);
+//!     assert_eq!(found[0], found[2]);
+//!     assert_eq!(backend.len(), 2);
+//! }
+//! ```
+use crate::{InternTable, Istr, LocalInterner};
+
+/// A table of interned strings.
+///
+/// Every handle a backend returns must stay valid for as long as the backend is used, and
+/// interning equal strings must return equal handles. The empty string needn't be stored:
+/// [`InternTable`] returns the same handle for it as every other table, without counting it.
+pub trait InternerBackend {
+    /// Intern `s`, returning the existing handle if it is already interned.
+    fn get_or_intern(&self, s: &str) -> Istr;
+
+    /// Look `s` up without inserting it.
+    fn get(&self, s: &str) -> Option<Istr>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every string in the backend, in no particular order.
+    fn iter(&self) -> Box<dyn Iterator<Item = Istr> + '_>;
+
+    /// Intern every string of `strings`, returning the handles in the same order. Backends
+    /// that can intern a batch more cheaply than one string at a time override this.
+    fn get_or_intern_many(&self, strings: &[&str]) -> Vec<Istr> {
+        strings.iter().map(|s| self.get_or_intern(s)).collect()
+    }
+}
+
+impl InternerBackend for InternTable {
+    fn get_or_intern(&self, s: &str) -> Istr {
+        InternTable::get_or_intern(self, s)
+    }
+
+    fn get(&self, s: &str) -> Option<Istr> {
+        InternTable::get(self, s)
+    }
+
+    fn len(&self) -> usize {
+        InternTable::len(self)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Istr> + '_> {
+        Box::new(InternTable::iter(self))
+    }
+
+    fn get_or_intern_many(&self, strings: &[&str]) -> Vec<Istr> {
+        InternTable::get_or_intern_many(self, strings)
+    }
+}
+
+/// The [active table](crate::active_table), interned into as by [`Istr::new`]: checked
+/// against the [frozen](crate::frozen) table, the [intercept](crate::intercept) hook and the
+/// [budget](crate::budget), and panicking for the strings they reject.
+///
+/// [`Istr::new`]: crate::Istr::new
+#[cfg(feature = "global")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ActiveTable;
+
+#[cfg(feature = "global")]
+impl InternerBackend for ActiveTable {
+    fn get_or_intern(&self, s: &str) -> Istr {
+        Istr::new(s)
+    }
+
+    fn get(&self, s: &str) -> Option<Istr> {
+        Istr::get(s)
+    }
+
+    fn len(&self) -> usize {
+        crate::active_table().len()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Istr> + '_> {
+        Box::new(crate::active_table().iter())
+    }
+
+    fn get_or_intern_many(&self, strings: &[&str]) -> Vec<Istr> {
+        match crate::convert::intern_many(strings.to_vec(), false) {
+            Ok(handles) => handles,
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+
+impl InternerBackend for LocalInterner {
+    fn get_or_intern(&self, s: &str) -> Istr {
+        LocalInterner::get_or_intern(self, s)
+    }
+
+    fn get(&self, s: &str) -> Option<Istr> {
+        LocalInterner::get(self, s)
+    }
+
+    fn len(&self) -> usize {
+        LocalInterner::len(self)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Istr> + '_> {
+        Box::new(self.to_vec().into_iter())
+    }
+}
+
+impl<B: InternerBackend + ?Sized> InternerBackend for &B {
+    fn get_or_intern(&self, s: &str) -> Istr {
+        (**self).get_or_intern(s)
+    }
+
+    fn get(&self, s: &str) -> Option<Istr> {
+        (**self).get(s)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Istr> + '_> {
+        (**self).iter()
+    }
+
+    fn get_or_intern_many(&self, strings: &[&str]) -> Vec<Istr> {
+        (**self).get_or_intern_many(strings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    /// A test double counting the strings interned through it.
+    struct Counting<B> {
+        inner: B,
+        interned: Cell<usize>,
+    }
+
+    impl<B: InternerBackend> InternerBackend for Counting<B> {
+        fn get_or_intern(&self, s: &str) -> Istr {
+            self.interned.set(self.interned.get() + 1);
+            self.inner.get_or_intern(s)
+        }
+
+        fn get(&self, s: &str) -> Option<Istr> {
+            self.inner.get(s)
+        }
+
+        fn len(&self) -> usize {
+            self.inner.len()
+        }
+
+        fn iter(&self) -> Box<dyn Iterator<Item = Istr> + '_> {
+            self.inner.iter()
+        }
+    }
+
+    #[test]
+    fn test_backends() {
+        let table = InternTable::new();
+        let counting = Counting {
+            inner: LocalInterner::new(),
+            interned: Cell::new(0),
+        };
+        for backend in [&table as &dyn InternerBackend, &counting] {
+            let [a, b, a2] =
+                <[Istr; 3]>::try_from(backend.get_or_intern_many(&["a", "b", "a"])).unwrap();
+            assert_eq!(a, a2);
+            assert_ne!(a, b);
+            assert_eq!(backend.get("b"), Some(b));
+            assert_eq!(backend.get("c"), None);
+            assert_eq!(backend.get(""), Some(backend.get_or_intern("")));
+            assert_eq!(backend.len(), 2);
+            let mut all: Vec<&str> = backend.iter().map(|s| s.as_str()).collect();
+            all.sort();
+            assert_eq!(all, ["a", "b"]);
+        }
+        assert_eq!(counting.interned.get(), 4);
+    }
+}
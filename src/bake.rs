@@ -0,0 +1,64 @@
+//! Compiling a table's contents into the next build.
+//!
+//! [`bake_snapshot_to_rust`] writes the current contents of the global table as a Rust source
+//! file declaring a [`literal_set!`](crate::literal_set) module named `baked`. Including that
+//! file, e.g. from a build script's output directory, gives the next build the vocabulary the
+//! last run discovered dynamically as preset atoms:
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/baked.rs"));
+//!
+//! fn main() {
+//!     // Interns every baked string up front, before any request is served.
+//!     baked::ATOMS.verify().unwrap();
+//! }
+//! ```
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::active_table;
+
+/// Write every string in the global table to `path` as a `baked` preset module, in sorted
+/// order so that the same contents always produce the same file.
+pub fn bake_snapshot_to_rust(path: impl AsRef<Path>) -> io::Result<()> {
+    let mut strings: Vec<&str> = active_table().to_vec().iter().map(|s| s.as_str()).collect();
+    strings.sort_unstable();
+    let mut file = BufWriter::new(File::create(path)?);
+    bake_to_rust(&mut file, "baked", strings)?;
+    file.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+/// Write `strings` as a [`literal_set!`](crate::literal_set) declaring a public module named
+/// `module`.
+pub fn bake_to_rust<'a>(
+    mut w: impl Write,
+    module: &str,
+    strings: impl IntoIterator<Item = &'a str>,
+) -> io::Result<()> {
+    writeln!(w, "// @generated by lsi::bake. Do not edit.")?;
+    writeln!(w, "lsi::literal_set! {{")?;
+    writeln!(w, "    pub mod {} = [", module)?;
+    for s in strings {
+        // `Debug` escapes strings as valid Rust string literals.
+        writeln!(w, "        {:?},", s)?;
+    }
+    writeln!(w, "    ];")?;
+    writeln!(w, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bake_to_rust() {
+        let mut out = Vec::new();
+        bake_to_rust(&mut out, "vocab", ["plain", "quote\"d", "tab\t", "é"]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("    pub mod vocab = [\n        \"plain\",\n"));
+        assert!(out.contains(r#"        "quote\"d","#));
+        assert!(out.contains(r#"        "tab\t","#));
+        assert!(out.contains("        \"é\",\n    ];\n}\n"));
+    }
+}
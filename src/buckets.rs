@@ -0,0 +1,109 @@
+//! Streaming a table partitioned by stable hash, for hash-partitioned joins.
+//!
+//! [`InternTable::buckets`] yields the entries of a table grouped by the top bits of their
+//! [`Istr::stable_hash`], one partition at a time. Because the hash is the same in every process,
+//! an external system partitioning its own keys by the same bits of XXH3 can join against the
+//! table partition by partition, holding only one partition of the table in memory at a time.
+use crate::{InternTable, Istr};
+
+/// The largest number of prefix bits [`InternTable::buckets`] accepts.
+pub const MAX_BITS: u32 = 16;
+
+impl InternTable {
+    /// Iterate over the table in `2^bits` partitions, in prefix order.
+    ///
+    /// Each partition is collected by a separate scan of the table under its read lock, so the
+    /// iteration is not a consistent snapshot: a string interned while it runs is yielded only
+    /// if its partition hasn't been collected yet.
+    ///
+    /// Panics if `bits` is greater than [`MAX_BITS`].
+    pub fn buckets(&self, bits: u32) -> Buckets<'_> {
+        assert!(
+            bits <= MAX_BITS,
+            "at most {} prefix bits are supported",
+            MAX_BITS
+        );
+        Buckets {
+            table: self,
+            bits,
+            next: 0,
+        }
+    }
+}
+
+/// The partitions of the global table. See [`InternTable::buckets`].
+#[cfg(feature = "global")]
+pub fn buckets(bits: u32) -> Buckets<'static> {
+    crate::active_table().buckets(bits)
+}
+
+/// The partition of a hash, its top `bits` bits.
+pub fn hash_prefix(hash: u64, bits: u32) -> u64 {
+    hash.checked_shr(64 - bits).unwrap_or(0)
+}
+
+/// One partition of a table.
+#[derive(Clone, Debug)]
+pub struct Bucket {
+    /// The top bits shared by the stable hashes of every entry.
+    pub prefix: u64,
+    pub entries: Vec<Istr>,
+}
+
+/// The iterator returned by [`InternTable::buckets`].
+#[derive(Debug)]
+pub struct Buckets<'a> {
+    table: &'a InternTable,
+    bits: u32,
+    next: u64,
+}
+
+impl Iterator for Buckets<'_> {
+    type Item = Bucket;
+
+    fn next(&mut self) -> Option<Bucket> {
+        if self.next >= 1 << self.bits {
+            return None;
+        }
+        let prefix = self.next;
+        self.next += 1;
+        let entries = self
+            .table
+            .iter()
+            .filter(|s| hash_prefix(s.stable_hash(), self.bits) == prefix)
+            .collect();
+        Some(Bucket { prefix, entries })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = ((1 << self.bits) - self.next) as usize;
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for Buckets<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stable_hash;
+
+    #[test]
+    fn test_buckets() {
+        let table = InternTable::new();
+        for i in 0..1000 {
+            table.get_or_intern(&format!("bucket-{}", i));
+        }
+        let buckets: Vec<Bucket> = table.buckets(3).collect();
+        assert_eq!(buckets.len(), 8);
+        assert_eq!(buckets.iter().map(|b| b.entries.len()).sum::<usize>(), 1000);
+        for bucket in &buckets {
+            for s in &bucket.entries {
+                assert_eq!(stable_hash(s.as_str()) >> 61, bucket.prefix);
+            }
+        }
+        let all: Vec<Bucket> = table.buckets(0).collect();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].entries.len(), 1000);
+    }
+}
@@ -0,0 +1,162 @@
+//! A cap on the memory of the global table, for services interning untrusted input.
+//!
+//! [`set_limit`] caps the bytes taken by the entries of the [`GLOBAL_TABLE`].
+//! Once a new string would take the table over the limit, [`Istr::try_new`] returns
+//! [`Error::OverBudget`] and [`Istr::new`] panics, while strings already interned are still
+//! found. [`CowIstr::new`](crate::CowIstr::new) falls back to an uninterned `Arc<str>` instead.
+//! A callback set with [`set_on_exceeded`] is asked first, and may let the string in anyway,
+//! e.g. after logging or raising the limit.
+//!
+//! ```
+//! use lsi::{budget, Istr};
+//!
+//! budget::set_limit(Some(budget::used()));
+//! assert!(Istr::try_new("doc over budget").is_err());
+//! budget::set_on_exceeded(Some(|overrun| overrun.len < 100));
+//! assert!(Istr::try_new("doc over budget").is_ok());
+//! budget::set_on_exceeded(None);
+//! budget::set_limit(None);
+//! ```
+//!
+//! [`set_max_len`] caps the length of each string instead, rejecting longer ones with
+//! [`Error::TooLong`] before they are even hashed, whether or not they are interned already:
+//!
+//! ```
+//! lsi::budget::set_max_len(Some(1024));
+//! assert!(matches!(
+//!     lsi::Istr::try_new(&"x".repeat(4096)),
+//!     Err(lsi::Error::TooLong(4096))
+//! ));
+//! lsi::budget::set_max_len(None);
+//! ```
+//!
+//! Every function interning into the active table is checked: [`Istr::new`] and its variants,
+//! [`try_intern`](crate::try_intern), the batches of [`Istr::intern_all`] and
+//! [`bulk_load_unique`](crate::bulk_load_unique), and the prefill of [`init`](crate::init).
+//! Strings interned into the table directly with [`InternTable`] methods aren't, but every
+//! entry counts towards [`used`]. Threads interning at the same moment may each see room for
+//! their string, so the limit can be overshot by a few entries.
+//!
+//! The budget only covers this copy's global table. After [`attach`](crate::attach)ing to the
+//! table of another copy of the crate, whose entries this copy doesn't count, the strings
+//! interned through this copy are only checked against the maximum length.
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+use crate::{Error, InternTable, InternedData, Istr, GLOBAL_TABLE};
+
+static LIMIT: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+static MAX_LEN: AtomicUsize = AtomicUsize::new(crate::MAX_LEN);
+
+/// The bytes of the entries of the global table.
+static USED: AtomicUsize = AtomicUsize::new(0);
+
+/// A callback deciding whether to intern a string over budget anyway, see [`set_on_exceeded`].
+pub type OnExceeded = fn(Overrun) -> bool;
+
+static ON_EXCEEDED: RwLock<Option<OnExceeded>> = RwLock::new(None);
+
+/// A new string that doesn't fit the budget, passed to the [`set_on_exceeded`] callback and
+/// returned in [`Error::OverBudget`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Overrun {
+    /// The length of the string.
+    pub len: usize,
+    /// The bytes in use when it was rejected.
+    pub used: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for Overrun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "interning a string of {} bytes would exceed the budget of {} bytes ({} in use)",
+            self.len, self.limit, self.used
+        )
+    }
+}
+
+/// Cap the bytes of the global table's entries, or lift the cap with `None`. Lowering the
+/// limit below [`used`] frees nothing, but rejects every new string.
+pub fn set_limit(bytes: Option<usize>) {
+    LIMIT.store(bytes.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+pub fn limit() -> Option<usize> {
+    Some(LIMIT.load(Ordering::Relaxed)).filter(|&limit| limit != usize::MAX)
+}
+
+/// The bytes taken by the entries of the global table, headers included.
+pub fn used() -> usize {
+    USED.load(Ordering::Relaxed)
+}
+
+/// Reject strings longer than `len` bytes, or only those longer than
+/// [`MAX_LEN`](crate::MAX_LEN) with `None`.
+pub fn set_max_len(len: Option<usize>) {
+    let len = len.map_or(crate::MAX_LEN, |len| len.min(crate::MAX_LEN));
+    MAX_LEN.store(len, Ordering::Relaxed);
+}
+
+/// The length set with [`set_max_len`].
+pub fn max_len() -> Option<usize> {
+    Some(MAX_LEN.load(Ordering::Relaxed)).filter(|&len| len != crate::MAX_LEN)
+}
+
+/// Call `f` for every new string that doesn't fit the budget, and intern it anyway if `f`
+/// returns `true`. `None` rejects them all, which is the default.
+pub fn set_on_exceeded(f: Option<OnExceeded>) {
+    *ON_EXCEEDED.write().unwrap() = f;
+}
+
+/// Check that a string of `len` bytes is no longer than the length set with [`set_max_len`].
+pub(crate) fn check_len(len: usize) -> Result<(), Error> {
+    if len > MAX_LEN.load(Ordering::Relaxed) {
+        return Err(Error::TooLong(len));
+    }
+    Ok(())
+}
+
+/// Whether new strings interned into `table` are checked with [`admit`]. Only the entries of
+/// this copy's [`GLOBAL_TABLE`] are counted in [`used`], so no other table is budgeted.
+pub(crate) fn is_limited(table: &InternTable) -> bool {
+    LIMIT.load(Ordering::Relaxed) != usize::MAX && std::ptr::eq(table, &GLOBAL_TABLE)
+}
+
+/// Check that a new string of `len` bytes fits the budget. Called before the table is locked,
+/// as the callback may intern.
+pub(crate) fn admit(len: usize) -> Result<(), Error> {
+    let used = used();
+    let limit = LIMIT.load(Ordering::Relaxed);
+    if used.saturating_add(entry_size(len)) <= limit {
+        return Ok(());
+    }
+    let overrun = Overrun { len, used, limit };
+    let on_exceeded = *ON_EXCEEDED.read().unwrap();
+    match on_exceeded {
+        Some(f) if f(overrun) => Ok(()),
+        _ => Err(Error::OverBudget(overrun)),
+    }
+}
+
+fn entry_size(len: usize) -> usize {
+    InternedData::layout_of(len).map_or(usize::MAX, |layout| layout.size())
+}
+
+/// Called when a string is inserted into the global table.
+pub(crate) fn inserted(s: Istr) {
+    USED.fetch_add(entry_size(s.len()), Ordering::Relaxed);
+}
+
+/// Called when a string is removed from the global table.
+pub(crate) fn removed(s: Istr) {
+    USED.fetch_sub(entry_size(s.len()), Ordering::Relaxed);
+}
+
+/// Called when the global table is reset.
+pub(crate) fn cleared() {
+    USED.store(0, Ordering::Relaxed);
+}
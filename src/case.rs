@@ -0,0 +1,117 @@
+//! Case-insensitive interning where the first spelling of a word becomes canonical.
+//!
+//! A [`CaseRegistry`] interns the first spelling it sees of each word, ignoring case, and
+//! returns that canonical handle for every later spelling, along with whether the spelling
+//! matched. This is what a linter for inconsistent identifier casing needs:
+//!
+//! ```
+//! use lsi::case::CaseRegistry;
+//! use lsi::InternTable;
+//!
+//! let table = InternTable::new();
+//! let registry = CaseRegistry::new(&table);
+//! assert!(registry.get_or_intern("userId").exact);
+//! let second = registry.get_or_intern("UserID");
+//! assert_eq!(second.canonical, "userId");
+//! assert!(!second.exact);
+//! ```
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::{InternTable, Istr};
+
+/// Case-insensitive interning into an [`InternTable`].
+///
+/// Spellings are compared by their [`str::to_lowercase`] form, which is not full Unicode case
+/// folding: "STRASSE" and "Straße" are different words. Only canonical spellings are
+/// interned; other spellings are never added to the table.
+#[derive(Debug)]
+pub struct CaseRegistry<'t> {
+    table: &'t InternTable,
+    /// Canonical spellings by lowercase form.
+    canonical: RwLock<HashMap<Box<str>, Istr>>,
+}
+
+/// The result of [`CaseRegistry::get_or_intern`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cased {
+    /// The first spelling interned for this word.
+    pub canonical: Istr,
+    /// Whether the spelling passed in is the canonical one.
+    pub exact: bool,
+}
+
+impl<'t> CaseRegistry<'t> {
+    pub fn new(table: &'t InternTable) -> Self {
+        CaseRegistry {
+            table,
+            canonical: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn get_or_intern(&self, s: &str) -> Cased {
+        let folded = s.to_lowercase();
+        if let Some(&canonical) = self.canonical.read().unwrap().get(folded.as_str()) {
+            return Cased::new(canonical, s);
+        }
+        // Interning under the registry lock is fine: the table lock is always taken second.
+        let mut lock = self.canonical.write().unwrap();
+        let canonical = *lock
+            .entry(folded.into_boxed_str())
+            .or_insert_with(|| self.table.get_or_intern(s));
+        Cased::new(canonical, s)
+    }
+
+    /// The canonical spelling of `s`, if any spelling of it has been interned.
+    pub fn canonical(&self, s: &str) -> Option<Istr> {
+        self.canonical
+            .read()
+            .unwrap()
+            .get(s.to_lowercase().as_str())
+            .copied()
+    }
+
+    /// The number of distinct words, ignoring case.
+    pub fn len(&self) -> usize {
+        self.canonical.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Cased {
+    fn new(canonical: Istr, s: &str) -> Self {
+        Cased {
+            canonical,
+            exact: canonical.as_str() == s,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_spelling_wins() {
+        let table = InternTable::new();
+        let registry = CaseRegistry::new(&table);
+        let first = registry.get_or_intern("HttpClient");
+        assert_eq!(first.canonical, "HttpClient");
+        assert!(first.exact);
+        for spelling in ["HTTPClient", "httpclient", "HttpClient"] {
+            let cased = registry.get_or_intern(spelling);
+            assert_eq!(cased.canonical.data_ptr(), first.canonical.data_ptr());
+            assert_eq!(cased.exact, spelling == "HttpClient");
+        }
+        assert_eq!(registry.canonical("HTTPCLIENT"), Some(first.canonical));
+        assert_eq!(registry.canonical("Straße"), None);
+        registry.get_or_intern("Straße");
+        assert_eq!(registry.get_or_intern("STRAßE").canonical, "Straße");
+        assert_eq!(registry.len(), 2);
+        // Variant spellings are never interned.
+        assert_eq!(table.len(), 2);
+    }
+}
@@ -0,0 +1,169 @@
+//! Rolling a table back to an earlier state, for fuzzing and snapshot tests.
+//!
+//! Interned strings are leaked, so a fuzzer that interns attacker-controlled strings on every
+//! iteration grows the table without bound, and a snapshot test's output can depend on which
+//! strings earlier tests happened to intern. [`InternTable::checkpoint`] records the current
+//! size of each shard of a table, and [`InternTable::restore`] removes and frees every string
+//! interned since.
+//!
+//! Restoring is only sound when nothing is still using the strings it frees, which in practice
+//! means single-threaded test harnesses that restore between cases.
+//!
+//! ```
+//! use lsi::InternTable;
+//!
+//! let table = InternTable::new();
+//! table.get_or_intern("kept");
+//! let checkpoint = table.checkpoint();
+//! for case in ["a", "b", "c"] {
+//!     table.get_or_intern(case);
+//!     // SAFETY: nothing interned during the case outlives it.
+//!     unsafe { table.restore(checkpoint) };
+//! }
+//! assert_eq!(table.len(), 1);
+//! ```
+use std::ptr::slice_from_raw_parts_mut;
+
+#[cfg(feature = "global")]
+use crate::active_table;
+use crate::hasher::TABLE_HASHER;
+use crate::{InternTable, InternedData, Istr, SHARDS};
+
+/// The size of a table at some point, returned by [`InternTable::checkpoint`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    table: *const InternTable,
+    /// The number of strings in each shard.
+    lens: [usize; SHARDS],
+}
+
+impl Checkpoint {
+    /// The number of strings in the table when the checkpoint was taken.
+    pub fn len(&self) -> usize {
+        self.lens.iter().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// The table pointer is only compared, never dereferenced.
+unsafe impl Send for Checkpoint {}
+
+unsafe impl Sync for Checkpoint {}
+
+impl InternTable {
+    /// Shards are measured one at a time, so strings interned concurrently by other threads
+    /// may or may not be covered by the checkpoint.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            table: self,
+            lens: std::array::from_fn(|i| self.read(i, |entries| entries.order.len())),
+        }
+    }
+
+    /// Remove and free every string interned since `checkpoint` was taken.
+    ///
+    /// Panics if `checkpoint` was taken from another table, or if the table has already been
+    /// restored to an earlier checkpoint.
+    ///
+    /// # Safety
+    ///
+    /// No handle to a string interned after `checkpoint` may be used afterwards, on any thread.
+    /// This includes handles cached by this crate, e.g. by [`istr!`](crate::istr) or an
+    /// [`AtomSet`](crate::atoms::AtomSet), if they were first used after the checkpoint.
+    /// Lookups that hit don't take the table's locks, so no lookup may run concurrently with
+    /// the restore.
+    pub unsafe fn restore(&self, checkpoint: Checkpoint) {
+        assert!(
+            std::ptr::eq(checkpoint.table, self),
+            "checkpoint belongs to another table"
+        );
+        #[cfg(feature = "counting-alloc")]
+        let _scope = crate::counting::Scope::enter();
+        let removed = self.write_all(|shards| {
+            let mut removed = Vec::new();
+            for (i, (entries, &len)) in shards.iter_mut().zip(&checkpoint.lens).enumerate() {
+                assert!(
+                    len <= entries.order.len(),
+                    "table was restored to an earlier checkpoint"
+                );
+                if len == entries.order.len() {
+                    continue;
+                }
+                let hasher = TABLE_HASHER;
+                for s in entries.order.split_off(len) {
+                    let hash = s.hash_in(&hasher);
+                    entries
+                        .set
+                        .raw_table_mut()
+                        .remove_entry(hash, |&(q, _)| q.0 == s.0);
+                    removed.push(s);
+                }
+                let kept = entries.order.iter().map(|s| (s.hash_in(&hasher), *s));
+                self.0[i].index.rebuild(kept);
+            }
+            #[cfg(feature = "hot-tier")]
+            self.1.clear(shards.iter_mut().map(|entries| &mut entries.hot_counts));
+            removed
+        });
+        self.freeing();
+        for s in removed {
+            self.removed(s);
+            free(s);
+        }
+    }
+}
+
+/// Checkpoint the global table.
+#[cfg(feature = "global")]
+pub fn checkpoint() -> Checkpoint {
+    active_table().checkpoint()
+}
+
+/// Restore the global table to `checkpoint`.
+///
+/// # Safety
+///
+/// See [`InternTable::restore`]. Strings interned by other threads, or by other tests running in
+/// parallel, are freed too.
+#[cfg(feature = "global")]
+pub unsafe fn restore(checkpoint: Checkpoint) {
+    active_table().restore(checkpoint)
+}
+
+/// Free the allocation behind `s`, which must have been created by [`InternedData::construct`],
+/// or by a table, whose entries have allocations of their own with this feature.
+unsafe fn free(s: Istr) {
+    let ptr = slice_from_raw_parts_mut(s.0.as_ptr() as *mut u8, s.len()) as *mut InternedData;
+    drop(Box::from_raw(ptr));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore() {
+        let table = InternTable::new();
+        let kept = table.get_or_intern("kept");
+        let checkpoint = table.checkpoint();
+        table.get_or_intern("dropped");
+        table.get_or_intern("also dropped");
+        unsafe { table.restore(checkpoint) };
+        assert_eq!(table.to_vec(), [kept]);
+        assert_eq!(table.get_or_intern("kept").0, kept.0);
+        table.get_or_intern("dropped");
+        assert_eq!(table.len(), 2);
+        unsafe { table.restore(checkpoint) };
+        assert_eq!(table.checkpoint(), checkpoint);
+    }
+
+    #[test]
+    #[should_panic(expected = "another table")]
+    fn test_restore_other_table() {
+        let checkpoint = InternTable::new().checkpoint();
+        unsafe { InternTable::new().restore(checkpoint) };
+    }
+}
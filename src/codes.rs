@@ -0,0 +1,138 @@
+//! Dense numeric codes for interned names, e.g. error codes.
+//!
+//! A [`CodeRegistry`] numbers names in the order they are first registered, so a service can
+//! log and transmit compact `u16` codes internally and render the interned names at the edges.
+//!
+//! ```
+//! use lsi::codes::CodeRegistry;
+//! use lsi::InternTable;
+//!
+//! static ERRORS: CodeRegistry = CodeRegistry::new();
+//!
+//! let table = InternTable::new();
+//! let timeout = ERRORS.register(table.get_or_intern("E_TIMEOUT")).unwrap();
+//! assert_eq!(ERRORS.register(table.get_or_intern("E_TIMEOUT")), Ok(timeout));
+//! assert_eq!(ERRORS.name(timeout).unwrap(), "E_TIMEOUT");
+//! ```
+use std::fmt;
+use std::sync::RwLock;
+
+use crate::{Istr, IstrMap};
+
+/// A two-way mapping between names and dense `u16` codes. The first registration of a name
+/// assigns its code for good.
+#[derive(Debug)]
+pub struct CodeRegistry {
+    state: RwLock<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    codes: Option<IstrMap<u16>>,
+    names: Vec<Istr>,
+}
+
+impl CodeRegistry {
+    pub const fn new() -> Self {
+        CodeRegistry {
+            state: RwLock::new(State {
+                codes: None,
+                names: Vec::new(),
+            }),
+        }
+    }
+
+    /// The code of `name`, assigning the next free one if it has none yet.
+    pub fn register(&self, name: Istr) -> Result<u16, CodesExhausted> {
+        if let Some(code) = self.code(name) {
+            return Ok(code);
+        }
+        let mut state = self.state.write().unwrap();
+        let State { codes, names } = &mut *state;
+        let codes = codes.get_or_insert_with(IstrMap::default);
+        if let Some(&code) = codes.get(&name) {
+            return Ok(code);
+        }
+        let code = u16::try_from(names.len()).map_err(|_| CodesExhausted)?;
+        codes.insert(name, code);
+        names.push(name);
+        Ok(code)
+    }
+
+    /// The code of `name`, if it has been registered.
+    pub fn code(&self, name: Istr) -> Option<u16> {
+        let state = self.state.read().unwrap();
+        state.codes.as_ref()?.get(&name).copied()
+    }
+
+    /// The name registered with `code`.
+    pub fn name(&self, code: u16) -> Option<Istr> {
+        self.state.read().unwrap().names.get(code as usize).copied()
+    }
+
+    /// Every registered name, in code order.
+    pub fn names(&self) -> Vec<Istr> {
+        self.state.read().unwrap().names.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.read().unwrap().names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for CodeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returned by [`CodeRegistry::register`] once all 65536 codes are assigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodesExhausted;
+
+impl fmt::Display for CodesExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("all 65536 codes are assigned")
+    }
+}
+
+impl std::error::Error for CodesExhausted {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InternTable;
+
+    #[test]
+    fn test_first_registration_wins() {
+        let table = InternTable::new();
+        let registry = CodeRegistry::new();
+        let [a, b] = ["E_A", "E_B"].map(|s| table.get_or_intern(s));
+        assert_eq!(registry.code(a), None);
+        assert_eq!(registry.register(b), Ok(0));
+        assert_eq!(registry.register(a), Ok(1));
+        assert_eq!(registry.register(b), Ok(0));
+        assert_eq!(registry.names(), [b, a]);
+        assert_eq!(registry.name(2), None);
+    }
+
+    #[test]
+    fn test_exhausted() {
+        let table = InternTable::new();
+        let registry = CodeRegistry::new();
+        for i in 0..=u16::MAX as u64 {
+            registry
+                .register(table.get_or_intern(&i.to_string()))
+                .unwrap();
+        }
+        assert_eq!(
+            registry.register(table.get_or_intern("one more")),
+            Err(CodesExhausted)
+        );
+        assert_eq!(registry.name(u16::MAX).unwrap(), "65535");
+    }
+}
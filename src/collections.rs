@@ -0,0 +1,477 @@
+//! Collections keyed by interned strings.
+//!
+//! [`IstrMap`] and [`IstrSet`] hash their keys with [`IstrHasher`], which hashes the handle's
+//! address rather than the string, so inserts and lookups cost the same however long the keys
+//! are, unless the `equivalent` feature makes `Istr` hash its string. Since they don't use
+//! hashbrown's default hasher, they are created with `default()` or `with_capacity_and_hasher`
+//! instead of `new()` or `with_capacity`.
+use std::fmt;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+
+use crate::{Istr, EMPTY_FAST_STR};
+
+/// A hash set of interned strings.
+pub type IstrSet = hashbrown::HashSet<Istr, BuildIstrHasher>;
+
+/// A hash map keyed by interned strings.
+pub type IstrMap<V> = hashbrown::HashMap<Istr, V, BuildIstrHasher>;
+
+/// An entry of an [`IstrMap`], from [`IstrMap::entry`](hashbrown::HashMap::entry).
+pub type IstrEntry<'a, V> = hashbrown::hash_map::Entry<'a, Istr, V, BuildIstrHasher>;
+
+/// Builds [`IstrHasher`]s.
+pub type BuildIstrHasher = BuildHasherDefault<IstrHasher>;
+
+/// A hasher for interned strings, which hash as their address.
+///
+/// An address is already unique, so the hasher only scrambles it with one multiplication,
+/// spreading its entropy to the high bits hashbrown probes with and away from the low bits,
+/// which alignment leaves zero. Other keys hash correctly, but slowly for byte strings: with
+/// the `equivalent` feature, `Istr` hashes its string, and is one of them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IstrHasher(u64);
+
+impl Hasher for IstrHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u64(b as u64);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = self.0.rotate_left(5) ^ n;
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0.wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(26)
+    }
+}
+
+/// A set of interned strings optimized for holding only a handful, like the attributes of a
+/// syntax node.
+///
+/// Up to `N` strings are stored inline, sorted by address, and membership is a linear scan of
+/// pointer comparisons, which beats hashing for such small sets. Inserting more than `N`
+/// strings moves them into an [`IstrSet`].
+///
+/// Members are compared by address, so they should all come from the same table.
+#[derive(Clone)]
+pub struct SmallSet<const N: usize = 4> {
+    repr: Repr<N>,
+}
+
+#[derive(Clone)]
+enum Repr<const N: usize> {
+    /// The first `len` items are the members, sorted by address. The rest are filler.
+    Inline {
+        len: usize,
+        items: [Istr; N],
+    },
+    Spilled(IstrSet),
+}
+
+impl<const N: usize> SmallSet<N> {
+    pub const fn new() -> Self {
+        SmallSet {
+            repr: Repr::Inline {
+                len: 0,
+                items: [EMPTY_FAST_STR; N],
+            },
+        }
+    }
+
+    /// Add `s`, returning whether it was newly inserted.
+    pub fn insert(&mut self, s: Istr) -> bool {
+        match &mut self.repr {
+            Repr::Inline { len, items } => match position(&items[..*len], s) {
+                Ok(_) => false,
+                Err(i) if *len < N => {
+                    items[i..=*len].rotate_right(1);
+                    items[i] = s;
+                    *len += 1;
+                    true
+                }
+                Err(_) => {
+                    let mut set: IstrSet = items.iter().copied().collect();
+                    set.insert(s);
+                    self.repr = Repr::Spilled(set);
+                    true
+                }
+            },
+            Repr::Spilled(set) => set.insert(s),
+        }
+    }
+
+    /// Remove `s`, returning whether it was a member.
+    pub fn remove(&mut self, s: Istr) -> bool {
+        match &mut self.repr {
+            Repr::Inline { len, items } => match position(&items[..*len], s) {
+                Ok(i) => {
+                    items[i..*len].rotate_left(1);
+                    *len -= 1;
+                    items[*len] = EMPTY_FAST_STR;
+                    true
+                }
+                Err(_) => false,
+            },
+            Repr::Spilled(set) => set.remove(&s),
+        }
+    }
+
+    pub fn contains(&self, s: Istr) -> bool {
+        match &self.repr {
+            Repr::Inline { len, items } => position(&items[..*len], s).is_ok(),
+            Repr::Spilled(set) => set.contains(&s),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.repr {
+            Repr::Inline { len, .. } => *len,
+            Repr::Spilled(set) => set.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the set has outgrown its inline storage.
+    pub fn is_spilled(&self) -> bool {
+        matches!(self.repr, Repr::Spilled(_))
+    }
+
+    /// The members, sorted by address while inline and in arbitrary order once spilled.
+    pub fn iter(&self) -> Iter<'_> {
+        match &self.repr {
+            Repr::Inline { len, items } => Iter::Inline(items[..*len].iter()),
+            Repr::Spilled(set) => Iter::Spilled(set.iter()),
+        }
+    }
+
+    pub fn into_set(self) -> IstrSet {
+        match self.repr {
+            Repr::Inline { len, items } => items[..len].iter().copied().collect(),
+            Repr::Spilled(set) => set,
+        }
+    }
+}
+
+/// Find `s` among address-sorted `items`, or where to insert it.
+fn position(items: &[Istr], s: Istr) -> Result<usize, usize> {
+    let addr = s.0.as_ptr() as usize;
+    for (i, item) in items.iter().enumerate() {
+        let item_addr = item.0.as_ptr() as usize;
+        if item_addr == addr {
+            return Ok(i);
+        }
+        if item_addr > addr {
+            return Err(i);
+        }
+    }
+    Err(items.len())
+}
+
+pub enum Iter<'a> {
+    Inline(std::slice::Iter<'a, Istr>),
+    Spilled(hashbrown::hash_set::Iter<'a, Istr>),
+}
+
+impl Iterator for Iter<'_> {
+    type Item = Istr;
+
+    fn next(&mut self) -> Option<Istr> {
+        match self {
+            Iter::Inline(it) => it.next().copied(),
+            Iter::Spilled(it) => it.next().copied(),
+        }
+    }
+}
+
+impl<const N: usize> Default for SmallSet<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Debug for SmallSet<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(self.iter().map(|s| s.as_str()))
+            .finish()
+    }
+}
+
+impl<const N: usize> PartialEq for SmallSet<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|s| other.contains(s))
+    }
+}
+
+impl<const N: usize> Eq for SmallSet<N> {}
+
+impl<const N: usize> Extend<Istr> for SmallSet<N> {
+    fn extend<I: IntoIterator<Item = Istr>>(&mut self, iter: I) {
+        for s in iter {
+            self.insert(s);
+        }
+    }
+}
+
+impl<const N: usize> FromIterator<Istr> for SmallSet<N> {
+    fn from_iter<I: IntoIterator<Item = Istr>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<const N: usize> From<SmallSet<N>> for IstrSet {
+    fn from(set: SmallSet<N>) -> IstrSet {
+        set.into_set()
+    }
+}
+
+/// A map from interned strings to any number of values, e.g. from a symbol to its definitions.
+///
+/// Keys are hashed by address rather than contents, and a key with a single value, the
+/// common case, stores it without a separate allocation.
+#[derive(Clone)]
+pub struct IstrMultiMap<V> {
+    map: hashbrown::HashMap<ByAddress, Values<V>, BuildIstrHasher>,
+}
+
+/// An interned string compared and hashed by address.
+#[derive(Clone, Copy)]
+struct ByAddress(Istr);
+
+impl PartialEq for ByAddress {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 .0 == other.0 .0
+    }
+}
+
+impl Eq for ByAddress {}
+
+impl Hash for ByAddress {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0 .0.hash(state)
+    }
+}
+
+#[derive(Clone)]
+enum Values<V> {
+    One(V),
+    Many(Vec<V>),
+}
+
+impl<V> Values<V> {
+    fn as_slice(&self) -> &[V] {
+        match self {
+            Values::One(v) => std::slice::from_ref(v),
+            Values::Many(vs) => vs,
+        }
+    }
+
+    fn push(&mut self, value: V) {
+        // `Vec::new` doesn't allocate, so the placeholder is free.
+        *self = match std::mem::replace(self, Values::Many(Vec::new())) {
+            Values::One(first) => Values::Many(vec![first, value]),
+            Values::Many(mut vs) => {
+                vs.push(value);
+                Values::Many(vs)
+            }
+        };
+    }
+}
+
+impl<V> IstrMultiMap<V> {
+    pub fn new() -> Self {
+        IstrMultiMap {
+            map: hashbrown::HashMap::default(),
+        }
+    }
+
+    /// Append `value` to the values of `key`.
+    pub fn insert(&mut self, key: Istr, value: V) {
+        match self.map.entry(ByAddress(key)) {
+            hashbrown::hash_map::Entry::Occupied(mut e) => e.get_mut().push(value),
+            hashbrown::hash_map::Entry::Vacant(e) => {
+                e.insert(Values::One(value));
+            }
+        }
+    }
+
+    /// The values of `key`, in insertion order. Empty if it has none.
+    pub fn get(&self, key: Istr) -> &[V] {
+        self.map
+            .get(&ByAddress(key))
+            .map_or(&[], |values| values.as_slice())
+    }
+
+    /// Remove `key`, returning its values.
+    pub fn remove(&mut self, key: Istr) -> Vec<V> {
+        match self.map.remove(&ByAddress(key)) {
+            Some(Values::One(v)) => vec![v],
+            Some(Values::Many(vs)) => vs,
+            None => Vec::new(),
+        }
+    }
+
+    pub fn contains_key(&self, key: Istr) -> bool {
+        self.map.contains_key(&ByAddress(key))
+    }
+
+    /// The number of keys.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Each key with its values, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (Istr, &[V])> + '_ {
+        self.map.iter().map(|(k, v)| (k.0, v.as_slice()))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = Istr> + '_ {
+        self.map.keys().map(|k| k.0)
+    }
+}
+
+/// Helpers for using a multimap as the adjacency list of a graph of named nodes.
+impl IstrMultiMap<Istr> {
+    /// Add an edge from `from` to `to`, returning whether it is new.
+    pub fn add_edge(&mut self, from: Istr, to: Istr) -> bool {
+        if self.get(from).iter().any(|n| n.0 == to.0) {
+            return false;
+        }
+        self.insert(from, to);
+        true
+    }
+
+    /// The nodes `node` has edges to.
+    pub fn neighbors(&self, node: Istr) -> &[Istr] {
+        self.get(node)
+    }
+
+    /// The graph with every edge reversed.
+    pub fn reversed(&self) -> Self {
+        let mut reversed = IstrMultiMap::new();
+        for (from, tos) in self.iter() {
+            for &to in tos {
+                reversed.insert(to, from);
+            }
+        }
+        reversed
+    }
+}
+
+impl<V> Default for IstrMultiMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for IstrMultiMap<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|(k, v)| (k.as_str(), v)))
+            .finish()
+    }
+}
+
+impl<V> Extend<(Istr, V)> for IstrMultiMap<V> {
+    fn extend<I: IntoIterator<Item = (Istr, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<V> FromIterator<(Istr, V)> for IstrMultiMap<V> {
+    fn from_iter<I: IntoIterator<Item = (Istr, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InternTable;
+    use std::hash::BuildHasher;
+
+    #[test]
+    fn test_small_set() {
+        let table = InternTable::new();
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|s| table.get_or_intern(s));
+        let mut set: SmallSet<2> = [c, a, c].into_iter().collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(a) && !set.contains(b));
+        let addrs: Vec<usize> = set.iter().map(|s| s.0.as_ptr() as usize).collect();
+        assert!(addrs[0] < addrs[1]);
+        assert!(set.remove(c));
+        assert!(!set.remove(c));
+        set.extend([b, c]);
+        assert!(set.is_spilled());
+        assert!(set.contains(c) && !set.contains(d));
+        assert_eq!(set.clone().into_set().len(), 3);
+        assert_eq!(set, [a, b, c].into_iter().collect());
+    }
+
+    #[test]
+    fn test_istr_map() {
+        let table = InternTable::new();
+        let keys: Vec<Istr> = (0..1000)
+            .map(|i| table.get_or_intern(&format!("key {}", i)))
+            .collect();
+        let mut map = IstrMap::default();
+        for (i, &key) in keys.iter().enumerate() {
+            map.insert(key, i);
+        }
+        match map.entry(keys[7]) {
+            IstrEntry::Occupied(e) => assert_eq!(*e.get(), 7),
+            IstrEntry::Vacant(_) => unreachable!(),
+        }
+        *map.entry(table.get_or_intern("new")).or_default() += 1;
+        assert_eq!(map.len(), 1001);
+        assert!(keys.iter().enumerate().all(|(i, key)| map[key] == i));
+        // Aligned addresses still spread over the high bits.
+        let top: IstrSet = keys[..64].iter().copied().collect();
+        let h2: std::collections::HashSet<u64> = top
+            .iter()
+            .map(|key| BuildIstrHasher::default().hash_one(key) >> 57)
+            .collect();
+        assert!(h2.len() > 16);
+    }
+
+    #[test]
+    fn test_multi_map() {
+        let table = InternTable::new();
+        let [f, g, h] = ["f", "g", "h"].map(|s| table.get_or_intern(s));
+        let mut defs: IstrMultiMap<u32> = [(f, 1), (g, 2), (f, 3)].into_iter().collect();
+        assert_eq!(defs.get(f), [1, 3]);
+        assert_eq!(defs.get(g), [2]);
+        assert!(defs.get(h).is_empty());
+        assert_eq!(defs.remove(f), [1, 3]);
+        assert_eq!(defs.len(), 1);
+
+        let mut calls = IstrMultiMap::new();
+        assert!(calls.add_edge(f, g));
+        assert!(calls.add_edge(f, h));
+        assert!(!calls.add_edge(f, g));
+        assert!(calls.add_edge(g, h));
+        let callers = calls.reversed();
+        let mut into_h: Vec<&str> = callers.neighbors(h).iter().map(|s| s.as_str()).collect();
+        into_h.sort_unstable();
+        assert_eq!(into_h, ["f", "g"]);
+    }
+}
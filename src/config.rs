@@ -0,0 +1,298 @@
+//! Loading configuration into a value tree of interned strings.
+//!
+//! [`Value`] deserializes from any self-describing serde format. Every map key is interned,
+//! as are string scalars of up to [`MAX_INTERNED_LEN`] bytes, so the keys and enum-like values
+//! that get compared all over an application are pointer-comparable from the moment the
+//! config is loaded. Longer strings, which are rarely compared, are kept as `String`s.
+//!
+//! The `json`, `toml` and `yaml` features add loaders for those formats. Other formats
+//! deserialize the same way through their own deserializers.
+//!
+//! ```
+//! use lsi::config::Value;
+//! use serde::de::value::{Error, MapDeserializer};
+//! use serde::Deserialize;
+//!
+//! let pairs = [("host", "localhost"), ("level", "debug")];
+//! let value = Value::deserialize(MapDeserializer::<_, Error>::new(pairs.into_iter())).unwrap();
+//! let level = value.get("level").unwrap();
+//! assert_eq!(level.as_istr(), Some(lsi::Istr::new("debug")));
+//! ```
+use std::fmt;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::{Error, Istr, IstrMap};
+
+/// String scalars up to this many bytes are interned.
+pub const MAX_INTERNED_LEN: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// A string of up to [`MAX_INTERNED_LEN`] bytes.
+    Str(Istr),
+    /// A longer string.
+    String(String),
+    Seq(Vec<Value>),
+    Map(IstrMap<Value>),
+}
+
+impl Value {
+    /// The value of `key`, if this is a map containing it.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            // A key that was never interned can't be in any map.
+            Value::Map(map) => map.get(&crate::active_table().get(key)?),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s.as_str()),
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The interned string, if this is a short string.
+    pub fn as_istr(&self) -> Option<Istr> {
+        match *self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Int(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// The number, if this is a float or an integer.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Float(f) => Some(f),
+            Value::Int(n) => Some(n as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_seq(&self) -> Option<&[Value]> {
+        match self {
+            Value::Seq(seq) => Some(seq),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&IstrMap<Value>> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// `s` interned, unless it is long, failing with the error of [`Istr::try_new`] if it
+    /// can't be interned.
+    fn string(s: &str) -> Result<Value, Error> {
+        if s.len() <= MAX_INTERNED_LEN {
+            Istr::try_new(s).map(Value::Str)
+        } else {
+            Ok(Value::String(s.to_owned()))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor { toml: false })
+    }
+}
+
+/// Deserializes a [`Value`], taking the dates and times of the TOML deserializer apart from
+/// other maps if `toml` is set.
+#[derive(Clone, Copy)]
+struct ValueVisitor {
+    toml: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a configuration value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        DeserializeSeed::deserialize(self, deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        i64::try_from(v)
+            .map(Value::Int)
+            .map_err(|_| E::custom(format_args!("integer {} is out of range", v)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Value::string(v).map_err(E::custom)
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Value, E> {
+        if v.len() <= MAX_INTERNED_LEN {
+            Istr::try_new(&v).map(Value::Str).map_err(E::custom)
+        } else {
+            Ok(Value::String(v))
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element_seed(self)? {
+            values.push(value);
+        }
+        Ok(Value::Seq(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let capacity = map.size_hint().unwrap_or(0);
+        let mut values = IstrMap::with_capacity_and_hasher(capacity, Default::default());
+        while let Some(key) = map.next_key::<Istr>()? {
+            let value = map.next_value_seed(self)?;
+            // The TOML deserializer hands out dates and times as a map with one magic key.
+            if self.toml && key.as_str() == "$__toml_private_datetime" {
+                return Ok(value);
+            }
+            values.insert(key, value);
+        }
+        Ok(Value::Map(values))
+    }
+}
+
+/// Load a JSON document, interning its keys and short strings.
+#[cfg(feature = "json")]
+pub fn load_json(reader: impl std::io::Read) -> serde_json::Result<Value> {
+    serde_json::from_reader(reader)
+}
+
+/// Load a TOML document, interning its keys and short strings. Dates and times become strings.
+#[cfg(feature = "toml")]
+pub fn load_toml(s: &str) -> Result<Value, toml::de::Error> {
+    ValueVisitor { toml: true }.deserialize(toml::Deserializer::new(s))
+}
+
+/// Load a YAML document, interning its keys and short strings.
+#[cfg(feature = "yaml")]
+pub fn load_yaml(reader: impl std::io::Read) -> serde_yaml::Result<Value> {
+    serde_yaml::from_reader(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        use serde::de::value::{Error, SeqDeserializer};
+        let seq = SeqDeserializer::<_, Error>::new([1u64, u64::MAX].into_iter());
+        assert!(Value::deserialize(seq).is_err());
+        let seq = SeqDeserializer::<_, Error>::new(["on", "off"].into_iter());
+        assert_eq!(
+            Value::deserialize(seq).unwrap().as_seq().unwrap()[1],
+            Value::Str(Istr::new("off"))
+        );
+        // Only TOML dates and times are taken apart from other maps.
+        use serde::de::value::MapDeserializer;
+        let pairs = [("$__toml_private_datetime", "1979-05-27")];
+        let map = MapDeserializer::<_, Error>::new(pairs.into_iter());
+        assert!(Value::deserialize(map).unwrap().as_map().is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_load_json() {
+        let long = "x".repeat(MAX_INTERNED_LEN + 1);
+        let doc = format!(
+            r#"{{"name": "api", "port": 8080, "ratio": 0.5, "debug": false, "tags": ["a", null], "motd": "{}"}}"#,
+            long
+        );
+        let value = load_json(doc.as_bytes()).unwrap();
+        assert_eq!(value.get("name").unwrap().as_istr(), Some(Istr::new("api")));
+        assert_eq!(value.get("port").unwrap().as_i64(), Some(8080));
+        assert_eq!(value.get("ratio").unwrap().as_f64(), Some(0.5));
+        assert_eq!(value.get("debug").unwrap().as_bool(), Some(false));
+        assert_eq!(
+            value.get("tags").unwrap().as_seq().unwrap(),
+            [Value::Str(Istr::new("a")), Value::Null]
+        );
+        let motd = value.get("motd").unwrap();
+        assert_eq!(motd, &Value::String(long.clone()));
+        assert_eq!(motd.as_str(), Some(long.as_str()));
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn test_load_toml() {
+        let doc = "name = \"api\"\nstarted = 1979-05-27T07:32:00Z\n[server]\nport = 8080\n";
+        let value = load_toml(doc).unwrap();
+        assert_eq!(value.get("name").unwrap().as_istr(), Some(Istr::new("api")));
+        assert_eq!(
+            value.get("started").unwrap().as_str(),
+            Some("1979-05-27T07:32:00Z")
+        );
+        let server = value.get("server").unwrap();
+        assert_eq!(server.get("port").unwrap().as_i64(), Some(8080));
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_load_yaml() {
+        let doc = "name: api\nlevels: [debug, info]\nport: 8080\n";
+        let value = load_yaml(doc.as_bytes()).unwrap();
+        assert_eq!(value.get("name").unwrap().as_istr(), Some(Istr::new("api")));
+        assert_eq!(
+            value.get("levels").unwrap().as_seq().unwrap(),
+            [Value::Str(Istr::new("debug")), Value::Str(Istr::new("info"))]
+        );
+        assert_eq!(value.get("port").unwrap().as_i64(), Some(8080));
+    }
+}
@@ -0,0 +1,247 @@
+//! Interning values that aren't strings yet, without formatting them into a `String` first.
+use std::collections::HashMap;
+use std::fmt::{self, Display, Write};
+use std::sync::{OnceLock, RwLock};
+
+use crate::{active_table, budget, check, check_on_miss, Checked, Error, InternTable, Istr, EMPTY_FAST_STR};
+
+/// Every character of the Latin-1 range, U+0000 to U+00FF, interned on first use.
+static LATIN1: OnceLock<Box<[Istr]>> = OnceLock::new();
+
+const SMALL_INTS: u64 = 1024;
+
+/// The integers below `SMALL_INTS`, interned on first use.
+static SMALL: OnceLock<Box<[Istr]>> = OnceLock::new();
+
+/// The interned copies of the slices passed to `intern_table`, keyed by address and length.
+static TABLES: RwLock<Option<HashMap<SliceKey, &'static [Istr]>>> = RwLock::new(None);
+
+type SliceKey = (usize, usize);
+
+/// Intern every string of a static slice, returning the handles in the same order.
+///
+/// The result is cached by the address and length of `strings`, so calling this repeatedly
+/// with the same slice, e.g. from a function that builds a list of CLI options, interns it
+/// once and leaks one slice of handles.
+///
+/// ```
+/// static LEVELS: &[&str] = &["error", "warn", "info"];
+/// let levels = lsi::intern_table(LEVELS);
+/// assert_eq!(levels[1], "warn");
+/// assert!(std::ptr::eq(levels, lsi::intern_table(LEVELS)));
+/// ```
+pub fn intern_table(strings: &'static [&'static str]) -> &'static [Istr] {
+    let key = (strings.as_ptr() as usize, strings.len());
+    if let Some(&table) = TABLES.read().unwrap().as_ref().and_then(|m| m.get(&key)) {
+        return table;
+    }
+    let table: Vec<Istr> = strings.iter().map(|s| Istr::new(s)).collect();
+    let mut lock = TABLES.write().unwrap();
+    lock.get_or_insert_with(HashMap::new)
+        .entry(key)
+        .or_insert_with(|| Box::leak(table.into_boxed_slice()))
+}
+
+/// Intern every string of `strings` into the active table in one batch, see
+/// [`InternTable::get_or_intern_many`](crate::InternTable::get_or_intern_many). Fails with the
+/// error of [`Istr::try_new`] for the first string that can't be interned.
+///
+/// Entries store their bytes inline, so the `String` buffers can't be reused and are freed.
+pub fn intern_vec(strings: Vec<String>) -> Result<Vec<Istr>, Error> {
+    intern_many(strings, false)
+}
+
+/// Intern a batch of strings that are expected to be new into the active table, see
+/// [`InternTable::bulk_load_unique`](crate::InternTable::bulk_load_unique). Fails as
+/// [`intern_vec`] does.
+pub fn bulk_load_unique<S: AsRef<str>>(
+    strings: impl IntoIterator<Item = S>,
+) -> Result<Vec<Istr>, Error> {
+    intern_many(strings.into_iter().collect(), true)
+}
+
+/// Intern `strings` into the active table, each [checked](crate::check) as by
+/// [`Istr::try_new`], in one batch unless there is a budget. Strings interned before one that
+/// fails stay interned. A string missing from a table frozen with
+/// [`OnMiss::Panic`](crate::frozen::OnMiss::Panic) panics, as in [`Istr::new`].
+pub(crate) fn intern_many<S: AsRef<str>>(
+    strings: Vec<S>,
+    reserve: bool,
+) -> Result<Vec<Istr>, Error> {
+    let table = active_table();
+    if budget::is_limited(table) {
+        // Each new string has to be admitted against the bytes of those before it.
+        return strings
+            .iter()
+            .map(|s| Istr::try_new(s.as_ref()).map_err(|e| check_on_miss(s.as_ref(), e)))
+            .collect();
+    }
+    let mut handles = vec![EMPTY_FAST_STR; strings.len()];
+    let mut new = Vec::new();
+    for (i, s) in strings.iter().enumerate() {
+        let s = s.as_ref();
+        if s.is_empty() {
+            continue;
+        }
+        match check(table, s, InternTable::hash(s), true).map_err(|e| check_on_miss(s, e))? {
+            Checked::Found(fast_str) => handles[i] = fast_str,
+            Checked::Insert(s, _) => new.push((i, s)),
+            Checked::Contended => unreachable!("waited for the lock"),
+        }
+    }
+    let (at, new): (Vec<usize>, Vec<_>) = new.into_iter().unzip();
+    let inserted = if reserve {
+        table.bulk_load_unique(new)
+    } else {
+        table.get_or_intern_many(new)
+    };
+    for (i, fast_str) in at.into_iter().zip(inserted) {
+        handles[i] = fast_str;
+    }
+    Ok(handles)
+}
+
+/// The strings of `handles`, in the same order.
+pub fn resolve_vec(handles: &[Istr]) -> Vec<&'static str> {
+    handles.iter().map(|s| s.as_str()).collect()
+}
+
+impl Istr {
+    /// Intern every string of `strings` into the active table in one batch, returning the
+    /// handles in the same order. See
+    /// [`InternTable::get_or_intern_many`](crate::InternTable::get_or_intern_many).
+    ///
+    /// ```
+    /// use lsi::Istr;
+    ///
+    /// let words = Istr::intern_all(&["let", "x", "=", "x"]).unwrap();
+    /// assert_eq!(words[1], words[3]);
+    /// ```
+    ///
+    /// Fails with the error of [`Istr::try_new`] for the first string that can't be interned.
+    pub fn intern_all<S: AsRef<str>>(strings: &[S]) -> Result<Vec<Istr>, Error> {
+        intern_many(strings.iter().collect(), false)
+    }
+
+    /// [`Istr::intern_all`] for strings that aren't in a slice yet, e.g. the lines of a file.
+    pub fn intern_iter<S: AsRef<str>>(
+        strings: impl IntoIterator<Item = S>,
+    ) -> Result<Vec<Istr>, Error> {
+        intern_many(strings.into_iter().collect(), false)
+    }
+
+    /// Intern the one-character string `c`.
+    ///
+    /// Latin-1 characters are looked up in a table that is filled once, so converting them
+    /// costs an array index rather than a table lookup.
+    pub fn from_char(c: char) -> Istr {
+        if let Some(latin1) = u8::try_from(c).ok().map(usize::from) {
+            return LATIN1.get_or_init(|| {
+                (0..=u8::MAX)
+                    .map(|b| Istr::new(char::from(b).encode_utf8(&mut [0; 4])))
+                    .collect()
+            })[latin1];
+        }
+        Istr::new(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Intern the decimal representation of `n`.
+    ///
+    /// Integers below 1024 are looked up in a table that is filled once; larger ones are
+    /// formatted on the stack.
+    pub fn from_int(n: u64) -> Istr {
+        if n < SMALL_INTS {
+            return SMALL.get_or_init(|| {
+                let mut buf = itoa::Buffer::new();
+                (0..SMALL_INTS).map(|i| Istr::new(buf.format(i))).collect()
+            })[n as usize];
+        }
+        Istr::new(itoa::Buffer::new().format(n))
+    }
+
+    /// Intern the `Display` representation of `value`.
+    ///
+    /// Representations of up to 128 bytes are formatted on the stack, so interning a value
+    /// that is already in the table doesn't allocate.
+    pub fn from_display(value: impl Display) -> Istr {
+        let mut buf = StackBuf {
+            buf: [0; 128],
+            len: 0,
+        };
+        if write!(buf, "{}", value).is_ok() {
+            // `StackBuf` only ever holds whole `&str`s.
+            return Istr::new(std::str::from_utf8(&buf.buf[..buf.len]).unwrap());
+        }
+        Istr::new(&value.to_string())
+    }
+}
+
+/// A fixed-size formatting buffer that fails once it is full.
+struct StackBuf {
+    buf: [u8; 128],
+    len: usize,
+}
+
+impl fmt::Write for StackBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_char() {
+        for c in ['a', '\0', 'é', 'ÿ', 'Ā', '字', '🦀'] {
+            assert_eq!(Istr::from_char(c).as_str(), c.to_string());
+            assert_eq!(Istr::from_char(c), Istr::new(&c.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_intern_table() {
+        static NAMES: &[&str] = &["alpha", "beta", "alpha"];
+        let table = intern_table(NAMES);
+        assert_eq!(table, ["alpha", "beta", "alpha"].map(Istr::new));
+        assert!(std::ptr::eq(table, intern_table(NAMES)));
+        assert_eq!(intern_table(&NAMES[1..]), [Istr::new("beta"), Istr::new("alpha")]);
+    }
+
+    #[test]
+    fn test_intern_vec() {
+        let strings = vec!["vec-a".to_owned(), String::new(), "vec-a".to_owned()];
+        let handles = intern_vec(strings).unwrap();
+        assert_eq!(handles, [Istr::new("vec-a"), Istr::new(""), Istr::new("vec-a")]);
+        assert!(handles[1].is_empty());
+        assert_eq!(resolve_vec(&handles), ["vec-a", "", "vec-a"]);
+    }
+
+    #[test]
+    fn test_intern_all() {
+        let handles = Istr::intern_all(&["all-a", "", "all-b", "all-a"]).unwrap();
+        assert_eq!(resolve_vec(&handles), ["all-a", "", "all-b", "all-a"]);
+        assert_eq!(handles[0], handles[3]);
+        assert_eq!(Istr::intern_all(&handles).unwrap(), handles);
+        let text = "all-b\nall-c\nall-a\n";
+        let lines = Istr::intern_iter(text.lines()).unwrap();
+        assert_eq!(lines, [handles[2], Istr::new("all-c"), handles[0]]);
+    }
+
+    #[test]
+    fn test_from_int() {
+        for n in [0, 7, 1023, 1024, u64::MAX] {
+            assert_eq!(Istr::from_int(n), Istr::new(&n.to_string()));
+        }
+        assert_eq!(Istr::from_display(format_args!("shard-{}", 17)), "shard-17");
+        let long = "x".repeat(200);
+        assert_eq!(Istr::from_display(&long).as_str(), long);
+    }
+}
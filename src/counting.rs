@@ -0,0 +1,176 @@
+//! Attributing heap usage to the interner.
+//!
+//! [`LsiCountingAlloc`] wraps a global allocator and counts the bytes allocated and freed while
+//! a thread is inside an interning call, i.e. the entries themselves and the growth of the
+//! tables holding them. It gives a process an accurate "the interner owns this many bytes"
+//! number without an external heap profiler.
+//!
+//! ```ignore
+//! use std::alloc::System;
+//! use lsi::counting::LsiCountingAlloc;
+//!
+//! #[global_allocator]
+//! static ALLOC: LsiCountingAlloc = LsiCountingAlloc::new(System);
+//!
+//! fn report() {
+//!     println!("interner owns {} bytes", ALLOC.interner_bytes());
+//! }
+//! ```
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+thread_local! {
+    /// Whether this thread is inside an interning call.
+    static INTERNING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the current thread as interning for as long as it is alive.
+pub(crate) struct Scope {
+    prev: bool,
+}
+
+impl Scope {
+    pub(crate) fn enter() -> Self {
+        Scope {
+            prev: INTERNING.with(|i| i.replace(true)),
+        }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        INTERNING.with(|i| i.set(self.prev));
+    }
+}
+
+fn interning() -> bool {
+    // The flag has no destructor, but be safe during thread teardown.
+    INTERNING.try_with(|i| i.get()).unwrap_or(false)
+}
+
+/// A global allocator wrapper counting the memory allocated by interning.
+#[derive(Debug)]
+pub struct LsiCountingAlloc<A = System> {
+    inner: A,
+    allocated: AtomicUsize,
+    freed: AtomicUsize,
+    allocations: AtomicUsize,
+}
+
+/// Counters of a [`LsiCountingAlloc`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Bytes allocated while interning.
+    pub allocated: usize,
+    /// Bytes freed while interning, e.g. by a table growing out of its old storage.
+    pub freed: usize,
+    /// The number of allocations made while interning.
+    pub allocations: usize,
+}
+
+impl AllocStats {
+    /// The bytes currently owned by the interner.
+    pub fn live(&self) -> usize {
+        self.allocated.saturating_sub(self.freed)
+    }
+}
+
+impl<A> LsiCountingAlloc<A> {
+    pub const fn new(inner: A) -> Self {
+        LsiCountingAlloc {
+            inner,
+            allocated: AtomicUsize::new(0),
+            freed: AtomicUsize::new(0),
+            allocations: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> AllocStats {
+        AllocStats {
+            allocated: self.allocated.load(Ordering::Relaxed),
+            freed: self.freed.load(Ordering::Relaxed),
+            allocations: self.allocations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The bytes currently owned by the interner, see [`AllocStats::live`].
+    pub fn interner_bytes(&self) -> usize {
+        self.stats().live()
+    }
+
+    fn count_alloc(&self, size: usize) {
+        if interning() {
+            self.allocated.fetch_add(size, Ordering::Relaxed);
+            self.allocations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn count_dealloc(&self, size: usize) {
+        if interning() {
+            self.freed.fetch_add(size, Ordering::Relaxed);
+        }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for LsiCountingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            self.count_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            self.count_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        self.count_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = self.inner.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            self.count_dealloc(layout.size());
+            self.count_alloc(new_size);
+        }
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_counts_while_interning() {
+        let alloc = LsiCountingAlloc::new(System);
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        unsafe {
+            let outside = alloc.alloc(layout);
+            let inside = {
+                let _scope = Scope::enter();
+                alloc.alloc(layout)
+            };
+            assert_eq!(alloc.interner_bytes(), 64);
+            alloc.dealloc(outside, layout);
+            let _scope = Scope::enter();
+            alloc.dealloc(inside, layout);
+        }
+        assert_eq!(
+            alloc.stats(),
+            AllocStats {
+                allocated: 64,
+                freed: 64,
+                allocations: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::{InternTable, Istr};
+
+static THRESHOLD: AtomicUsize = AtomicUsize::new(CowIstr::DEFAULT_THRESHOLD);
+
+/// A string interned if it is short, or reference-counted if it is long.
+///
+/// Interned strings are never freed, which is the right trade for names and keys but not for
+/// the occasional 10 MB request body. [`CowIstr::new`] interns strings of up to
+/// [`CowIstr::threshold`] bytes and stores longer ones in an `Arc<str>`, freed when the last
+/// clone is dropped.
+///
+/// Equality and hashing are by string contents, like [`MaybeInterned`](crate::MaybeInterned),
+/// so values compare equal whichever way they were stored. Two interned values are compared
+/// by address first.
+///
+/// ```
+/// use lsi::{CowIstr, InternTable};
+///
+/// let table = InternTable::new();
+/// let short = CowIstr::new_in(&table, "content-type");
+/// let long = CowIstr::new_in(&table, &"x".repeat(CowIstr::threshold() + 1));
+/// assert!(short.is_interned() && !long.is_interned());
+/// assert_eq!(table.len(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub enum CowIstr {
+    Interned(Istr),
+    Shared(Arc<str>),
+}
+
+impl CowIstr {
+    /// The threshold until [`CowIstr::set_threshold`] is called.
+    pub const DEFAULT_THRESHOLD: usize = 4096;
+
+    /// Intern `s` into the active table if it is no longer than the threshold, otherwise copy
+    /// it into an `Arc<str>`. Strings that don't fit the [budget](crate::budget) are copied
+    /// too.
+    #[cfg(feature = "global")]
+    pub fn new(s: &str) -> Self {
+        if s.len() > Self::threshold() {
+            return CowIstr::Shared(s.into());
+        }
+        match Istr::try_new(s) {
+            Ok(fast_str) => CowIstr::Interned(fast_str),
+            Err(_) => CowIstr::Shared(s.into()),
+        }
+    }
+
+    /// Like [`CowIstr::new`], interning into `table`.
+    pub fn new_in(table: &InternTable, s: &str) -> Self {
+        if s.len() <= Self::threshold() {
+            CowIstr::Interned(table.get_or_intern(s))
+        } else {
+            CowIstr::Shared(s.into())
+        }
+    }
+
+    /// The length in bytes of the longest strings [`CowIstr::new`] interns.
+    pub fn threshold() -> usize {
+        THRESHOLD.load(Ordering::Relaxed)
+    }
+
+    /// Change the threshold for the whole process. Values created earlier keep their storage.
+    pub fn set_threshold(len: usize) {
+        THRESHOLD.store(len, Ordering::Relaxed);
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            CowIstr::Interned(s) => s.as_str(),
+            CowIstr::Shared(s) => s,
+        }
+    }
+
+    pub fn is_interned(&self) -> bool {
+        matches!(self, CowIstr::Interned(_))
+    }
+
+    pub fn interned(&self) -> Option<Istr> {
+        match self {
+            CowIstr::Interned(s) => Some(*s),
+            CowIstr::Shared(_) => None,
+        }
+    }
+}
+
+impl Deref for CowIstr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for CowIstr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<Istr> for CowIstr {
+    fn from(s: Istr) -> Self {
+        CowIstr::Interned(s)
+    }
+}
+
+impl From<Arc<str>> for CowIstr {
+    fn from(s: Arc<str>) -> Self {
+        CowIstr::Shared(s)
+    }
+}
+
+impl PartialEq for CowIstr {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (CowIstr::Interned(a), CowIstr::Interned(b)) if a == b => true,
+            (CowIstr::Shared(a), CowIstr::Shared(b)) if Arc::ptr_eq(a, b) => true,
+            _ => self.as_str() == other.as_str(),
+        }
+    }
+}
+
+impl PartialEq<&str> for CowIstr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Eq for CowIstr {}
+
+impl Hash for CowIstr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Display for CowIstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_cow_istr() {
+        let table = InternTable::new();
+        let long = "y".repeat(CowIstr::threshold() + 1);
+        let a = CowIstr::new_in(&table, &long);
+        let b = CowIstr::new_in(&table, &long);
+        assert!(!a.is_interned());
+        assert_eq!(a, b);
+        assert_eq!(a.interned(), None);
+        let interned = CowIstr::from(table.get_or_intern(&long));
+        assert_eq!(a, interned);
+        let short = CowIstr::new_in(&table, "short");
+        assert_eq!(short.interned(), Some(table.get_or_intern("short")));
+        assert_eq!(short, "short");
+        let set: HashSet<CowIstr> = [a, b, interned, short].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+}
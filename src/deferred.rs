@@ -0,0 +1,77 @@
+//! Deferred interning for write-heavy bursts.
+//!
+//! [`intern_deferred`] never inserts on the calling thread. Strings already in the table are
+//! returned interned; misses are queued to a background interner thread and the caller gets
+//! the borrowed string back immediately, together with a [`ResolveLater`] handle that yields
+//! the [`Istr`] once the background thread has processed it, or the error of [`Istr::try_new`]
+//! if the string can't be interned. This keeps the latency of the
+//! calling thread flat while the vocabulary is growing quickly.
+use std::fmt;
+use std::sync::Arc;
+
+use crate::worker::{self, Slot};
+use crate::{active_table, Error, Istr, MaybeInterned, EMPTY_FAST_STR};
+
+/// Look up `s`, queueing it for background interning on a miss.
+///
+/// Returns the provisional value, and on a miss, a handle that upgrades it to an [`Istr`].
+pub fn intern_deferred(s: &str) -> (MaybeInterned<'_>, Option<ResolveLater>) {
+    if s.is_empty() {
+        return (MaybeInterned::Interned(EMPTY_FAST_STR), None);
+    }
+    match active_table().try_get(s) {
+        Some(Some(fast_str)) => (MaybeInterned::Interned(fast_str), None),
+        // A contended lock is treated like a miss: the worker will sort it out.
+        Some(None) | None => (
+            MaybeInterned::Borrowed(s),
+            Some(ResolveLater(worker::submit(s))),
+        ),
+    }
+}
+
+/// A string queued for background interning.
+#[derive(Clone)]
+pub struct ResolveLater(Arc<Slot>);
+
+impl ResolveLater {
+    /// The interned string, or the error of [`Istr::try_new`], if the background thread has
+    /// processed it.
+    pub fn try_resolve(&self) -> Option<Result<Istr, Error>> {
+        self.0.get()
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.try_resolve().is_some()
+    }
+
+    /// Block until the background thread has interned the string, returning the error of
+    /// [`Istr::try_new`] if it couldn't.
+    pub fn wait(&self) -> Result<Istr, Error> {
+        self.0.wait()
+    }
+}
+
+impl fmt::Debug for ResolveLater {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ResolveLater")
+            .field(&self.try_resolve())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_deferred() {
+        let (value, handle) = intern_deferred("deferred miss");
+        assert!(!value.is_interned());
+        let resolved = handle.unwrap().wait().unwrap();
+        assert_eq!(resolved, value.as_str());
+
+        let (value, handle) = intern_deferred("deferred miss");
+        assert!(handle.is_none());
+        assert_eq!(value.interned(), Some(resolved));
+    }
+}
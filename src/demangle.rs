@@ -0,0 +1,152 @@
+//! A memoizing symbol demangler.
+//!
+//! Profilers and debuggers see the same mangled symbols over and over. [`DemangleCache`] interns
+//! the mangled name, demangles it once, and remembers the interned demangled form, so repeated
+//! lookups are a single hash map probe.
+//!
+//! Rust symbols are demangled with `rustc-demangle` and C++ symbols with `cpp_demangle`,
+//! depending on which of those features are enabled. Names that aren't recognized by either
+//! demangler are returned as-is.
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::Istr;
+
+/// The cache used by the free functions in this module.
+pub static GLOBAL_CACHE: DemangleCache = DemangleCache::new();
+
+#[derive(Debug)]
+pub struct DemangleCache(RwLock<Option<HashMap<Istr, Istr>>>);
+
+impl DemangleCache {
+    pub const fn new() -> Self {
+        DemangleCache(RwLock::new(None))
+    }
+
+    /// Demangle a symbol, returning the interned demangled name.
+    pub fn demangle(&self, mangled: &str) -> Istr {
+        self.demangle_istr(Istr::new(mangled))
+    }
+
+    pub fn demangle_istr(&self, mangled: Istr) -> Istr {
+        if let Some(&demangled) = self
+            .0
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|m| m.get(&mangled))
+        {
+            return demangled;
+        }
+        // Demangling is comparatively slow, so do it before taking the write lock.
+        let demangled = demangle_uncached(mangled);
+        *self
+            .0
+            .write()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .entry(mangled)
+            .or_insert(demangled)
+    }
+
+    /// Demangle a batch of symbols. The cache is probed under a single read lock, and all misses
+    /// are inserted under a single write lock.
+    pub fn demangle_all<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Vec<Istr> {
+        let mangled: Vec<Istr> = names.into_iter().map(Istr::new).collect();
+        let mut out = Vec::with_capacity(mangled.len());
+        let mut misses = Vec::new();
+        {
+            let lock = self.0.read().unwrap();
+            for (i, &m) in mangled.iter().enumerate() {
+                match lock.as_ref().and_then(|map| map.get(&m)) {
+                    Some(&d) => out.push(d),
+                    None => {
+                        out.push(m);
+                        misses.push(i);
+                    }
+                }
+            }
+        }
+        if misses.is_empty() {
+            return out;
+        }
+        let demangled: Vec<Istr> = misses
+            .iter()
+            .map(|&i| demangle_uncached(mangled[i]))
+            .collect();
+        let mut lock = self.0.write().unwrap();
+        let map = lock.get_or_insert_with(HashMap::new);
+        for (&i, d) in misses.iter().zip(demangled) {
+            out[i] = *map.entry(mangled[i]).or_insert(d);
+        }
+        out
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.read().unwrap().as_ref().map_or(0, |m| m.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for DemangleCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Demangle a symbol using [`GLOBAL_CACHE`].
+pub fn demangle(mangled: &str) -> Istr {
+    GLOBAL_CACHE.demangle(mangled)
+}
+
+/// Demangle a batch of symbols using [`GLOBAL_CACHE`].
+pub fn demangle_all<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<Istr> {
+    GLOBAL_CACHE.demangle_all(names)
+}
+
+fn demangle_uncached(mangled: Istr) -> Istr {
+    #[cfg(feature = "rustc-demangle")]
+    if let Ok(d) = rustc_demangle::try_demangle(mangled.as_str()) {
+        // The alternate format omits the trailing hash, e.g. `::h5f3a0b1c2d3e4f56`.
+        return Istr::new(&format!("{:#}", d));
+    }
+    #[cfg(feature = "cpp_demangle")]
+    if let Ok(sym) = cpp_demangle::Symbol::new(mangled.as_str()) {
+        if let Ok(d) = sym.demangle() {
+            return Istr::new(&d);
+        }
+    }
+    mangled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demangle_cached() {
+        let cache = DemangleCache::new();
+        let a = cache.demangle("not_a_mangled_symbol");
+        assert_eq!(a, "not_a_mangled_symbol");
+        let out = cache.demangle_all(["not_a_mangled_symbol", "another_one"]);
+        assert_eq!(out, vec![a, Istr::new("another_one")]);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[cfg(feature = "rustc-demangle")]
+    #[test]
+    fn test_demangle_rust() {
+        assert_eq!(demangle("_ZN4testE"), "test");
+        assert_eq!(demangle("_ZN3foo3barE"), "foo::bar");
+    }
+
+    #[cfg(feature = "cpp_demangle")]
+    #[test]
+    fn test_demangle_cpp() {
+        let out = demangle_all(["_ZN5space3fooEii"]);
+        assert!(out[0].as_str().starts_with("space::foo"));
+    }
+}
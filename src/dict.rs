@@ -0,0 +1,807 @@
+//! A compact, self-describing serde format that stores each distinct string once.
+//!
+//! [`to_vec`] serializes any `Serialize` value to a tagged binary encoding. The first time a
+//! string is written it's stored inline and appended to a dictionary embedded in the stream;
+//! later occurrences are written as a back-reference into that dictionary. Struct field names
+//! and unit enum variants go through the dictionary too, so a list of records pays for each
+//! field name once rather than once per record, which is where most of the size of JSON-ish
+//! payloads goes.
+//!
+//! The format is self-describing, so it can be deserialized without knowing the type, e.g.
+//! into a `serde_json::Value` for debugging.
+//!
+//! Both directions build their dictionary with the interner: every string that passes
+//! through is interned into the global table, and so leaked. This makes repeated strings cost
+//! one table lookup to encode, and deserialized `&str` fields can borrow from the table, but it
+//! means the format is meant for traffic with a bounded vocabulary, not arbitrary user text.
+//! The strings written and read are still the caller's own: a string the
+//! [intercept hook](crate::intercept) interns as a canonical form round-trips as it was given.
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//!
+//! let mut routes = BTreeMap::new();
+//! routes.insert("index", vec!["GET", "HEAD"]);
+//! routes.insert("upload", vec!["POST", "PUT"]);
+//! routes.insert("health", vec!["GET", "HEAD"]);
+//! let bytes = lsi::dict::to_vec(&routes).unwrap();
+//! let decoded: BTreeMap<&str, Vec<&str>> = lsi::dict::from_slice(&bytes).unwrap();
+//! assert_eq!(decoded, routes);
+//! ```
+//!
+//! # Encoding
+//!
+//! The stream starts with the 8-byte header `LSIDICT\x01` (the last byte is the format version),
+//! followed by a single value. Every value starts with a tag byte. Lengths, unsigned integers,
+//! and dictionary indices are LEB128 varints; signed integers are zigzag-encoded first. Floats
+//! are little-endian. Sequences and maps are terminated by an end tag rather than prefixed with
+//! their length, so values of unknown length can be streamed.
+//!
+//! | Value | Encoding |
+//! |-------|----------|
+//! | `()`, unit structs | `UNIT` |
+//! | `bool` | `FALSE` or `TRUE` |
+//! | signed, unsigned integers | `INT` zigzag varint, `UINT` varint |
+//! | `f32`, `f64` | `F32`, `F64` then the bytes |
+//! | `char` | `CHAR` varint |
+//! | strings | `STR` length bytes, or `REF` index |
+//! | byte strings | `BYTES` length bytes |
+//! | `Option` | `NONE`, or `SOME` value |
+//! | sequences, tuples | `SEQ` values `END` |
+//! | maps, structs | `MAP` (key value)* `END` |
+//! | unit variants | as a string |
+//! | other variants | as a map with one entry, from the variant name to its value |
+//!
+//! Newtype structs are encoded as their contents.
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::{ser, Deserialize, Serialize};
+
+use crate::Istr;
+
+const MAGIC: &[u8; 8] = b"LSIDICT\x01";
+
+const UNIT: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const INT: u8 = 3;
+const UINT: u8 = 4;
+const F32: u8 = 5;
+const F64: u8 = 6;
+const CHAR: u8 = 7;
+const STR: u8 = 8;
+const REF: u8 = 9;
+const BYTES: u8 = 10;
+const NONE: u8 = 11;
+const SOME: u8 = 12;
+const SEQ: u8 = 13;
+const MAP: u8 = 14;
+const END: u8 = 15;
+
+/// Serialize `value` to a byte vector.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    to_writer(&mut out, value)?;
+    Ok(out)
+}
+
+/// Serialize `value` to `writer`.
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Result<()> {
+    let mut serializer = Serializer::new(writer)?;
+    value.serialize(&mut serializer)
+}
+
+/// Deserialize a value from bytes written by [`to_vec`] or [`to_writer`].
+pub fn from_slice<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T> {
+    let mut deserializer = Deserializer::from_slice(bytes)?;
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// An error reported by a `Serialize` or `Deserialize` implementation.
+    Message(String),
+    /// The input doesn't start with the format header.
+    NotDict,
+    /// The input ended in the middle of a value.
+    Eof,
+    InvalidTag(u8),
+    /// A back-reference to a string not yet in the dictionary.
+    InvalidRef(u64),
+    InvalidUtf8,
+    InvalidChar(u64),
+    VarintOverflow,
+    /// The input continues after the value.
+    TrailingBytes,
+    /// A string couldn't be interned, with the error of [`Istr::try_new`].
+    Intern(Box<crate::Error>),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => e.fmt(f),
+            Error::Message(m) => f.write_str(m),
+            Error::NotDict => f.write_str("not an lsi dictionary stream"),
+            Error::Eof => f.write_str("unexpected end of input"),
+            Error::InvalidTag(t) => write!(f, "invalid tag {}", t),
+            Error::InvalidRef(i) => write!(f, "reference to undefined string {}", i),
+            Error::InvalidUtf8 => f.write_str("string is not valid UTF-8"),
+            Error::InvalidChar(c) => write!(f, "invalid char {:#x}", c),
+            Error::VarintOverflow => f.write_str("varint overflows 64 bits"),
+            Error::TrailingBytes => f.write_str("trailing bytes after value"),
+            Error::Intern(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Intern(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Intern `s`, failing with [`Error::Intern`] instead of panicking.
+fn intern(s: &str) -> Result<Istr> {
+    Istr::try_new(s).map_err(|e| Error::Intern(Box::new(e)))
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+pub struct Serializer<W> {
+    writer: W,
+    dictionary: HashMap<Istr, u64>,
+    /// The number of strings written inline, which is the index the reader gives the next one.
+    strings: u64,
+}
+
+impl<W: Write> Serializer<W> {
+    /// Create a serializer, writing the format header to `writer`.
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(MAGIC)?;
+        Ok(Serializer {
+            writer,
+            dictionary: HashMap::new(),
+            strings: 0,
+        })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn tag(&mut self, tag: u8) -> Result<()> {
+        Ok(self.writer.write_all(&[tag])?)
+    }
+
+    fn varint(&mut self, mut v: u64) -> Result<()> {
+        let mut buf = [0; 10];
+        let mut i = 0;
+        while v >= 0x80 {
+            buf[i] = v as u8 | 0x80;
+            v >>= 7;
+            i += 1;
+        }
+        buf[i] = v as u8;
+        Ok(self.writer.write_all(&buf[..=i])?)
+    }
+
+    fn string(&mut self, s: &str) -> Result<()> {
+        let key = intern(s)?;
+        // A string the hook interned as another one is written out every time, so that a
+        // reference never stands for a different string.
+        if key == s {
+            if let Some(&index) = self.dictionary.get(&key) {
+                self.tag(REF)?;
+                return self.varint(index);
+            }
+            self.dictionary.insert(key, self.strings);
+        }
+        self.strings += 1;
+        self.tag(STR)?;
+        self.varint(s.len() as u64)?;
+        Ok(self.writer.write_all(s.as_bytes())?)
+    }
+}
+
+impl<W: Write> ser::Serializer for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.tag(if v { TRUE } else { FALSE })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.tag(INT)?;
+        self.varint(((v << 1) ^ (v >> 63)) as u64)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.tag(UINT)?;
+        self.varint(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.tag(F32)?;
+        Ok(self.writer.write_all(&v.to_le_bytes())?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.tag(F64)?;
+        Ok(self.writer.write_all(&v.to_le_bytes())?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.tag(CHAR)?;
+        self.varint(v as u64)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.string(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.tag(BYTES)?;
+        self.varint(v.len() as u64)?;
+        Ok(self.writer.write_all(v)?)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.tag(NONE)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        self.tag(SOME)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.tag(UNIT)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.tag(UNIT)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.string(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.tag(MAP)?;
+        self.string(variant)?;
+        value.serialize(&mut *self)?;
+        self.tag(END)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self> {
+        self.tag(SEQ)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.tag(MAP)?;
+        self.string(variant)?;
+        self.tag(SEQ)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self> {
+        self.tag(MAP)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.tag(MAP)?;
+        self.string(variant)?;
+        self.tag(MAP)?;
+        Ok(self)
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.tag(END)
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.tag(END)
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.tag(END)
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.tag(END)?;
+        self.tag(END)
+    }
+}
+
+impl<W: Write> ser::SerializeMap for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.tag(END)
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.string(key)?;
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.tag(END)
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.string(key)?;
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.tag(END)?;
+        self.tag(END)
+    }
+}
+
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+    /// The strings read inline: from the table, unless the hook interned them as another one.
+    dictionary: Vec<&'de str>,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Create a deserializer, checking the format header at the start of `input`.
+    pub fn from_slice(input: &'de [u8]) -> Result<Self> {
+        let input = input.strip_prefix(MAGIC).ok_or(Error::NotDict)?;
+        Ok(Deserializer {
+            input,
+            dictionary: Vec::new(),
+        })
+    }
+
+    /// Check that the whole input has been consumed.
+    pub fn end(&self) -> Result<()> {
+        if self.input.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::TrailingBytes)
+        }
+    }
+
+    fn peek(&self) -> Result<u8> {
+        self.input.first().copied().ok_or(Error::Eof)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'de [u8]> {
+        if self.input.len() < n {
+            return Err(Error::Eof);
+        }
+        let (head, rest) = self.input.split_at(n);
+        self.input = rest;
+        Ok(head)
+    }
+
+    fn tag(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut v = 0;
+        for shift in (0..64).step_by(7) {
+            let b = self.tag()?;
+            v |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(Error::VarintOverflow)
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        usize::try_from(self.varint()?).map_err(|_| Error::Eof)
+    }
+
+    fn expect_end(&mut self) -> Result<()> {
+        match self.tag()? {
+            END => Ok(()),
+            _ => Err(Error::TrailingBytes),
+        }
+    }
+
+    /// Read the rest of a string value whose tag has been consumed.
+    fn string(&mut self, tag: u8) -> Result<&'de str> {
+        if tag == REF {
+            let index = self.varint()?;
+            return match self.dictionary.get(index as usize) {
+                Some(&s) => Ok(s),
+                None => Err(Error::InvalidRef(index)),
+            };
+        }
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        let s = std::str::from_utf8(bytes).map_err(|_| Error::InvalidUtf8)?;
+        let interned = intern(s)?;
+        let s = if interned == s { interned.as_str() } else { s };
+        self.dictionary.push(s);
+        Ok(s)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.tag()? {
+            UNIT => visitor.visit_unit(),
+            FALSE => visitor.visit_bool(false),
+            TRUE => visitor.visit_bool(true),
+            INT => {
+                let v = self.varint()?;
+                visitor.visit_i64((v >> 1) as i64 ^ -((v & 1) as i64))
+            }
+            UINT => visitor.visit_u64(self.varint()?),
+            F32 => visitor.visit_f32(f32::from_le_bytes(self.take(4)?.try_into().unwrap())),
+            F64 => visitor.visit_f64(f64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+            CHAR => {
+                let c = self.varint()?;
+                let c = u32::try_from(c)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or(Error::InvalidChar(c))?;
+                visitor.visit_char(c)
+            }
+            tag @ (STR | REF) => visitor.visit_borrowed_str(self.string(tag)?),
+            BYTES => {
+                let len = self.len()?;
+                visitor.visit_borrowed_bytes(self.take(len)?)
+            }
+            NONE => visitor.visit_none(),
+            SOME => visitor.visit_some(self),
+            SEQ => {
+                let value = visitor.visit_seq(Compound { de: &mut *self })?;
+                self.expect_end()?;
+                Ok(value)
+            }
+            MAP => {
+                let value = visitor.visit_map(Compound { de: &mut *self })?;
+                self.expect_end()?;
+                Ok(value)
+            }
+            tag => Err(Error::InvalidTag(tag)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.tag()? {
+            tag @ (STR | REF) => visitor.visit_enum(self.string(tag)?.into_deserializer()),
+            MAP => {
+                let value = visitor.visit_enum(Compound { de: &mut *self })?;
+                self.expect_end()?;
+                Ok(value)
+            }
+            tag => Err(Error::InvalidTag(tag)),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Access to the elements of a sequence or map, or the content of a variant.
+struct Compound<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> de::SeqAccess<'de> for Compound<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.de.peek()? == END {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Compound<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.de.peek()? == END {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for Compound<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Compound<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Method {
+        Get,
+        Other(String),
+        Redirect { to: Istr, permanent: bool },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Request<'a> {
+        method: Method,
+        path: &'a str,
+        status: Option<u16>,
+        latency: f64,
+        offset: i32,
+        tags: (char, Vec<u8>),
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let requests: Vec<Request> = (0..100)
+            .map(|i| Request {
+                method: match i % 3 {
+                    0 => Method::Get,
+                    1 => Method::Other("PATCH".to_string()),
+                    _ => Method::Redirect {
+                        to: Istr::new("/login"),
+                        permanent: i % 2 == 0,
+                    },
+                },
+                path: if i % 2 == 0 {
+                    "/index.html"
+                } else {
+                    "/api/users"
+                },
+                status: (i % 5 != 0).then_some(200),
+                latency: i as f64 / 8.0,
+                offset: -i,
+                tags: ('λ', vec![i as u8]),
+            })
+            .collect();
+        let bytes = to_vec(&requests).unwrap();
+        let decoded: Vec<Request> = from_slice(&bytes).unwrap();
+        assert_eq!(decoded, requests);
+
+        // Field names, variant names and repeated values are each stored once.
+        for s in ["latency", "Redirect", "/index.html", "PATCH"] {
+            let count = bytes.windows(s.len()).filter(|w| *w == s.as_bytes()).count();
+            assert_eq!(count, 1, "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(from_slice::<u8>(b"{}"), Err(Error::NotDict)));
+        let mut bytes = to_vec(&5u8).unwrap();
+        bytes.push(UNIT);
+        assert!(matches!(
+            from_slice::<u8>(&bytes),
+            Err(Error::TrailingBytes)
+        ));
+        let bytes = [&MAGIC[..], &[REF, 0]].concat();
+        assert!(matches!(
+            from_slice::<&str>(&bytes),
+            Err(Error::InvalidRef(0))
+        ));
+    }
+}
@@ -0,0 +1,225 @@
+//! Diffing sequences of interned tokens.
+use crate::Istr;
+
+/// One run of a diff produced by [`diff`], in terms of indices into the compared sequences.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DiffOp {
+    /// `a[a..a + len]` equals `b[b..b + len]`.
+    Equal { a: usize, b: usize, len: usize },
+    /// `a[a..a + len]` is not in `b`.
+    Delete { a: usize, len: usize },
+    /// `b[b..b + len]` is not in `a`.
+    Insert { b: usize, len: usize },
+}
+
+/// A shortest edit script turning `a` into `b`, using Myers' algorithm.
+///
+/// Tokens are compared by handle rather than by contents, so each comparison is a single
+/// pointer comparison. Both sequences must therefore be interned in the same table.
+///
+/// ```
+/// use lsi::{diff, DiffOp, InternTable};
+///
+/// let table = InternTable::new();
+/// let a = ["the", "quick", "fox"].map(|s| table.get_or_intern(s));
+/// let b = ["the", "slow", "fox"].map(|s| table.get_or_intern(s));
+/// assert_eq!(
+///     diff(&a, &b),
+///     [
+///         DiffOp::Equal { a: 0, b: 0, len: 1 },
+///         DiffOp::Delete { a: 1, len: 1 },
+///         DiffOp::Insert { b: 1, len: 1 },
+///         DiffOp::Equal { a: 2, b: 2, len: 1 },
+///     ]
+/// );
+/// ```
+pub fn diff(a: &[Istr], b: &[Istr]) -> Vec<DiffOp> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| same(x, y)).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| same(x, y))
+        .count();
+    let mut ops = Ops(Vec::new());
+    ops.equal(0, 0, prefix);
+    myers(
+        &a[prefix..a.len() - suffix],
+        &b[prefix..b.len() - suffix],
+        prefix,
+        &mut ops,
+    );
+    ops.equal(a.len() - suffix, b.len() - suffix, suffix);
+    ops.0
+}
+
+fn same(x: &Istr, y: &Istr) -> bool {
+    x.data_ptr() == y.data_ptr()
+}
+
+/// Diff the middle of both sequences, which starts at `offset` in each.
+fn myers(a: &[Istr], b: &[Istr], offset: usize, ops: &mut Ops) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    // `v[max + k]` is the furthest `x` reached on diagonal `k = x - y`.
+    let mut v = vec![0isize; 2 * max as usize + 2];
+    let mut trace = Vec::new();
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let i = (max + k) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && same(&a[x as usize], &b[y as usize]) {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back from the end, collecting the edits in reverse.
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let i = (max + k) as usize;
+        let prev_k = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[(max + prev_k) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal(x, y));
+        }
+        if d > 0 {
+            edits.push(if x == prev_x {
+                Edit::Insert(prev_y)
+            } else {
+                Edit::Delete(prev_x)
+            });
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    for edit in edits.into_iter().rev() {
+        match edit {
+            Edit::Equal(x, y) => ops.equal(offset + x as usize, offset + y as usize, 1),
+            Edit::Delete(x) => ops.delete(offset + x as usize),
+            Edit::Insert(y) => ops.insert(offset + y as usize),
+        }
+    }
+}
+
+enum Edit {
+    Equal(isize, isize),
+    Delete(isize),
+    Insert(isize),
+}
+
+/// Diff runs, merging each edit into the previous run where they are contiguous.
+struct Ops(Vec<DiffOp>);
+
+impl Ops {
+    fn equal(&mut self, a: usize, b: usize, len: usize) {
+        if len == 0 {
+            return;
+        }
+        match self.0.last_mut() {
+            Some(DiffOp::Equal {
+                a: a0,
+                b: b0,
+                len: n,
+            }) if *a0 + *n == a && *b0 + *n == b => *n += len,
+            _ => self.0.push(DiffOp::Equal { a, b, len }),
+        }
+    }
+
+    fn delete(&mut self, a: usize) {
+        match self.0.last_mut() {
+            Some(DiffOp::Delete { a: a0, len }) if *a0 + *len == a => *len += 1,
+            _ => self.0.push(DiffOp::Delete { a, len: 1 }),
+        }
+    }
+
+    fn insert(&mut self, b: usize) {
+        match self.0.last_mut() {
+            Some(DiffOp::Insert { b: b0, len }) if *b0 + *len == b => *len += 1,
+            _ => self.0.push(DiffOp::Insert { b, len: 1 }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InternTable;
+
+    fn tokens(table: &InternTable, s: &str) -> Vec<Istr> {
+        s.chars()
+            .map(|c| table.get_or_intern(c.encode_utf8(&mut [0; 4])))
+            .collect()
+    }
+
+    /// Apply `ops` to `a`, checking they cover both sequences in order.
+    fn apply(ops: &[DiffOp], a: &[Istr], b: &[Istr]) -> Vec<Istr> {
+        let (mut i, mut j, mut out) = (0, 0, Vec::new());
+        for &op in ops {
+            match op {
+                DiffOp::Equal { a: x, b: y, len } => {
+                    assert_eq!((x, y), (i, j));
+                    assert_eq!(a[x..x + len], b[y..y + len]);
+                    out.extend_from_slice(&a[x..x + len]);
+                    (i, j) = (i + len, j + len);
+                }
+                DiffOp::Delete { a: x, len } => {
+                    assert_eq!(x, i);
+                    i += len;
+                }
+                DiffOp::Insert { b: y, len } => {
+                    assert_eq!(y, j);
+                    out.extend_from_slice(&b[y..y + len]);
+                    j += len;
+                }
+            }
+        }
+        assert_eq!((i, j), (a.len(), b.len()));
+        out
+    }
+
+    #[test]
+    fn test_diff() {
+        let table = InternTable::new();
+        for (a, b, edits) in [
+            ("abcabba", "cbabac", 5),
+            ("", "", 0),
+            ("abc", "", 3),
+            ("", "abc", 3),
+            ("same", "same", 0),
+            ("kitten", "sitting", 5),
+        ] {
+            let (a, b) = (tokens(&table, a), tokens(&table, b));
+            let ops = diff(&a, &b);
+            assert_eq!(apply(&ops, &a, &b), b);
+            let changed: usize = ops
+                .iter()
+                .map(|op| match *op {
+                    DiffOp::Equal { .. } => 0,
+                    DiffOp::Delete { len, .. } | DiffOp::Insert { len, .. } => len,
+                })
+                .sum();
+            assert_eq!(changed, edits, "{:?}", ops);
+        }
+    }
+}
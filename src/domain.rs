@@ -0,0 +1,183 @@
+//! Domain names.
+//!
+//! Names are lowercased, and stripped of the trailing dot of a fully qualified name, before
+//! being interned, and each name keeps its labels as interned strings. Comparing names, or
+//! checking whether one is under another, is then a handful of pointer comparisons rather
+//! than a case-insensitive walk over the bytes.
+use std::fmt;
+use std::sync::RwLock;
+
+use crate::{Error, Istr, IstrMap};
+
+/// The longest name accepted, in bytes, without the trailing dot.
+pub const MAX_LEN: usize = 253;
+
+/// The longest label accepted, in bytes.
+pub const MAX_LABEL_LEN: usize = 63;
+
+static LABELS: RwLock<Option<IstrMap<&'static [Istr]>>> = RwLock::new(None);
+
+/// A lowercased, interned domain name.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct IDomain {
+    name: Istr,
+    /// The labels of the name, leftmost first.
+    labels: &'static [Istr],
+}
+
+impl IDomain {
+    /// Parse and lowercase a name. Returns `None` if it is not a well-formed hostname: labels
+    /// of ASCII letters, digits, `-` and `_`, not starting or ending with `-`. Internationalized
+    /// names must be in their `xn--` form.
+    ///
+    /// # Panics
+    ///
+    /// If the name or one of its labels can't be interned, see [`IDomain::try_parse`].
+    pub fn parse(s: &str) -> Option<Self> {
+        match Self::try_parse(s) {
+            Ok(name) => name,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [`IDomain::parse`], but returns the error of [`Istr::try_new`] if the name or one
+    /// of its labels can't be interned, instead of panicking.
+    pub fn try_parse(s: &str) -> Result<Option<Self>, Error> {
+        let s = s.strip_suffix('.').unwrap_or(s);
+        if s.is_empty() || s.len() > MAX_LEN || !s.split('.').all(valid_label) {
+            return Ok(None);
+        }
+        let name = if s.bytes().any(|b| b.is_ascii_uppercase()) {
+            Istr::try_new(&s.to_ascii_lowercase())?
+        } else {
+            Istr::try_new(s)?
+        };
+        Self::from_canonical(name).map(Some)
+    }
+
+    /// The domain named `name`, which must already be valid and lowercase.
+    fn from_canonical(name: Istr) -> Result<Self, Error> {
+        if let Some(&labels) = LABELS.read().unwrap().as_ref().and_then(|m| m.get(&name)) {
+            return Ok(IDomain { name, labels });
+        }
+        let labels = name
+            .as_str()
+            .split('.')
+            .map(Istr::try_new)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut lock = LABELS.write().unwrap();
+        let labels = *lock
+            .get_or_insert_with(IstrMap::default)
+            .entry(name)
+            .or_insert_with(|| Box::leak(labels.into_boxed_slice()));
+        Ok(IDomain { name, labels })
+    }
+
+    pub fn as_istr(&self) -> Istr {
+        self.name
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.name.as_str()
+    }
+
+    /// The interned labels of this name, leftmost first, e.g. `["www", "example", "com"]`.
+    pub fn labels(&self) -> &'static [Istr] {
+        self.labels
+    }
+
+    /// The name with its leftmost label removed, e.g. `example.com` for `www.example.com`.
+    /// `None` for a single-label name.
+    ///
+    /// # Panics
+    ///
+    /// If the parent's name can't be interned, see [`IDomain::try_parent`].
+    pub fn parent(&self) -> Option<IDomain> {
+        match self.try_parent() {
+            Ok(parent) => parent,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [`IDomain::parent`], but returns the error of [`Istr::try_new`] if the parent's
+    /// name can't be interned, instead of panicking.
+    pub fn try_parent(&self) -> Result<Option<IDomain>, Error> {
+        let Some((first, rest)) = self.labels.split_first().filter(|(_, rest)| !rest.is_empty())
+        else {
+            return Ok(None);
+        };
+        let name = Istr::try_new(&self.as_str()[first.len() + 1..])?;
+        Ok(Some(IDomain { name, labels: rest }))
+    }
+
+    /// Whether this name is `other` or under it, comparing whole labels: `api.example.com` is
+    /// a subdomain of `example.com`, but `badexample.com` is not.
+    pub fn is_subdomain_of(&self, other: &IDomain) -> bool {
+        self.labels.ends_with(other.labels)
+    }
+}
+
+impl fmt::Display for IDomain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn valid_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= MAX_LABEL_LEN
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let name = IDomain::parse("WWW.Example.COM.").unwrap();
+        assert_eq!(name.as_str(), "www.example.com");
+        assert_eq!(name, IDomain::parse("www.example.com").unwrap());
+        assert_eq!(name.labels(), ["www", "example", "com"].map(Istr::new));
+        assert_eq!(
+            IDomain::parse("_sip._tcp.example.com")
+                .unwrap()
+                .labels()
+                .len(),
+            4
+        );
+        for invalid in [
+            "",
+            ".",
+            "a..b",
+            "-a.com",
+            "a-.com",
+            "ex ample.com",
+            "bücher.de",
+        ] {
+            assert!(IDomain::parse(invalid).is_none(), "{:?}", invalid);
+        }
+        assert!(IDomain::parse(&format!("{}.com", "a".repeat(64))).is_none());
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        let www = IDomain::parse("www.example.com").unwrap();
+        let example = IDomain::parse("example.com").unwrap();
+        let com = IDomain::parse("com").unwrap();
+        assert_eq!(www.parent(), Some(example));
+        assert_eq!(example.parent(), Some(com));
+        assert_eq!(com.parent(), None);
+        assert!(www.is_subdomain_of(&example));
+        assert!(www.is_subdomain_of(&com));
+        assert!(www.is_subdomain_of(&www));
+        assert!(!example.is_subdomain_of(&www));
+        assert!(!IDomain::parse("badexample.com")
+            .unwrap()
+            .is_subdomain_of(&example));
+    }
+}
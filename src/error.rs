@@ -0,0 +1,208 @@
+//! [`Error`], every failure of the crate's fallible API in one type.
+//!
+//! Each module keeps its own error type, which is what its functions return, and converts into
+//! [`Error`] with `?`. Functions that would otherwise panic on bad input have a `try_` variant
+//! returning [`Error`] as well, and the panicking one is implemented on top of it, so a service
+//! embedding the crate can handle every failure without unwinding:
+//!
+//! ```
+//! fn load(table: &lsi::InternTable, names: &[&str]) -> Result<Vec<lsi::Istr>, lsi::Error> {
+//!     names.iter().map(|s| table.get_or_intern_checked(s)).collect()
+//! }
+//! assert_eq!(load(&lsi::InternTable::new(), &["a", "b"]).unwrap(), ["a", "b"]);
+//! ```
+//!
+//! Only misuse the caller can't check for up front, and can't recover from, is left to
+//! panic: running out of memory, or out of `u32` ids in a [`Vocab`](crate::vocab::Vocab).
+use std::fmt;
+use std::io;
+
+use crate::codes::CodesExhausted;
+use crate::replicate::ReplicationError;
+use crate::verify::Report;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A string of this many bytes is longer than [`MAX_LEN`](crate::MAX_LEN), or the
+    /// [maximum length](crate::budget::set_max_len) of the global table, and can't be
+    /// interned.
+    TooLong(usize),
+    Io(io::Error),
+    /// Verifying a table found problems.
+    Verify(Report),
+    /// A preset atom set disagrees with its declared names.
+    #[cfg(feature = "global")]
+    Atoms(crate::atoms::AtomMismatch),
+    /// A schema was asked to require a key that isn't one of its allowed keys.
+    #[cfg(feature = "global")]
+    UnknownKey(Box<str>),
+    #[cfg(feature = "global")]
+    Invalid(crate::newtype::Invalid),
+    #[cfg(feature = "global")]
+    Attach(crate::AttachError),
+    #[cfg(all(feature = "serde", feature = "global"))]
+    Dict(crate::dict::Error),
+    Replication(ReplicationError),
+    CodesExhausted,
+    /// A new string didn't fit the [budget](crate::budget) of the global table.
+    #[cfg(feature = "global")]
+    OverBudget(crate::budget::Overrun),
+    /// A string was missing from the frozen [pool](crate::pool::Pool) with this name.
+    Frozen(&'static str),
+    #[cfg(feature = "global")]
+    Init(crate::InitError),
+    /// The global table is [frozen](crate::frozen) without overflow, and the string isn't in it.
+    #[cfg(feature = "global")]
+    TableFrozen,
+    /// The [intercept hook](crate::intercept) rejected a string, for this reason.
+    #[cfg(feature = "global")]
+    Rejected(&'static str),
+    /// A UTF-16 string to intern has an unpaired surrogate.
+    #[cfg(feature = "wide")]
+    Utf16(std::string::FromUtf16Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TooLong(len) => write!(f, "a string of {} bytes is too long to intern", len),
+            Error::Io(e) => e.fmt(f),
+            Error::Verify(report) => write!(f, "verification failed: {}", report),
+            #[cfg(feature = "global")]
+            Error::Atoms(e) => e.fmt(f),
+            #[cfg(feature = "global")]
+            Error::UnknownKey(key) => write!(f, "{:?} is not an allowed key", key),
+            #[cfg(feature = "global")]
+            Error::Invalid(e) => e.fmt(f),
+            #[cfg(feature = "global")]
+            Error::Attach(e) => e.fmt(f),
+            #[cfg(all(feature = "serde", feature = "global"))]
+            Error::Dict(e) => e.fmt(f),
+            Error::Replication(e) => e.fmt(f),
+            Error::CodesExhausted => CodesExhausted.fmt(f),
+            #[cfg(feature = "global")]
+            Error::OverBudget(overrun) => overrun.fmt(f),
+            Error::Frozen(name) => write!(f, "pool {:?} is frozen", name),
+            #[cfg(feature = "global")]
+            Error::Init(e) => e.fmt(f),
+            #[cfg(feature = "global")]
+            Error::TableFrozen => f.write_str("the global table is frozen"),
+            #[cfg(feature = "global")]
+            Error::Rejected(reason) => write!(f, "string rejected: {}", reason),
+            #[cfg(feature = "wide")]
+            Error::Utf16(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error {
+    /// A copy of an error of [`Istr::try_new`](crate::Istr::try_new), to hand the same failure
+    /// to several waiters. Any other error is copied as its message.
+    #[cfg(any(feature = "async", feature = "background"))]
+    pub(crate) fn duplicate(&self) -> Error {
+        match *self {
+            Error::TooLong(len) => Error::TooLong(len),
+            Error::OverBudget(overrun) => Error::OverBudget(overrun),
+            Error::TableFrozen => Error::TableFrozen,
+            Error::Rejected(reason) => Error::Rejected(reason),
+            ref e => Error::Io(io::Error::other(e.to_string())),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            #[cfg(all(feature = "serde", feature = "global"))]
+            Error::Dict(e) => Some(e),
+            #[cfg(feature = "wide")]
+            Error::Utf16(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(feature = "global")]
+impl From<crate::atoms::AtomMismatch> for Error {
+    fn from(e: crate::atoms::AtomMismatch) -> Self {
+        Error::Atoms(e)
+    }
+}
+
+#[cfg(feature = "global")]
+impl From<crate::newtype::Invalid> for Error {
+    fn from(e: crate::newtype::Invalid) -> Self {
+        Error::Invalid(e)
+    }
+}
+
+#[cfg(feature = "global")]
+impl From<crate::AttachError> for Error {
+    fn from(e: crate::AttachError) -> Self {
+        Error::Attach(e)
+    }
+}
+
+#[cfg(feature = "global")]
+impl From<crate::InitError> for Error {
+    fn from(e: crate::InitError) -> Self {
+        Error::Init(e)
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "global"))]
+impl From<crate::dict::Error> for Error {
+    fn from(e: crate::dict::Error) -> Self {
+        Error::Dict(e)
+    }
+}
+
+#[cfg(feature = "wide")]
+impl From<std::string::FromUtf16Error> for Error {
+    fn from(e: std::string::FromUtf16Error) -> Self {
+        Error::Utf16(e)
+    }
+}
+
+impl From<ReplicationError> for Error {
+    fn from(e: ReplicationError) -> Self {
+        Error::Replication(e)
+    }
+}
+
+impl From<CodesExhausted> for Error {
+    fn from(_: CodesExhausted) -> Self {
+        Error::CodesExhausted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        fn register(codes: &crate::codes::CodeRegistry) -> Result<u16, Error> {
+            let code = codes.register(crate::InternTable::new().get_or_intern("code"))?;
+            Ok(code)
+        }
+        assert!(register(&crate::codes::CodeRegistry::new()).is_ok());
+        let e = Error::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert_eq!(e.to_string(), "missing");
+        assert!(std::error::Error::source(&e).is_some());
+        let e = Error::from(ReplicationError::Conflict { id: 3 });
+        assert!(matches!(e, Error::Replication(_)));
+        assert_eq!(
+            Error::TooLong(usize::MAX).to_string(),
+            format!("a string of {} bytes is too long to intern", usize::MAX)
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::ptr::NonNull;
+
+use crate::{InternTable, Istr};
+
+/// A 16-byte handle carrying the string's address and length, so that
+/// [`as_str`](FatIstr::as_str) reads neither from the entry.
+///
+/// An [`Istr`] is a pointer to its entry, and reading the string loads the length from the
+/// entry's header first. That load is usually a cache hit, but in a loop calling `as_str` on
+/// millions of handles it can show up in profiles. A `FatIstr` trades twice the footprint for
+/// building the `&str` from its own fields, so pick one or the other per data structure:
+/// `Istr` for large collections of handles, `FatIstr` for the hot ones. Converting between
+/// them is free apart from the length load.
+///
+/// Equality and hashing compare addresses, like `Istr`'s.
+///
+/// ```
+/// use lsi::{FatIstr, InternTable, Istr};
+///
+/// let table = InternTable::new();
+/// let s = table.get_or_intern("hot");
+/// let fat = FatIstr::from(s);
+/// assert_eq!(std::mem::size_of::<FatIstr>(), 2 * std::mem::size_of::<usize>());
+/// assert_eq!(fat.as_str(), "hot");
+/// assert_eq!(Istr::from(fat), s);
+/// ```
+#[derive(Copy, Clone)]
+pub struct FatIstr {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// SAFETY: a `FatIstr` points to the bytes of an `Istr`, which is `Send` and `Sync`.
+unsafe impl Send for FatIstr {}
+unsafe impl Sync for FatIstr {}
+
+impl FatIstr {
+    /// Intern `s` into the active table.
+    #[cfg(feature = "global")]
+    pub fn new(s: &str) -> Self {
+        Istr::new(s).into()
+    }
+
+    /// Intern `s` into `table`.
+    pub fn new_in(table: &InternTable, s: &str) -> Self {
+        table.get_or_intern(s).into()
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        // SAFETY: `ptr` and `len` were read from an `Istr`, whose bytes live for the rest of
+        // the process.
+        unsafe {
+            let slice = std::slice::from_raw_parts(self.ptr.as_ptr(), self.len);
+            std::str::from_utf8_unchecked(slice)
+        }
+    }
+
+    pub fn as_istr(&self) -> Istr {
+        // SAFETY: `ptr` was returned by `Istr::data_ptr`.
+        unsafe { Istr::from_data_ptr(self.ptr.as_ptr()) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl From<Istr> for FatIstr {
+    fn from(s: Istr) -> Self {
+        FatIstr {
+            // SAFETY: a handle's bytes, even the empty string's, have a non-null address.
+            ptr: unsafe { NonNull::new_unchecked(s.data_ptr() as *mut u8) },
+            len: s.len(),
+        }
+    }
+}
+
+impl From<FatIstr> for Istr {
+    fn from(s: FatIstr) -> Self {
+        s.as_istr()
+    }
+}
+
+impl PartialEq for FatIstr {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+
+impl Eq for FatIstr {}
+
+impl PartialEq<&str> for FatIstr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Hash for FatIstr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.ptr.as_ptr() as usize).hash(state)
+    }
+}
+
+impl Deref for FatIstr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for FatIstr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for FatIstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for FatIstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+crate::assert_eq_size!(FatIstr, Option<FatIstr>, [usize; 2]);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fat_istr() {
+        let table = InternTable::new();
+        for s in ["", "x", "a longer string"] {
+            let istr = table.get_or_intern(s);
+            let fat = FatIstr::new_in(&table, s);
+            assert_eq!(fat, FatIstr::from(istr));
+            assert_eq!(fat.as_istr(), istr);
+            assert_eq!((fat.as_str(), fat.len(), fat.is_empty()), (s, s.len(), s.is_empty()));
+            assert_eq!(fat.as_str().as_ptr(), istr.data_ptr());
+        }
+        assert_ne!(FatIstr::new_in(&table, "x"), FatIstr::new_in(&InternTable::new(), "x"));
+    }
+}
@@ -0,0 +1,105 @@
+//! C functions for interning from non-Rust code into the same table as the Rust code.
+//!
+//! With the `ffi` feature, the crate exports the functions of this module unmangled, so that a
+//! C or C++ engine linked with a Rust component can intern into the active table, the
+//! [`GLOBAL_TABLE`](crate::GLOBAL_TABLE) or the one [attached](crate::attach) to, and pass
+//! handles back and forth. A handle is a [`RawIstr`], whose representation the C side can read
+//! directly, see the [`raw`](crate::raw) module. The declarations, as cbindgen emits them:
+//!
+//! ```c
+//! typedef uintptr_t LsiHandle;
+//!
+//! LsiHandle lsi_intern(const uint8_t *ptr, uintptr_t len);
+//! LsiHandle lsi_get(const uint8_t *ptr, uintptr_t len);
+//! const uint8_t *lsi_str(LsiHandle handle, uintptr_t *len);
+//! uintptr_t lsi_len(LsiHandle handle);
+//! ```
+//!
+//! Handles are equal if and only if their strings are, so C code compares them as integers.
+//! `0` is the empty string, and what [`lsi_intern`] returns for a string it can't intern.
+use crate::{Istr, RawIstr};
+
+/// A handle, for C: `uintptr_t`.
+pub type LsiHandle = RawIstr;
+
+/// Intern the `len` bytes at `ptr`. Returns `0` for the empty string, and for bytes that aren't
+/// UTF-8 or that [`Istr::try_new`] rejects.
+///
+/// # Safety
+///
+/// `ptr` must be valid for reading `len` bytes, or `len` must be 0.
+#[no_mangle]
+pub unsafe extern "C" fn lsi_intern(ptr: *const u8, len: usize) -> LsiHandle {
+    match str_from(ptr, len) {
+        Some(s) => Istr::try_new(s).map_or(RawIstr::EMPTY, Istr::into_raw),
+        None => RawIstr::EMPTY,
+    }
+}
+
+/// The handle of the `len` bytes at `ptr` if they have been interned, without interning them
+/// otherwise, or `0`.
+///
+/// # Safety
+///
+/// As [`lsi_intern`].
+#[no_mangle]
+pub unsafe extern "C" fn lsi_get(ptr: *const u8, len: usize) -> LsiHandle {
+    str_from(ptr, len)
+        .and_then(Istr::get)
+        .map_or(RawIstr::EMPTY, Istr::into_raw)
+}
+
+/// The address of the string's bytes, which are not nul-terminated, storing its length at
+/// `len` unless it is null. Never null, even for the empty string.
+///
+/// # Safety
+///
+/// `handle` must be `0` or have been returned by this module or [`Istr::into_raw`], and `len`
+/// must be null or valid for writing.
+#[no_mangle]
+pub unsafe extern "C" fn lsi_str(handle: LsiHandle, len: *mut usize) -> *const u8 {
+    let s = Istr::from_raw(handle);
+    if !len.is_null() {
+        len.write(s.len());
+    }
+    s.data_ptr()
+}
+
+/// The length of the string in bytes.
+///
+/// # Safety
+///
+/// As for the handle of [`lsi_str`].
+#[no_mangle]
+pub unsafe extern "C" fn lsi_len(handle: LsiHandle) -> usize {
+    handle.len()
+}
+
+unsafe fn str_from<'a>(ptr: *const u8, len: usize) -> Option<&'a str> {
+    if len == 0 {
+        return Some("");
+    }
+    std::str::from_utf8(std::slice::from_raw_parts(ptr, len)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi() {
+        let s = "ffi string";
+        let handle = unsafe { lsi_intern(s.as_ptr(), s.len()) };
+        assert_eq!(handle, Istr::new(s).into_raw());
+        assert_eq!(unsafe { lsi_get(s.as_ptr(), s.len()) }, handle);
+        let mut len = 0;
+        let ptr = unsafe { lsi_str(handle, &mut len) };
+        assert_eq!((ptr, len), (Istr::new(s).data_ptr(), s.len()));
+        assert_eq!(unsafe { lsi_len(handle) }, s.len());
+        let unseen = "ffi unseen";
+        assert!(unsafe { lsi_get(unseen.as_ptr(), unseen.len()) }.is_empty());
+        assert!(unsafe { lsi_intern(b"\xff".as_ptr(), 1) }.is_empty());
+        assert!(unsafe { lsi_intern(std::ptr::null(), 0) }.is_empty());
+        assert!(!unsafe { lsi_str(RawIstr::EMPTY, std::ptr::null_mut()) }.is_null());
+    }
+}
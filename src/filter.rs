@@ -0,0 +1,202 @@
+//! Approximate membership snapshots of the table.
+//!
+//! [`filter_snapshot`] exports the current contents of the global table as an [`XorFilter`]
+//! (an 8-bit xor filter, see Graf & Lemire, "Xor Filters: Faster and Smaller Than Bloom and
+//! Cuckoo Filters"). It uses about 9.84 bits per string and has a false positive rate of
+//! roughly 0.4%, with no false negatives for strings that were in the table when it was built.
+//!
+//! The filter hashes strings with a fixed algorithm (64-bit FNV-1a, mixed with the filter's
+//! seed by the MurmurHash3 finalizer), so a filter serialized with [`XorFilter::to_bytes`] can be
+//! queried by another process, e.g. to check whether a string would be a hit over there before
+//! making an IPC round trip.
+#[cfg(feature = "global")]
+use crate::active_table;
+
+const MAGIC: &[u8; 8] = b"LSIXOR8\x01";
+
+/// Build a filter of every string currently in the global table.
+#[cfg(feature = "global")]
+pub fn filter_snapshot() -> XorFilter {
+    XorFilter::new(active_table().to_vec().iter().map(|s| s.as_str()))
+}
+
+/// An immutable approximate set of strings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XorFilter {
+    seed: u64,
+    block_length: u32,
+    fingerprints: Box<[u8]>,
+}
+
+impl XorFilter {
+    pub fn new<'a>(strings: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut keys: Vec<u64> = strings.into_iter().map(base_hash).collect();
+        // Duplicate keys can never be peeled, and colliding strings are indistinguishable to the
+        // filter anyway.
+        keys.sort_unstable();
+        keys.dedup();
+        Self::from_keys(&keys)
+    }
+
+    /// Whether `s` may be in the set. Always true for strings that were in it.
+    pub fn contains(&self, s: &str) -> bool {
+        let h = mix(base_hash(s), self.seed);
+        let [h0, h1, h2] = self.positions(h);
+        fingerprint(h) == self.fingerprints[h0] ^ self.fingerprints[h1] ^ self.fingerprints[h2]
+    }
+
+    /// The size of the filter in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.fingerprints.len()
+    }
+
+    /// Serialize the filter. The layout is an 8-byte magic, the seed as a little-endian `u64`,
+    /// the block length as a little-endian `u32`, then the fingerprints.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(20 + self.fingerprints.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        out.extend_from_slice(&self.block_length.to_le_bytes());
+        out.extend_from_slice(&self.fingerprints);
+        out
+    }
+
+    /// Deserialize a filter written by [`XorFilter::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let rest = bytes.strip_prefix(MAGIC)?;
+        let seed = u64::from_le_bytes(rest.get(..8)?.try_into().ok()?);
+        let block_length = u32::from_le_bytes(rest.get(8..12)?.try_into().ok()?);
+        let fingerprints: Box<[u8]> = rest[12..].into();
+        if block_length == 0 || fingerprints.len() != 3 * block_length as usize {
+            return None;
+        }
+        Some(XorFilter {
+            seed,
+            block_length,
+            fingerprints,
+        })
+    }
+
+    fn positions(&self, h: u64) -> [usize; 3] {
+        let bl = self.block_length;
+        [
+            reduce(h as u32, bl) as usize,
+            (reduce(h.rotate_left(21) as u32, bl) + bl) as usize,
+            (reduce(h.rotate_left(42) as u32, bl) + 2 * bl) as usize,
+        ]
+    }
+
+    fn from_keys(keys: &[u64]) -> Self {
+        let capacity = 32 + (1.23 * keys.len() as f64).ceil() as usize;
+        let block_length = (capacity / 3) as u32;
+        let size = 3 * block_length as usize;
+        let mut seed = 0x9e37_79b9_7f4a_7c15;
+        loop {
+            let mut filter = XorFilter {
+                seed,
+                block_length,
+                fingerprints: vec![0; size].into(),
+            };
+            if let Some(stack) = filter.peel(keys) {
+                for &(h, found) in stack.iter().rev() {
+                    let [h0, h1, h2] = filter.positions(h);
+                    let fp = &filter.fingerprints;
+                    // `fp[found]` is still zero here, so it drops out of the xor.
+                    let value = fingerprint(h) ^ fp[h0] ^ fp[h1] ^ fp[h2];
+                    filter.fingerprints[found] = value;
+                }
+                return filter;
+            }
+            seed = splitmix(seed);
+        }
+    }
+
+    /// Find an order in which every key has a slot no later key maps to. Returns `None` if the
+    /// current seed doesn't allow one.
+    fn peel(&self, keys: &[u64]) -> Option<Vec<(u64, usize)>> {
+        let size = self.fingerprints.len();
+        let mut count = vec![0u32; size];
+        let mut xor = vec![0u64; size];
+        for &key in keys {
+            let h = mix(key, self.seed);
+            for i in self.positions(h) {
+                count[i] += 1;
+                xor[i] ^= h;
+            }
+        }
+        let mut queue: Vec<usize> = (0..size).filter(|&i| count[i] == 1).collect();
+        let mut stack = Vec::with_capacity(keys.len());
+        while let Some(i) = queue.pop() {
+            if count[i] != 1 {
+                continue;
+            }
+            let h = xor[i];
+            stack.push((h, i));
+            for j in self.positions(h) {
+                count[j] -= 1;
+                xor[j] ^= h;
+                if count[j] == 1 {
+                    queue.push(j);
+                }
+            }
+        }
+        (stack.len() == keys.len()).then_some(stack)
+    }
+}
+
+fn base_hash(s: &str) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in s.as_bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    h
+}
+
+fn mix(key: u64, seed: u64) -> u64 {
+    let mut h = key.wrapping_add(seed);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+fn splitmix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn fingerprint(h: u64) -> u8 {
+    (h ^ (h >> 32)) as u8
+}
+
+fn reduce(x: u32, n: u32) -> u32 {
+    ((x as u64 * n as u64) >> 32) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let words: Vec<String> = (0..10_000).map(|i| format!("filter-word-{}", i)).collect();
+        let filter = XorFilter::new(words.iter().map(|s| s.as_str()));
+        assert!(words.iter().all(|w| filter.contains(w)));
+        let false_positives = (0..10_000)
+            .filter(|i| filter.contains(&format!("absent-{}", i)))
+            .count();
+        assert!(false_positives < 100, "{} false positives", false_positives);
+        assert_eq!(XorFilter::from_bytes(&filter.to_bytes()), Some(filter));
+    }
+
+    #[test]
+    #[cfg(feature = "global")]
+    fn test_filter_snapshot() {
+        let s = crate::Istr::new("in the filter snapshot");
+        assert!(filter_snapshot().contains(s.as_str()));
+    }
+}
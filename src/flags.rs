@@ -0,0 +1,100 @@
+//! Per-entry flag bits for marking symbols without a side set per mark.
+//!
+//! Every entry carries eight atomic bits that any code holding a handle can set and test, e.g.
+//! for "visited" in a graph walk or "exported" in a linker. The bits belong to the entry, so
+//! they are shared by every user of the table: agree on which bit means what within a process.
+//!
+//! ```
+//! use lsi::flags::Flag;
+//! use lsi::InternTable;
+//!
+//! const EXPORTED: Flag = Flag::new(0);
+//!
+//! let table = InternTable::new();
+//! let main = table.get_or_intern("main");
+//! assert!(!main.set_flag(EXPORTED));
+//! assert!(table.get_or_intern("main").get_flag(EXPORTED));
+//! ```
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::Istr;
+
+/// The flags of the empty string, which has no entry of its own.
+static EMPTY_FLAGS: AtomicU8 = AtomicU8::new(0);
+
+/// One of the eight flag bits of an entry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Flag(u8);
+
+impl Flag {
+    /// The flag at bit `bit`.
+    ///
+    /// # Panics
+    ///
+    /// If `bit` is 8 or more, which fails to compile when used in a constant.
+    pub const fn new(bit: u8) -> Self {
+        assert!(bit < 8, "entries have 8 flag bits");
+        Flag(1 << bit)
+    }
+
+    pub const fn bit(self) -> u8 {
+        self.0.trailing_zeros() as u8
+    }
+}
+
+impl Istr {
+    /// Set `flag`, returning whether it was already set.
+    pub fn set_flag(&self, flag: Flag) -> bool {
+        self.flag_bits().fetch_or(flag.0, Ordering::Relaxed) & flag.0 != 0
+    }
+
+    /// Clear `flag`, returning whether it was set.
+    pub fn clear_flag(&self, flag: Flag) -> bool {
+        self.flag_bits().fetch_and(!flag.0, Ordering::Relaxed) & flag.0 != 0
+    }
+
+    // Inlined so that the ordering is a constant and `load` has no panic path left, see the
+    // `realtime` module.
+    #[inline]
+    pub fn get_flag(&self, flag: Flag) -> bool {
+        self.flag_bits().load(Ordering::Relaxed) & flag.0 != 0
+    }
+
+    /// All eight flag bits, bit `n` being [`Flag::new(n)`](Flag::new).
+    pub fn flags(&self) -> u8 {
+        self.flag_bits().load(Ordering::Relaxed)
+    }
+
+    fn flag_bits(&self) -> &'static AtomicU8 {
+        if self.is_empty() {
+            return &EMPTY_FLAGS;
+        }
+        &self.header().flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InternTable;
+
+    #[test]
+    fn test_flags() {
+        const VISITED: Flag = Flag::new(0);
+        const DEPRECATED: Flag = Flag::new(7);
+        let table = InternTable::new();
+        let a = table.get_or_intern("a");
+        let b = table.get_or_intern("b");
+        assert!(!a.set_flag(VISITED));
+        assert!(a.set_flag(VISITED));
+        a.set_flag(DEPRECATED);
+        assert_eq!(a.flags(), 0b1000_0001);
+        assert!(!b.get_flag(VISITED));
+        assert!(a.clear_flag(VISITED));
+        assert!(!table.get_or_intern("a").get_flag(VISITED));
+        assert_eq!(DEPRECATED.bit(), 7);
+        let empty = table.get_or_intern("");
+        empty.set_flag(VISITED);
+        assert!(table.get_or_intern("").get_flag(VISITED));
+    }
+}
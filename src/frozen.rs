@@ -0,0 +1,245 @@
+//! Freezing the global table once startup interning is done.
+//!
+//! [`freeze`] copies every string of the [active table](crate::active_table) into a
+//! [`FrozenTable`]: an immutable perfect hash, where looking a string up reads one
+//! displacement and one slot, takes no lock and allocates nothing. [`Istr::get`] and every
+//! function interning into the active table, from [`Istr::new`] to
+//! [`try_intern`](crate::try_intern) and [`Istr::intern_all`](crate::Istr::intern_all),
+//! consult it before anything else. What happens to strings that aren't in it is up to the
+//! [`OnMiss`] given to [`freeze`]:
+//!
+//! ```
+//! use lsi::frozen::{self, OnMiss};
+//! use lsi::{Error, Istr};
+//!
+//! let keyword = Istr::new("match");
+//! lsi::freeze(OnMiss::Error);
+//! assert_eq!(Istr::new("match"), keyword);
+//! assert!(matches!(Istr::try_new("unknown"), Err(Error::TableFrozen)));
+//! lsi::freeze(OnMiss::Overflow);
+//! assert_eq!(Istr::new("unknown"), "unknown");
+//! assert_eq!(frozen::table().unwrap().len(), 1);
+//! ```
+//!
+//! The perfect hash is hash-and-displace: the strings are spread over buckets of about four,
+//! and each bucket gets the first displacement that sends every one of its strings to a free
+//! slot, trying the largest buckets first. There are a few percent more slots than strings, so
+//! that the last buckets find room quickly. A bucket that finds none, e.g. because two of its
+//! strings collide on all 64 bits of their hash, is left out, and its strings are found in
+//! the table behind.
+//!
+//! Restoring a checkpoint of the active table, resetting it or removing strings from it
+//! thaws it: the frozen table is dropped, and lookups go to the table again.
+//!
+//! [`Istr::new`]: crate::Istr::new
+//! [`Istr::get`]: crate::Istr::get
+use std::ptr::{self, null_mut};
+use std::sync::atomic::{AtomicPtr, AtomicU8, Ordering};
+use std::sync::Mutex;
+
+use crate::{active_table, Error, InternTable, Istr, EMPTY_FAST_STR};
+
+/// The average number of strings per bucket.
+const BUCKET_SIZE: usize = 4;
+
+/// The frozen table, or null. Frozen tables are leaked, as lookups may still be reading one
+/// when it is thawed.
+static FROZEN: AtomicPtr<FrozenTable> = AtomicPtr::new(null_mut());
+
+static ON_MISS: AtomicU8 = AtomicU8::new(OnMiss::Overflow as u8);
+
+/// Held while building a frozen table.
+static FREEZING: Mutex<()> = Mutex::new(());
+
+/// What interning a string that isn't in the frozen table does.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OnMiss {
+    /// Make [`Istr::new`](crate::Istr::new) and the batches of
+    /// [`Istr::intern_all`](crate::Istr::intern_all) panic, naming the string. Functions
+    /// interning a single string that return errors, like
+    /// [`Istr::try_new`](crate::Istr::try_new), fail with [`Error::TableFrozen`] instead, and
+    /// [`try_intern`](crate::try_intern) returns `None`.
+    Panic,
+    /// Fail with [`Error::TableFrozen`], or `None` from [`try_intern`](crate::try_intern).
+    /// [`Istr::new`](crate::Istr::new) panics with it.
+    Error,
+    /// Intern it into the table behind the frozen one, as if it weren't frozen.
+    Overflow,
+}
+
+/// An immutable copy of the table's strings at the time it was frozen.
+#[derive(Debug)]
+pub struct FrozenTable {
+    /// The displacement of each bucket.
+    pilots: Box<[u16]>,
+    /// The strings and their table hashes. Free slots hold the empty string, which no lookup
+    /// compares equal to.
+    slots: Box<[(u64, Istr)]>,
+    len: usize,
+}
+
+impl FrozenTable {
+    fn build(strings: Vec<Istr>) -> Self {
+        let n = strings.len();
+        let mut slots = vec![(0, EMPTY_FAST_STR); n + n / 32 + 1].into_boxed_slice();
+        let mut pilots = vec![0; n / BUCKET_SIZE + 1].into_boxed_slice();
+        let mut keys: Vec<(usize, u64, Istr)> = strings
+            .into_iter()
+            .map(|s| {
+                let hash = InternTable::hash(s.as_str());
+                (reduce(hash, pilots.len()), hash, s)
+            })
+            .collect();
+        keys.sort_unstable_by_key(|&(bucket, ..)| bucket);
+        let mut buckets: Vec<&[(usize, u64, Istr)]> = keys.chunk_by(|a, b| a.0 == b.0).collect();
+        buckets.sort_by_key(|bucket| std::cmp::Reverse(bucket.len()));
+        let mut taken = vec![false; slots.len()];
+        let mut positions = Vec::with_capacity(BUCKET_SIZE);
+        let mut len = 0;
+        for bucket in buckets {
+            let placed = (0..=u16::MAX).find(|&pilot| {
+                positions.clear();
+                bucket.iter().all(|&(_, hash, _)| {
+                    let i = position(hash, pilot, slots.len());
+                    let free = !taken[i] && !positions.contains(&i);
+                    positions.push(i);
+                    free
+                })
+            });
+            let Some(pilot) = placed else {
+                continue;
+            };
+            pilots[bucket[0].0] = pilot;
+            for (&(_, hash, s), &i) in bucket.iter().zip(&positions) {
+                taken[i] = true;
+                slots[i] = (hash, s);
+            }
+            len += bucket.len();
+        }
+        FrozenTable { pilots, slots, len }
+    }
+
+    /// The number of strings in the frozen table.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The handle of `s` if it is in the frozen table.
+    pub fn get(&self, s: &str) -> Option<Istr> {
+        if s.is_empty() {
+            return Some(EMPTY_FAST_STR);
+        }
+        self.get_hashed(s, InternTable::hash(s))
+    }
+
+    /// Like [`FrozenTable::get`], for the non-empty `s` whose table hash is `hash`.
+    fn get_hashed(&self, s: &str, hash: u64) -> Option<Istr> {
+        let pilot = self.pilots[reduce(hash, self.pilots.len())];
+        let (h, fast_str) = self.slots[position(hash, pilot, self.slots.len())];
+        (h == hash && fast_str.as_str() == s).then_some(fast_str)
+    }
+
+    /// Every string in the frozen table, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = Istr> + '_ {
+        self.slots
+            .iter()
+            .map(|&(_, fast_str)| fast_str)
+            .filter(|fast_str| !fast_str.is_empty())
+    }
+}
+
+/// Map `hash` onto `0..len`, from its high bits.
+fn reduce(hash: u64, len: usize) -> usize {
+    ((hash as u128 * len as u128) >> 64) as usize
+}
+
+/// The slot of a string with table hash `hash` in a bucket with displacement `pilot`.
+fn position(hash: u64, pilot: u16, len: usize) -> usize {
+    let mut x = hash ^ (pilot as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    x ^= x >> 32;
+    x = x.wrapping_mul(0xd6e8_feb8_6659_fd93);
+    x ^= x >> 32;
+    reduce(x, len)
+}
+
+/// Freeze the active table, with `on_miss` deciding what interning other strings does.
+///
+/// Only the first call copies the table: calling this again keeps the frozen table, and only
+/// changes `on_miss`. The strings interned after the first call, with
+/// [`OnMiss::Overflow`], are still found in the table behind.
+///
+/// Once the table has been thawed, the next call copies it again. The thawed frozen table is
+/// leaked, as lookups on other threads may still be reading it, so each freeze after a thaw
+/// costs the memory of another copy of the table's handles.
+pub fn freeze(on_miss: OnMiss) -> &'static FrozenTable {
+    let _lock = FREEZING.lock().unwrap();
+    ON_MISS.store(on_miss as u8, Ordering::Release);
+    if let Some(frozen) = table() {
+        return frozen;
+    }
+    let frozen = Box::leak(Box::new(FrozenTable::build(active_table().to_vec())));
+    FROZEN.store(frozen, Ordering::Release);
+    frozen
+}
+
+/// The frozen table, if [`freeze`] has been called and the table hasn't been thawed since.
+pub fn table() -> Option<&'static FrozenTable> {
+    // SAFETY: frozen tables are never freed.
+    unsafe { FROZEN.load(Ordering::Acquire).as_ref() }
+}
+
+/// What interning strings that aren't in the frozen table does.
+pub fn on_miss() -> OnMiss {
+    match ON_MISS.load(Ordering::Acquire) {
+        0 => OnMiss::Panic,
+        1 => OnMiss::Error,
+        _ => OnMiss::Overflow,
+    }
+}
+
+/// Look the non-empty `s`, whose table hash is `hash`, up for interning it. `None` if the
+/// table isn't frozen, or `s` should be interned into the table behind.
+pub(crate) fn lookup(s: &str, hash: u64) -> Option<Result<Istr, Error>> {
+    let frozen = table()?;
+    if let Some(fast_str) = frozen.get_hashed(s, hash) {
+        return Some(Ok(fast_str));
+    }
+    if let Some(fast_str) = active_table().get_hashed(s, hash) {
+        return Some(Ok(fast_str));
+    }
+    match on_miss() {
+        OnMiss::Panic | OnMiss::Error => Some(Err(Error::TableFrozen)),
+        OnMiss::Overflow => None,
+    }
+}
+
+/// Drop the frozen table. Called by restores, resets and removals of the active table, before
+/// they free entries.
+pub(crate) fn thaw() {
+    FROZEN.store(ptr::null_mut(), Ordering::Release);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let table = InternTable::new();
+        let strings: Vec<Istr> = (0..5000)
+            .map(|i| table.get_or_intern(&format!("frozen {}", i)))
+            .collect();
+        let frozen = FrozenTable::build(strings.clone());
+        assert_eq!(frozen.len(), 5000);
+        assert!(strings.iter().all(|&s| frozen.get(s.as_str()) == Some(s)));
+        assert_eq!(frozen.get("frozen 5000"), None);
+        assert_eq!(frozen.get(""), Some(EMPTY_FAST_STR));
+        assert_eq!(frozen.iter().count(), 5000);
+        assert!(FrozenTable::build(Vec::new()).get("anything").is_none());
+    }
+}
@@ -0,0 +1,93 @@
+//! Interning from async code without blocking the executor.
+//!
+//! [`intern_async`] takes the usual path when the table lock is free, which is almost always.
+//! If the lock is contended, or the string can't be interned, the string is handed to a
+//! dedicated interner thread and the returned future resolves once that thread has interned it,
+//! so an executor thread is never parked on the table lock. The future's output is that of
+//! [`Istr::try_new`].
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::worker::{self, Slot};
+use crate::{try_intern, Error, Istr};
+
+/// Intern `s` without blocking the calling thread.
+pub fn intern_async(s: &str) -> InternFuture {
+    match try_intern(s) {
+        Some(fast_str) => InternFuture(State::Ready(Ok(fast_str))),
+        None => InternFuture(State::Pending(worker::submit(s))),
+    }
+}
+
+/// The future returned by [`intern_async`].
+#[must_use = "futures do nothing unless polled"]
+pub struct InternFuture(State);
+
+enum State {
+    Ready(Result<Istr, Error>),
+    Pending(Arc<Slot>),
+}
+
+impl InternFuture {
+    /// Whether the string was interned on the synchronous fast path.
+    pub fn is_ready(&self) -> bool {
+        matches!(self.0, State::Ready(_))
+    }
+}
+
+impl Future for InternFuture {
+    type Output = Result<Istr, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &self.0 {
+            State::Ready(result) => Poll::Ready(worker::copy(result)),
+            State::Pending(slot) => match slot.poll(cx.waker()) {
+                Some(result) => {
+                    self.0 = State::Ready(worker::copy(&result));
+                    Poll::Ready(result)
+                }
+                None => Poll::Pending,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GLOBAL_TABLE;
+    use std::sync::mpsc;
+    use std::task::{Wake, Waker};
+    use std::thread;
+
+    struct Notify(mpsc::Sender<()>);
+
+    impl Wake for Notify {
+        fn wake(self: Arc<Self>) {
+            let _ = self.0.send(());
+        }
+    }
+
+    #[test]
+    fn test_intern_async_contended() {
+        let (tx, rx) = mpsc::channel();
+        let waker = Waker::from(Arc::new(Notify(tx)));
+        let mut cx = Context::from_waker(&waker);
+
+        let locks: Vec<_> = GLOBAL_TABLE.0.iter().map(|s| s.lock.write()).collect();
+        let mut fut = thread::spawn(|| intern_async("interned off-thread"))
+            .join()
+            .unwrap();
+        assert!(!fut.is_ready());
+        assert!(Pin::new(&mut fut).poll(&mut cx).is_pending());
+        drop(locks);
+
+        rx.recv().unwrap();
+        match Pin::new(&mut fut).poll(&mut cx) {
+            Poll::Ready(s) => assert_eq!(s.unwrap(), Istr::new("interned off-thread")),
+            Poll::Pending => panic!("future not ready after wake"),
+        }
+    }
+}
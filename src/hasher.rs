@@ -0,0 +1,136 @@
+//! [`TableHasher`], the hasher of the tables: `ahash` by default, `rustc-hash`'s `FxHasher`
+//! with the `fxhash` feature, or `foldhash` with the `foldhash` feature, which wins if both
+//! are on.
+//!
+//! With the `random-seed` feature, on by default, the hasher is seeded with random bytes from
+//! the operating system the first time a string is hashed, so that strings crafted to collide
+//! in one process don't collide in the next, and can't turn a table's lookups quadratic.
+//! Without it the hasher is unseeded, for builds whose behaviour must be reproducible. Either
+//! way, a string hashes the same every time in a process: the hash cached in an entry with the
+//! `cached-hash` feature, the shard a string is filed under and the slot of its thread cache
+//! all depend on it. Copies of the crate built with different hashers can't share a table, and
+//! [`attach`](crate::attach) refuses to; a copy attaching to another's table adopts its seed.
+//!
+//! The choice doesn't change which strings are equal, only how fast they are found. An
+//! [`Interner`](crate::Interner) takes any `BuildHasher` as a type parameter instead.
+
+/// The hasher of every [`InternTable`](crate::InternTable).
+#[cfg(all(
+    not(feature = "random-seed"),
+    not(any(feature = "fxhash", feature = "foldhash"))
+))]
+pub type TableHasher = hashbrown::hash_map::DefaultHashBuilder;
+
+/// The hasher of every [`InternTable`](crate::InternTable).
+#[cfg(all(
+    not(feature = "random-seed"),
+    feature = "fxhash",
+    not(feature = "foldhash")
+))]
+pub type TableHasher = rustc_hash::FxBuildHasher;
+
+/// The hasher of every [`InternTable`](crate::InternTable).
+#[cfg(all(not(feature = "random-seed"), feature = "foldhash"))]
+pub type TableHasher = foldhash::fast::FixedState;
+
+/// The hasher of every [`InternTable`](crate::InternTable).
+#[cfg(feature = "random-seed")]
+pub type TableHasher = seeded::SeededState;
+
+/// The one value of [`TableHasher`] the tables use.
+#[cfg(all(
+    not(feature = "random-seed"),
+    not(any(feature = "fxhash", feature = "foldhash"))
+))]
+pub(crate) const TABLE_HASHER: TableHasher =
+    // SAFETY: `BuildHasherDefault` is a zero-sized marker.
+    unsafe { std::mem::transmute::<(), TableHasher>(()) };
+
+#[cfg(all(
+    not(feature = "random-seed"),
+    feature = "fxhash",
+    not(feature = "foldhash")
+))]
+pub(crate) const TABLE_HASHER: TableHasher = rustc_hash::FxBuildHasher;
+
+#[cfg(all(not(feature = "random-seed"), feature = "foldhash"))]
+pub(crate) const TABLE_HASHER: TableHasher = foldhash::fast::FixedState::with_seed(0);
+
+#[cfg(feature = "random-seed")]
+pub(crate) const TABLE_HASHER: TableHasher = seeded::SeededState;
+
+#[cfg(feature = "random-seed")]
+pub(crate) mod seeded {
+    use std::hash::BuildHasher;
+    use std::sync::OnceLock;
+
+    /// The process's seed, chosen when the first string is hashed, or adopted from the table
+    /// attached to.
+    static SEED: OnceLock<Seed> = OnceLock::new();
+
+    pub(crate) type Seed = [u64; 4];
+
+    /// [`TableHasher`](super::TableHasher) with the `random-seed` feature: the hasher picked by
+    /// the other features, seeded with the process's seed.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct SeededState;
+
+    impl BuildHasher for SeededState {
+        #[cfg(not(any(feature = "fxhash", feature = "foldhash")))]
+        type Hasher = ahash::AHasher;
+        #[cfg(all(feature = "fxhash", not(feature = "foldhash")))]
+        type Hasher = rustc_hash::FxHasher;
+        #[cfg(feature = "foldhash")]
+        type Hasher = foldhash::fast::FoldHasher;
+
+        #[inline]
+        fn build_hasher(&self) -> Self::Hasher {
+            #[cfg_attr(any(feature = "fxhash", feature = "foldhash"), allow(unused_variables))]
+            let [k0, k1, k2, k3] = seed();
+            #[cfg(not(any(feature = "fxhash", feature = "foldhash")))]
+            return ahash::RandomState::with_seeds(k0, k1, k2, k3).build_hasher();
+            #[cfg(all(feature = "fxhash", not(feature = "foldhash")))]
+            return rustc_hash::FxHasher::with_seed(k0 as usize);
+            #[cfg(feature = "foldhash")]
+            foldhash::fast::FixedState::with_seed(k0).build_hasher()
+        }
+    }
+
+    pub(crate) fn seed() -> Seed {
+        *SEED.get_or_init(random_seed)
+    }
+
+    /// Use `seed` from now on, returning `false` if a different seed is already in use.
+    #[cfg(feature = "global")]
+    pub(crate) fn adopt(seed: Seed) -> bool {
+        *SEED.get_or_init(|| seed) == seed
+    }
+
+    fn random_seed() -> Seed {
+        let mut bytes = [0; 32];
+        if getrandom::getrandom(&mut bytes).is_err() {
+            // std's `RandomState` is seeded from the OS too, where it can be, and otherwise
+            // still differs between processes.
+            let state = std::collections::hash_map::RandomState::new();
+            return std::array::from_fn(|i| state.hash_one(i));
+        }
+        std::array::from_fn(|i| u64::from_ne_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap()))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_seed() {
+            assert_eq!(seed(), seed());
+            #[cfg(feature = "global")]
+            {
+                assert!(adopt(seed()));
+                assert!(!adopt([1, 2, 3, 4]));
+            }
+            assert_eq!(SeededState.hash_one("x"), SeededState.hash_one("x"));
+            assert_ne!(random_seed(), random_seed());
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! A small array of the most looked-up entries, consulted before the shards.
+//!
+//! With the `hot-tier` feature, every [`InternTable`](crate::InternTable) keeps a direct-mapped
+//! array of [`SLOTS`] entries next to its shards, split evenly between them. Lookups probe the
+//! slot for their hash first and return a hit without probing the shard's index; misses fall
+//! through to the shard, which counts a sample of its lookups per entry. Every
+//! [`PROMOTE_EVERY`] lookups of a shard, its most looked-up entries are promoted into its part
+//! of the array and its counts start over, so the array follows a shifting working set.
+//!
+//! Lookups that hit a shard's index don't take a lock either, so the array only saves the
+//! probe into a large index in favour of one into a small array that stays in cache, and
+//! counting costs hits a shared counter and, for a sample of them, trying the shard lock.
+//! Measure the `get_or_intern zipfian` bench with and without the feature before enabling it.
+use std::cmp::Reverse;
+use std::ptr::{null_mut, NonNull};
+use std::sync::atomic::{AtomicPtr, AtomicU32, AtomicU64, Ordering};
+
+use crate::{shard, Header, Istr, SHARDS};
+
+/// The number of slots in the hot array.
+pub const SLOTS: usize = 1024;
+
+/// The slots of each shard, which only its own promotions write, so that it can tell what it
+/// would evict.
+const SHARD_SLOTS: usize = SLOTS / SHARDS;
+
+/// The number of lookups of a shard of the main table between its promotions.
+pub const PROMOTE_EVERY: u32 = 32768;
+
+/// Only one in this many lookups of the main table is counted, which is enough to find the
+/// hottest entries at a fraction of the cost.
+const SAMPLE_EVERY: u32 = 8;
+
+#[derive(Debug)]
+pub(crate) struct HotTier {
+    slots: [Slot; SLOTS],
+    /// Lookups served by the shards' lock-free indexes, for sampling them.
+    hits: AtomicU32,
+}
+
+/// A promoted entry and its hash, which is compared first so that probing a slot holding
+/// another entry doesn't touch that entry's memory. The two are written separately, so a
+/// reader may see the hash of one entry with the pointer of another; the string comparison
+/// that follows a matching hash catches that.
+#[derive(Debug)]
+struct Slot {
+    hash: AtomicU64,
+    entry: AtomicPtr<Header>,
+}
+
+/// The entries of one shard looked up since its last promotion, guarded by the shard lock.
+/// Each entry counts its own lookups in its header.
+#[derive(Debug)]
+pub(crate) struct Counts {
+    touched: Vec<(Istr, u64)>,
+    lookups: u32,
+    /// The count each of the shard's slots' entry was promoted with, decayed since.
+    occupants: [u32; SHARD_SLOTS],
+}
+
+/// The slot for `hash` among those of its shard.
+fn shard_slot(hash: u64) -> usize {
+    (hash >> (64 - SHARD_SLOTS.trailing_zeros())) as usize
+}
+
+fn slot(hash: u64) -> usize {
+    shard(hash) * SHARD_SLOTS + shard_slot(hash)
+}
+
+impl HotTier {
+    pub(crate) const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const EMPTY: Slot = Slot {
+            hash: AtomicU64::new(0),
+            entry: AtomicPtr::new(null_mut()),
+        };
+        HotTier {
+            slots: [EMPTY; SLOTS],
+            hits: AtomicU32::new(0),
+        }
+    }
+
+    /// The promoted entry for `s`, whose table hash is `hash`.
+    pub(crate) fn get(&self, hash: u64, s: &str) -> Option<Istr> {
+        // Entries are fully written before they are published in the table, let alone here.
+        let slot = &self.slots[slot(hash)];
+        if slot.hash.load(Ordering::Relaxed) != hash {
+            return None;
+        }
+        NonNull::new(slot.entry.load(Ordering::Acquire))
+            .map(Istr)
+            .filter(|fast_str| fast_str.as_str() == s)
+    }
+
+    /// Count a lookup of `fast_str`, whose table hash is `hash`, in the main table, promoting
+    /// once enough have been counted. Called with the lock of the entry's shard held.
+    pub(crate) fn record(&self, counts: &mut Counts, fast_str: Istr, hash: u64) {
+        counts.lookups += 1;
+        if counts.lookups.is_multiple_of(SAMPLE_EVERY) {
+            self.count(counts, fast_str, hash);
+        }
+    }
+
+    /// Whether to count a lookup served by a shard's lock-free index, with
+    /// [`HotTier::record_hit`]. Only one in [`SAMPLE_EVERY`] is.
+    pub(crate) fn sample_hit(&self) -> bool {
+        self.hits
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(SAMPLE_EVERY)
+    }
+
+    /// Count a sampled lookup of `fast_str` served by a shard's lock-free index, standing in
+    /// for [`SAMPLE_EVERY`] lookups. Called with the lock of the entry's shard held.
+    pub(crate) fn record_hit(&self, counts: &mut Counts, fast_str: Istr, hash: u64) {
+        counts.lookups += SAMPLE_EVERY;
+        self.count(counts, fast_str, hash);
+    }
+
+    fn count(&self, counts: &mut Counts, fast_str: Istr, hash: u64) {
+        if fast_str.header().lookups.fetch_add(1, Ordering::Relaxed) == 0 {
+            counts.touched.push((fast_str, hash));
+        }
+        if counts.lookups < PROMOTE_EVERY {
+            return;
+        }
+        counts.lookups = 0;
+        // Read and reset the counts in one pass, touching each entry once.
+        let mut candidates: Vec<(u32, u64, Istr)> = counts
+            .touched
+            .drain(..)
+            .map(|(fast_str, hash)| {
+                let n = fast_str.header().lookups.swap(0, Ordering::Relaxed);
+                (n, hash, fast_str)
+            })
+            .collect();
+        if candidates.len() > SHARD_SLOTS {
+            candidates.select_nth_unstable_by_key(SHARD_SLOTS, |&(n, ..)| Reverse(n));
+            candidates.truncate(SHARD_SLOTS);
+        }
+        // Hits in the array aren't counted, so an entry keeps its slot until a candidate
+        // beats the count it was promoted with, which halves at every promotion.
+        for n in counts.occupants.iter_mut() {
+            *n /= 2;
+        }
+        for (n, hash, fast_str) in candidates {
+            let occupant = &mut counts.occupants[shard_slot(hash)];
+            if n > *occupant {
+                *occupant = n;
+                let slot = &self.slots[slot(hash)];
+                slot.hash.store(hash, Ordering::Relaxed);
+                slot.entry.store(fast_str.0.as_ptr(), Ordering::Release);
+            }
+        }
+    }
+
+    /// Empty the array and the counts of every shard, before entries are freed. Called with
+    /// every shard lock held.
+    pub(crate) fn clear<'a>(&self, shards: impl Iterator<Item = &'a mut Counts>) {
+        for slot in &self.slots {
+            slot.entry.store(null_mut(), Ordering::Release);
+        }
+        for counts in shards {
+            for (fast_str, _) in counts.touched.drain(..) {
+                fast_str.header().lookups.store(0, Ordering::Relaxed);
+            }
+            counts.lookups = 0;
+            counts.occupants = [0; SHARD_SLOTS];
+        }
+    }
+}
+
+impl Counts {
+    pub(crate) const fn new() -> Self {
+        Counts {
+            touched: Vec::new(),
+            lookups: 0,
+            occupants: [0; SHARD_SLOTS],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InternTable;
+
+    #[test]
+    fn test_promotion() {
+        let table = InternTable::new();
+        let hot = table.get_or_intern("hot");
+        let cold = table.get_or_intern("cold");
+        let hasher = crate::hasher::TABLE_HASHER;
+        let hash = hot.hash_in(&hasher);
+        assert_eq!(table.1.get(hash, "hot"), None);
+        // Lookups that hit are sampled, so give the shard a margin over `PROMOTE_EVERY`.
+        for _ in 0..2 * PROMOTE_EVERY {
+            table.get_or_intern("hot");
+        }
+        assert_eq!(table.1.get(hash, "hot"), Some(hot));
+        assert_eq!(table.1.get(hash, "not hot"), None);
+        assert_eq!(table.1.get(cold.hash_in(&hasher), "cold"), None);
+        // Hits are served from the array.
+        assert_eq!(table.get_or_intern("hot"), hot);
+        assert_eq!(table.get("hot"), Some(hot));
+    }
+
+    #[test]
+    fn test_shards_keep_their_slots() {
+        let table = InternTable::new();
+        let hasher = crate::hasher::TABLE_HASHER;
+        let hash = |s: &str| table.get_or_intern(s).hash_in(&hasher);
+        // Two entries of different shards, at the same place among their shard's slots.
+        let a = "shard slot 0".to_string();
+        let b = (1..)
+            .map(|i| format!("shard slot {}", i))
+            .find(|b| {
+                let (a, b) = (hash(&a), hash(b));
+                shard(a) != shard(b) && shard_slot(a) == shard_slot(b)
+            })
+            .unwrap();
+        for _ in 0..4 * PROMOTE_EVERY {
+            table.get_or_intern(&a);
+        }
+        // Promoting the entry of the other shard with fewer lookups doesn't evict the first.
+        for _ in 0..2 * PROMOTE_EVERY {
+            table.get_or_intern(&b);
+        }
+        assert!(table.1.get(hash(&a), &a).is_some());
+        assert!(table.1.get(hash(&b), &b).is_some());
+    }
+}
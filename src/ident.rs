@@ -0,0 +1,90 @@
+//! Identifier checks on interned strings, per [UAX #31](https://www.unicode.org/reports/tr31/).
+//!
+//! Language tooling asks whether the same symbols are identifiers over and over, so the
+//! answers are computed on first use and cached in the entry itself.
+use std::sync::atomic::Ordering;
+
+use crate::Istr;
+
+/// The cached results are valid.
+const KNOWN: u8 = 1;
+const XID: u8 = 2;
+const ASCII: u8 = 4;
+
+/// Whether `s` is an identifier by the UAX #31 default syntax, `XID_Start XID_Continue*`,
+/// with `_` also allowed to start one, as in Rust.
+pub fn is_xid_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || unicode_ident::is_xid_start(c))
+        && chars.all(unicode_ident::is_xid_continue)
+}
+
+/// Whether `s` matches `[A-Za-z_][A-Za-z0-9_]*`.
+pub fn is_ascii_identifier(s: &str) -> bool {
+    let mut bytes = s.bytes();
+    bytes
+        .next()
+        .is_some_and(|b| b == b'_' || b.is_ascii_alphabetic())
+        && bytes.all(|b| b == b'_' || b.is_ascii_alphanumeric())
+}
+
+impl Istr {
+    /// Whether this string is an identifier, see [`is_xid_identifier`].
+    pub fn is_xid_identifier(&self) -> bool {
+        self.ident_flags() & XID != 0
+    }
+
+    /// Whether this string is an ASCII identifier, see [`is_ascii_identifier`].
+    pub fn is_ascii_identifier(&self) -> bool {
+        self.ident_flags() & ASCII != 0
+    }
+
+    fn ident_flags(&self) -> u8 {
+        if self.is_empty() {
+            return KNOWN;
+        }
+        let cache = &self.header().ident;
+        let flags = cache.load(Ordering::Relaxed);
+        if flags & KNOWN != 0 {
+            return flags;
+        }
+        let s = self.as_str();
+        let mut flags = KNOWN;
+        if is_ascii_identifier(s) {
+            // Every ASCII identifier is also an XID identifier.
+            flags |= ASCII | XID;
+        } else if is_xid_identifier(s) {
+            flags |= XID;
+        }
+        // Racing threads compute the same flags, so the last store winning is fine.
+        cache.store(flags, Ordering::Relaxed);
+        flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::InternTable;
+
+    #[test]
+    fn test_identifiers() {
+        let table = InternTable::new();
+        for (s, xid, ascii) in [
+            ("foo_1", true, true),
+            ("_", true, true),
+            ("变量", true, false),
+            ("naïve", true, false),
+            ("1st", false, false),
+            ("a-b", false, false),
+            ("", false, false),
+        ] {
+            let istr = table.get_or_intern(s);
+            for _ in 0..2 {
+                assert_eq!(istr.is_xid_identifier(), xid, "{:?}", s);
+                assert_eq!(istr.is_ascii_identifier(), ascii, "{:?}", s);
+            }
+        }
+    }
+}
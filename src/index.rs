@@ -0,0 +1,191 @@
+//! The lock-free side of a shard, which lookups that hit read without taking its lock.
+//!
+//! Every shard keeps an open-addressing array of its entries next to its lock. Writers, holding
+//! the shard's write lock, publish each new entry into a free slot; readers probe from the
+//! slot selected by the string's hash until they find it or reach an empty slot, without any
+//! lock or read-modify-write, so a lookup that hits never waits for or slows down a writer. An
+//! entry a reader doesn't find may be one being inserted concurrently, so a miss falls back to
+//! the shard lock.
+//!
+//! The array is kept at most half full. Growing it copies the entries into a new array of twice
+//! the size and publishes that; readers may still be probing the old one, which can't be freed
+//! and is leaked like the entries themselves. The arrays of a shard add up to less than twice
+//! its current array, which has two to four 16-byte slots per entry.
+use std::ptr::{null_mut, NonNull};
+use std::sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+
+use crate::{Header, Istr};
+
+#[derive(Debug)]
+pub(crate) struct Index {
+    slots: AtomicPtr<Slots>,
+    /// The number of occupied slots. Only accessed with the shard's write lock held.
+    len: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct Slots(Box<[Slot]>);
+
+/// An entry and its table hash. The entry is published last, so a reader that sees it also
+/// sees its hash.
+#[derive(Debug)]
+struct Slot {
+    hash: AtomicU64,
+    entry: AtomicPtr<Header>,
+}
+
+const MIN_SLOTS: usize = 16;
+
+impl Index {
+    pub(crate) const fn new() -> Self {
+        Index {
+            slots: AtomicPtr::new(null_mut()),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// The entry for `s`, whose table hash is `hash`, if it has been published.
+    pub(crate) fn get(&self, hash: u64, s: &str) -> Option<Istr> {
+        // SAFETY: published arrays are never freed.
+        let slots = unsafe { self.slots.load(Ordering::Acquire).as_ref()? };
+        let mask = slots.0.len() - 1;
+        let mut i = hash as usize & mask;
+        loop {
+            let slot = &slots.0[i];
+            let entry = NonNull::new(slot.entry.load(Ordering::Acquire))?;
+            if slot.hash.load(Ordering::Relaxed) == hash {
+                let fast_str = Istr(entry);
+                if fast_str.as_str() == s {
+                    return Some(fast_str);
+                }
+            }
+            i = (i + 1) & mask;
+        }
+    }
+
+    /// Publish `fast_str`, whose table hash is `hash`. Called with the shard's write lock held.
+    pub(crate) fn insert(&self, hash: u64, fast_str: Istr) {
+        let len = self.len.load(Ordering::Relaxed) + 1;
+        let mut slots = self.slots.load(Ordering::Relaxed);
+        // SAFETY: published arrays are never freed.
+        let capacity = unsafe { slots.as_ref() }.map_or(0, |slots| slots.0.len());
+        if len * 2 > capacity {
+            slots = self.grow((capacity * 2).max(MIN_SLOTS));
+        }
+        // SAFETY: as above.
+        unsafe { &*slots }.place(hash, fast_str.0.as_ptr());
+        self.len.store(len, Ordering::Relaxed);
+    }
+
+    /// Make room for `additional` more entries, so inserting them doesn't grow the array
+    /// again. Called with the shard's write lock held.
+    pub(crate) fn reserve(&self, additional: usize) {
+        let len = self.len.load(Ordering::Relaxed) + additional;
+        // SAFETY: published arrays are never freed.
+        let slots = unsafe { self.slots.load(Ordering::Relaxed).as_ref() };
+        let capacity = slots.map_or(0, |slots| slots.0.len());
+        if len * 2 > capacity {
+            self.grow((len * 2).next_power_of_two().max(MIN_SLOTS));
+        }
+    }
+
+    /// Publish a copy of the array with `capacity` slots, a power of two, returning it.
+    fn grow(&self, capacity: usize) -> *mut Slots {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(capacity, len = self.len.load(Ordering::Relaxed), "growing index");
+        let new = Slots::new(capacity);
+        // SAFETY: published arrays are never freed.
+        if let Some(old) = unsafe { self.slots.load(Ordering::Relaxed).as_ref() } {
+            for slot in old.0.iter() {
+                let entry = slot.entry.load(Ordering::Relaxed);
+                if !entry.is_null() {
+                    new.place(slot.hash.load(Ordering::Relaxed), entry);
+                }
+            }
+        }
+        let new = Box::into_raw(Box::new(new));
+        self.slots.store(new, Ordering::Release);
+        new
+    }
+
+    /// Replace the contents with `entries`, in place. Called with the shard's write lock held
+    /// and no lookups running, as entries that are no longer in the shard are about to be
+    /// freed.
+    pub(crate) fn rebuild(&self, entries: impl Iterator<Item = (u64, Istr)>) {
+        // SAFETY: published arrays are never freed.
+        let Some(slots) = (unsafe { self.slots.load(Ordering::Relaxed).as_ref() }) else {
+            return;
+        };
+        for slot in slots.0.iter() {
+            slot.entry.store(null_mut(), Ordering::Relaxed);
+        }
+        let mut len = 0;
+        for (hash, fast_str) in entries {
+            slots.place(hash, fast_str.0.as_ptr());
+            len += 1;
+        }
+        self.len.store(len, Ordering::Relaxed);
+    }
+}
+
+impl Slots {
+    fn new(capacity: usize) -> Self {
+        Slots(
+            (0..capacity)
+                .map(|_| Slot {
+                    hash: AtomicU64::new(0),
+                    entry: AtomicPtr::new(null_mut()),
+                })
+                .collect(),
+        )
+    }
+
+    /// Store `entry` in the first free slot from `hash`. There is always one, as the array is
+    /// kept at most half full.
+    fn place(&self, hash: u64, entry: *mut Header) {
+        let mask = self.0.len() - 1;
+        let mut i = hash as usize & mask;
+        while !self.0[i].entry.load(Ordering::Relaxed).is_null() {
+            i = (i + 1) & mask;
+        }
+        self.0[i].hash.store(hash, Ordering::Relaxed);
+        self.0[i].entry.store(entry, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InternTable;
+
+    #[test]
+    fn test_index() {
+        let table = InternTable::new();
+        let index = Index::new();
+        assert_eq!(index.get(1, "a"), None);
+        let handles: Vec<Istr> = (0..100)
+            .map(|i| table.get_or_intern(&i.to_string()))
+            .collect();
+        // Colliding hashes probe past each other.
+        for (i, &s) in handles.iter().enumerate() {
+            index.insert(i as u64 % 7, s);
+        }
+        for (i, &s) in handles.iter().enumerate() {
+            assert_eq!(index.get(i as u64 % 7, s.as_str()), Some(s));
+        }
+        assert_eq!(index.get(0, "100"), None);
+    }
+
+    #[test]
+    fn test_reserve() {
+        let table = InternTable::new();
+        let index = Index::new();
+        index.reserve(100);
+        let slots = index.slots.load(Ordering::Relaxed);
+        for i in 0..100 {
+            index.insert(i, table.get_or_intern(&i.to_string()));
+        }
+        assert_eq!(index.slots.load(Ordering::Relaxed), slots);
+        assert_eq!(index.get(42, "42").unwrap(), "42");
+    }
+}
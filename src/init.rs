@@ -0,0 +1,96 @@
+//! Setting up the global table before its first use.
+//!
+//! A parser that is about to intern millions of identifiers can call [`init`] first, so the
+//! table is sized for them up front rather than rehashing each shard every time it doubles,
+//! and warmed with the keywords and names it knows it will see:
+//!
+//! ```
+//! lsi::init(lsi::Config {
+//!     capacity: 100_000,
+//!     prefill: &["fn", "let", "match"],
+//!     ..Default::default()
+//! })
+//! .unwrap();
+//! assert!(lsi::contains("match"));
+//! assert!(lsi::init(lsi::Config::default()).is_err());
+//! ```
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{active_table, convert, Error};
+
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// What [`init`] sets up.
+///
+/// There is deliberately no shard count to set. Every [`InternTable`] is a fixed array of
+/// [`SHARDS`] shards: that is what lets the global table be a `static` built at compile time,
+/// and the layout [attached](crate::attach) copies of the crate share. A count chosen at
+/// runtime would add a load of it to every lookup and a layout check to attaching, and the
+/// shard count only changes how often threads wait for each other's locks, not how often a
+/// shard rehashes. To size the table up front, set `capacity`, which is spread over the
+/// shards.
+///
+/// [`InternTable`]: crate::InternTable
+/// [`SHARDS`]: crate::SHARDS
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Config<'a> {
+    /// The number of strings to make room for, see [`InternTable::reserve`].
+    ///
+    /// [`InternTable::reserve`]: crate::InternTable::reserve
+    pub capacity: usize,
+    /// Strings to intern up front.
+    pub prefill: &'a [&'a str],
+}
+
+/// Returned by [`init`].
+#[derive(Debug)]
+pub enum InitError {
+    /// [`init`] has already been called.
+    AlreadyInitialized,
+    /// Strings were interned into the global table before [`init`].
+    AlreadyInterning,
+    /// A string of the prefill couldn't be interned, with the error of
+    /// [`Istr::try_new`](crate::Istr::try_new). The strings before it are interned.
+    Prefill(Box<Error>),
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::AlreadyInitialized => f.write_str("the global table is already initialized"),
+            InitError::AlreadyInterning => {
+                f.write_str("strings were interned before initializing the global table")
+            }
+            InitError::Prefill(e) => write!(f, "prefilling the global table failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for InitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InitError::Prefill(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Set up the [active table](crate::active_table) as `config` says. Must be called once,
+/// before anything is interned into it: later calls fail and leave the table as it is.
+pub fn init(config: Config<'_>) -> Result<(), InitError> {
+    if INITIALIZED.load(Ordering::Acquire) {
+        return Err(InitError::AlreadyInitialized);
+    }
+    let table = active_table();
+    if !table.is_empty() {
+        return Err(InitError::AlreadyInterning);
+    }
+    if INITIALIZED.swap(true, Ordering::AcqRel) {
+        return Err(InitError::AlreadyInitialized);
+    }
+    table.reserve(config.capacity.max(config.prefill.len()));
+    convert::intern_many(config.prefill.to_vec(), true)
+        .map_err(|e| InitError::Prefill(Box::new(e)))?;
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! A hook canonicalizing or rejecting strings before they enter the global table.
+//!
+//! The hook installed with [`set_hook`] sees every string about to be inserted into the active
+//! table, by [`Istr::new`] and its variants, [`try_intern`](crate::try_intern), the batches of
+//! [`Istr::intern_all`] and the prefill of [`init`](crate::init) alike, and returns the string
+//! to insert instead, or a reason to reject it, which [`Istr::try_new`] returns as
+//! [`Error::Rejected`]. Every call site then gets the same
+//! canonical form, e.g. NFC-normalized, lowercased or without a byte order mark, without
+//! wrapping [`Istr::new`]:
+//!
+//! ```
+//! use std::borrow::Cow;
+//! use lsi::{intercept, Error, Istr};
+//!
+//! fn canonical(s: &str) -> Result<Cow<'_, str>, &'static str> {
+//!     if s.len() > 64 {
+//!         return Err("names are at most 64 bytes");
+//!     }
+//!     let s = s.strip_prefix('\u{feff}').unwrap_or(s);
+//!     if s.bytes().any(|b| b.is_ascii_uppercase()) {
+//!         return Ok(Cow::Owned(s.to_ascii_lowercase()));
+//!     }
+//!     Ok(Cow::Borrowed(s))
+//! }
+//!
+//! intercept::set_hook(Some(canonical));
+//! assert_eq!(Istr::new("\u{feff}Content-Type"), Istr::new("content-type"));
+//! assert!(matches!(Istr::try_new(&"x".repeat(65)), Err(Error::Rejected(_))));
+//! intercept::set_hook(None);
+//! ```
+//!
+//! The hook only runs for strings that aren't interned yet, so a hit costs nothing more: a
+//! string that is already canonical is found as it is. That is only consistent if the hook is
+//! idempotent, returning canonical strings unchanged, and is installed before anything is
+//! interned. Like the [budget](crate::budget), it doesn't apply to strings interned into a
+//! table directly with [`InternTable`](crate::InternTable) methods.
+//!
+//! [`Istr::new`]: crate::Istr::new
+//! [`Istr::intern_all`]: crate::Istr::intern_all
+//! [`Istr::try_new`]: crate::Istr::try_new
+//! [`Error::Rejected`]: crate::Error::Rejected
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+/// A hook returning the canonical form of a string, or why it may not be interned.
+pub type Hook = fn(&str) -> Result<Cow<'_, str>, &'static str>;
+
+static HOOK: RwLock<Option<Hook>> = RwLock::new(None);
+
+/// Whether a hook is installed, checked before taking the lock.
+static SET: AtomicBool = AtomicBool::new(false);
+
+/// Install `hook` to run on new strings, or remove the current one with `None`.
+pub fn set_hook(hook: Option<Hook>) {
+    *HOOK.write().unwrap() = hook;
+    SET.store(hook.is_some(), Ordering::Release);
+}
+
+/// The installed hook.
+pub fn hook() -> Option<Hook> {
+    if !SET.load(Ordering::Acquire) {
+        return None;
+    }
+    *HOOK.read().unwrap()
+}
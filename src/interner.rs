@@ -0,0 +1,277 @@
+//! [`Interner`], a table that frees its strings when dropped.
+//!
+//! The tables behind [`Istr`](crate::Istr) leak their entries, which is what lets handles be
+//! `'static`. A batch job processing many independent documents would leak every document's
+//! strings for good, so an [`Interner`] owns its strings instead: its handles, [`Sym`]s, borrow
+//! the interner, and dropping the interner frees everything at once. A handle can't outlive its
+//! interner:
+//!
+//! ```compile_fail
+//! let sym = {
+//!     let interner = lsi::Interner::new();
+//!     interner.get_or_intern("dangling")
+//! };
+//! ```
+//!
+//! Unlike a [`LocalInterner`](crate::LocalInterner), whose handles are ordinary leaked
+//! [`Istr`](crate::Istr)s, nothing an [`Interner`] hands out outlives it.
+//!
+//! Strings are copied into chunks owned by the interner, so interning doesn't make an
+//! allocation per string, and dropping the interner frees a handful of chunks.
+use std::cell::RefCell;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ptr::NonNull;
+
+use hashbrown::HashTable;
+
+use crate::TableHasher;
+
+/// The size of an interner's chunks, except for strings larger than a quarter of it, which get
+/// a chunk of their own.
+const CHUNK: usize = 16 * 1024;
+
+/// An interner whose strings live as long as it does.
+///
+/// It is `Send`, so a job can be handed to another thread with its interner, but not `Sync`:
+/// interning goes through a `RefCell` rather than a lock.
+///
+/// ```
+/// use lsi::Interner;
+///
+/// for document in ["a b a", "c"] {
+///     let interner = Interner::new();
+///     let words: Vec<_> = document.split(' ').map(|w| interner.get_or_intern(w)).collect();
+///     assert_eq!(words[0], words[words.len() - 1]);
+/// } // The document's strings are freed here.
+/// ```
+///
+/// Strings are hashed with `S`, the tables' [`TableHasher`] unless another is picked with
+/// [`Interner::with_hasher`], e.g. to match the rest of a program's hashing.
+#[derive(Default)]
+pub struct Interner<S = TableHasher> {
+    inner: RefCell<Inner<S>>,
+}
+
+#[derive(Default)]
+struct Inner<S> {
+    hasher: S,
+    /// Points into `chunks`.
+    set: HashTable<*const str>,
+    /// Every chunk, the current one last. Chunks are never reallocated or freed before the
+    /// interner is dropped, so strings stored in them don't move.
+    chunks: Vec<Chunk>,
+    /// The bytes of the current chunk in use.
+    used: usize,
+    allocated: usize,
+}
+
+// SAFETY: the pointers are into chunks owned by the interner, which move with it.
+unsafe impl<S: Send> Send for Inner<S> {}
+
+/// A chunk's bytes, owned through a raw pointer rather than a `Box`: writing a string through
+/// a `&mut` to a `Box`'s bytes would invalidate the `&str`s of the strings already there.
+struct Chunk(NonNull<[u8]>);
+
+impl Chunk {
+    fn new(len: usize) -> Self {
+        Chunk(NonNull::from(Box::leak(vec![0; len].into_boxed_slice())))
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Copy `s` to the bytes at `offset`, which no stored string overlaps.
+    fn store(&self, offset: usize, s: &str) -> *const str {
+        assert!(offset + s.len() <= self.len());
+        // SAFETY: in bounds, and nothing references the bytes yet.
+        unsafe {
+            let stored = self.0.as_ptr().cast::<u8>().add(offset);
+            std::ptr::copy_nonoverlapping(s.as_ptr(), stored, s.len());
+            // Copied from a `&str`.
+            std::ptr::slice_from_raw_parts(stored, s.len()) as *const str
+        }
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        // SAFETY: leaked from a `Box` in `Chunk::new`.
+        drop(unsafe { Box::from_raw(self.0.as_ptr()) });
+    }
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: BuildHasher> Interner<S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Interner {
+            inner: RefCell::new(Inner {
+                hasher,
+                set: HashTable::new(),
+                chunks: Vec::new(),
+                used: 0,
+                allocated: 0,
+            }),
+        }
+    }
+
+    pub fn get_or_intern(&self, s: &str) -> Sym<'_> {
+        if let Some(sym) = self.get(s) {
+            return sym;
+        }
+        let mut inner = self.inner.borrow_mut();
+        let stored = inner.store(s);
+        let hash = inner.hasher.hash_one(s);
+        let Inner { hasher, set, .. } = &mut *inner;
+        // SAFETY: stored strings live as long as the interner.
+        set.insert_unique(hash, stored, |&p| hasher.hash_one(unsafe { &*p }));
+        // SAFETY: as above.
+        Sym(unsafe { &*stored })
+    }
+
+    /// The handle of `s`, if it has been interned.
+    pub fn get(&self, s: &str) -> Option<Sym<'_>> {
+        let inner = self.inner.borrow();
+        let hash = inner.hasher.hash_one(s);
+        // SAFETY: stored strings live as long as the interner.
+        let &found = inner.set.find(hash, |&p| unsafe { &*p } == s)?;
+        Some(Sym(unsafe { &*found }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.borrow().set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The bytes of the chunks holding the strings, freed when the interner is dropped.
+    pub fn allocated_bytes(&self) -> usize {
+        self.inner.borrow().allocated
+    }
+}
+
+impl<S> Inner<S> {
+    /// Copy `s` into a chunk.
+    fn store(&mut self, s: &str) -> *const str {
+        let fits = self
+            .chunks
+            .last()
+            .is_some_and(|chunk| chunk.len() - self.used >= s.len());
+        if !fits {
+            let chunk = Chunk::new(CHUNK.max(s.len()));
+            self.allocated += chunk.len();
+            if s.len() > CHUNK / 4 && !self.chunks.is_empty() {
+                // Keep filling the current chunk after this string.
+                let stored = chunk.store(0, s);
+                let last = self.chunks.len() - 1;
+                self.chunks.insert(last, chunk);
+                return stored;
+            }
+            self.chunks.push(chunk);
+            self.used = 0;
+        }
+        let stored = self.chunks.last().unwrap().store(self.used, s);
+        self.used += s.len();
+        stored
+    }
+}
+
+impl<S: BuildHasher> fmt::Debug for Interner<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interner")
+            .field("len", &self.len())
+            .field("allocated_bytes", &self.allocated_bytes())
+            .finish()
+    }
+}
+
+/// A string interned in an [`Interner`], valid for as long as the interner is borrowed.
+///
+/// Like [`Istr`](crate::Istr), handles compare and hash by address, so handles from different
+/// interners are never equal.
+#[derive(Copy, Clone)]
+pub struct Sym<'a>(&'a str);
+
+impl<'a> Sym<'a> {
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+}
+
+impl PartialEq for Sym<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Sym<'_> {}
+
+impl PartialEq<&str> for Sym<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Hash for Sym<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0.as_ptr() as usize).hash(state)
+    }
+}
+
+impl AsRef<str> for Sym<'_> {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+impl fmt::Debug for Sym<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.0, f)
+    }
+}
+
+impl fmt::Display for Sym<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interner() {
+        let interner = Interner::new();
+        let a = interner.get_or_intern("alpha");
+        assert_eq!(a, interner.get_or_intern("alpha"));
+        assert_ne!(a, interner.get_or_intern("beta"));
+        assert_eq!(interner.get("alpha"), Some(a));
+        assert_eq!(interner.get("gamma"), None);
+        assert_eq!(interner.get_or_intern(""), interner.get_or_intern(""));
+        // A long string gets a chunk of its own, and the current chunk keeps being filled.
+        let long = "x".repeat(CHUNK);
+        assert_eq!(interner.get_or_intern(&long).as_str(), long);
+        let c = interner.get_or_intern("after long");
+        assert_eq!(interner.allocated_bytes(), 2 * CHUNK);
+        let words: Vec<String> = (0..5000).map(|i| format!("word-{}", i)).collect();
+        let syms: Vec<Sym> = words.iter().map(|w| interner.get_or_intern(w)).collect();
+        assert_eq!(interner.len(), 5005);
+        assert!(words.iter().zip(&syms).all(|(w, s)| s == &w.as_str()));
+        assert_eq!(c, "after long");
+        assert_eq!(a.as_str(), "alpha");
+        let other = Interner::with_hasher(std::hash::BuildHasherDefault::<
+            std::collections::hash_map::DefaultHasher,
+        >::default());
+        assert_ne!(other.get_or_intern("alpha"), a);
+        assert_eq!(other.get_or_intern("alpha"), other.get("alpha").unwrap());
+    }
+}
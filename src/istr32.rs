@@ -0,0 +1,171 @@
+//! [`Istr32`], a 4-byte handle for memory-sensitive data structures such as AST and IR nodes.
+//!
+//! An `Istr32` is an index into a process-wide, append-only array of [`Istr`]s. The first
+//! conversion of an `Istr` assigns it the next index for good; converting it again looks the
+//! index up. Converting back, or reading the string, indexes the array without locking.
+//!
+//! ```
+//! use lsi::{InternTable, Istr, Istr32};
+//!
+//! let table = InternTable::new();
+//! let name = Istr32::from(table.get_or_intern("node"));
+//! assert_eq!(std::mem::size_of::<Option<Istr32>>(), 4);
+//! assert_eq!(name.as_str(), "node");
+//! assert_eq!(Istr::from(name), table.get_or_intern("node"));
+//! assert_eq!(Istr32::from(table.get_or_intern("node")), name);
+//! ```
+use std::fmt;
+use std::num::NonZeroU32;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+use std::sync::RwLock;
+
+use crate::{Header, Istr, IstrMap};
+
+/// Segment `k` of the array holds indices `2^k - 1 .. 2^(k + 1) - 1`, so the array grows
+/// without ever moving a slot.
+static SEGMENTS: [AtomicPtr<AtomicPtr<Header>>; 32] = [const { AtomicPtr::new(null_mut()) }; 32];
+
+/// The number of indices assigned.
+static LEN: AtomicU32 = AtomicU32::new(0);
+
+/// The index of every converted handle. Appends to the array are made holding its write lock.
+static INDICES: RwLock<Option<IstrMap<Istr32>>> = RwLock::new(None);
+
+/// A 4-byte handle to an interned string. `Option<Istr32>` is 4 bytes too.
+///
+/// Equality and hashing compare indices, which, like `Istr` pointers, are one per string per
+/// table. At most `u32::MAX` strings can be converted.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Istr32(NonZeroU32);
+
+impl Istr32 {
+    /// Intern `s` into the active table and convert it.
+    #[cfg(feature = "global")]
+    pub fn new(s: &str) -> Self {
+        Istr::new(s).into()
+    }
+
+    /// The handle's index, from 0 in the order strings were first converted.
+    pub fn index(self) -> u32 {
+        self.0.get() - 1
+    }
+
+    /// The handle with index `index`, if one has been assigned.
+    pub fn from_index(index: u32) -> Option<Self> {
+        (index < LEN.load(Ordering::Acquire)).then(|| Istr32(NonZeroU32::new(index + 1).unwrap()))
+    }
+
+    pub fn as_istr(self) -> Istr {
+        let (segment, offset) = locate(self.index());
+        // SAFETY: the handle's index was assigned, so its segment was published, and its slot
+        // was written, before the handle was returned.
+        let slot = unsafe { &*SEGMENTS[segment].load(Ordering::Acquire).add(offset) };
+        // SAFETY: slots hold handles' pointers.
+        Istr(unsafe { std::ptr::NonNull::new_unchecked(slot.load(Ordering::Acquire)) })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.as_istr().as_str()
+    }
+
+    /// The number of handles assigned so far.
+    pub fn count() -> u32 {
+        LEN.load(Ordering::Acquire)
+    }
+}
+
+/// The segment and offset within it of `index`.
+fn locate(index: u32) -> (usize, usize) {
+    let n = index as u64 + 1;
+    let segment = 63 - n.leading_zeros() as usize;
+    (segment, (n - (1 << segment)) as usize)
+}
+
+impl From<Istr> for Istr32 {
+    fn from(s: Istr) -> Self {
+        if let Some(&id) = INDICES.read().unwrap().as_ref().and_then(|m| m.get(&s)) {
+            return id;
+        }
+        let mut indices = INDICES.write().unwrap();
+        let indices = indices.get_or_insert_with(IstrMap::default);
+        if let Some(&id) = indices.get(&s) {
+            return id;
+        }
+        let index = LEN.load(Ordering::Relaxed);
+        assert!(index < u32::MAX, "more than u32::MAX Istr32 handles");
+        let (segment, offset) = locate(index);
+        let mut slots = SEGMENTS[segment].load(Ordering::Relaxed);
+        if slots.is_null() {
+            let new: Box<[AtomicPtr<Header>]> = (0..1usize << segment)
+                .map(|_| AtomicPtr::new(null_mut()))
+                .collect();
+            slots = Box::leak(new).as_mut_ptr();
+            SEGMENTS[segment].store(slots, Ordering::Release);
+        }
+        // SAFETY: `offset` is within the segment, which is never freed.
+        unsafe { &*slots.add(offset) }.store(s.0.as_ptr(), Ordering::Release);
+        LEN.store(index + 1, Ordering::Release);
+        let id = Istr32(NonZeroU32::new(index + 1).unwrap());
+        indices.insert(s, id);
+        id
+    }
+}
+
+impl From<Istr32> for Istr {
+    fn from(s: Istr32) -> Self {
+        s.as_istr()
+    }
+}
+
+impl PartialEq<&str> for Istr32 {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for Istr32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Istr32({}, {:?})", self.index(), self.as_str())
+    }
+}
+
+impl fmt::Display for Istr32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InternTable;
+
+    #[test]
+    fn test_locate() {
+        assert_eq!(locate(0), (0, 0));
+        assert_eq!(locate(1), (1, 0));
+        assert_eq!(locate(2), (1, 1));
+        assert_eq!(locate(3), (2, 0));
+        assert_eq!(locate(u32::MAX - 1), (31, (1 << 31) - 1));
+    }
+
+    #[test]
+    fn test_istr32() {
+        let table = InternTable::new();
+        let handles: Vec<Istr> = (0..100)
+            .map(|i| table.get_or_intern(&format!("istr32-{}", i)))
+            .chain([table.get_or_intern("")])
+            .collect();
+        let ids: Vec<Istr32> = handles.iter().map(|&s| s.into()).collect();
+        for (&s, &id) in handles.iter().zip(&ids) {
+            assert_eq!(Istr32::from(s), id);
+            assert_eq!(id.as_istr(), s);
+            assert_eq!(Istr32::from_index(id.index()), Some(id));
+        }
+        assert_eq!(ids[7], "istr32-7");
+        assert_eq!(ids[100].as_str(), "");
+        assert!(Istr32::count() >= 101);
+        assert_eq!(Istr32::from_index(u32::MAX - 1), None);
+    }
+}
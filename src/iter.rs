@@ -0,0 +1,107 @@
+//! [`InternExt`], interning the items of an iterator in batches: `lines.interned()` into the
+//! active table, or `lines.interned_in(&table)`.
+//!
+//! ```
+//! use lsi::{InternExt, InternTable};
+//!
+//! let table = InternTable::new();
+//! let text = "GET /\nPOST /login\nGET /\n";
+//! let methods: Vec<lsi::Istr> = text
+//!     .lines()
+//!     .map(|line| line.split(' ').next().unwrap())
+//!     .interned_in(&table)
+//!     .collect();
+//! assert_eq!(methods[0], methods[2]);
+//! ```
+use std::vec;
+
+#[cfg(feature = "global")]
+use crate::ActiveTable;
+use crate::{InternTable, InternerBackend, Istr};
+
+/// The number of items [`Interned`] reads ahead and interns at a time.
+pub const BATCH: usize = 256;
+
+/// Adds [`interned`](InternExt::interned) to every iterator of strings.
+pub trait InternExt: Iterator + Sized
+where
+    Self::Item: AsRef<str>,
+{
+    /// Intern every item into the active table. Items are read [`BATCH`] at a time and each
+    /// batch is interned with [`InternTable::get_or_intern_many`], so a batch costs one lock
+    /// acquisition per shard with misses rather than one per item. Panics like
+    /// [`Istr::new`](crate::Istr::new) for items that can't be interned, see [`ActiveTable`].
+    #[cfg(feature = "global")]
+    fn interned(self) -> Interned<'static, Self, ActiveTable> {
+        self.interned_in(&ActiveTable)
+    }
+
+    /// Like [`InternExt::interned`], into `table`, which may be any [`InternerBackend`].
+    fn interned_in<B: InternerBackend + ?Sized>(self, table: &B) -> Interned<'_, Self, B> {
+        Interned {
+            inner: self,
+            table,
+            batch: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<I: Iterator> InternExt for I where I::Item: AsRef<str> {}
+
+/// The iterator returned by [`InternExt::interned`]. It reads up to [`BATCH`] items of the
+/// inner iterator ahead of the handles it has returned.
+#[derive(Debug)]
+pub struct Interned<'a, I, B: ?Sized = InternTable> {
+    inner: I,
+    table: &'a B,
+    batch: vec::IntoIter<Istr>,
+}
+
+impl<I: Iterator, B: InternerBackend + ?Sized> Iterator for Interned<'_, I, B>
+where
+    I::Item: AsRef<str>,
+{
+    type Item = Istr;
+
+    fn next(&mut self) -> Option<Istr> {
+        if let Some(fast_str) = self.batch.next() {
+            return Some(fast_str);
+        }
+        let strings: Vec<I::Item> = self.inner.by_ref().take(BATCH).collect();
+        if strings.is_empty() {
+            return None;
+        }
+        let strings: Vec<&str> = strings.iter().map(AsRef::as_ref).collect();
+        self.batch = self.table.get_or_intern_many(&strings).into_iter();
+        self.batch.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.batch.len();
+        let (lower, upper) = self.inner.size_hint();
+        (
+            lower.saturating_add(buffered),
+            upper.and_then(|upper| upper.checked_add(buffered)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interned_in() {
+        let table = InternTable::new();
+        let words: Vec<String> = (0..1000).map(|i| format!("word-{}", i % 300)).collect();
+        let mut iter = words.iter().interned_in(&table);
+        assert_eq!(iter.size_hint(), (1000, Some(1000)));
+        assert_eq!(iter.next().unwrap(), "word-0");
+        assert_eq!(iter.size_hint(), (999, Some(999)));
+        let rest: Vec<Istr> = iter.collect();
+        assert_eq!(rest.len(), 999);
+        assert_eq!(rest[299], table.get_or_intern("word-0"));
+        assert_eq!(table.len(), 300);
+        assert_eq!(std::iter::empty::<&str>().interned_in(&table).next(), None);
+    }
+}
@@ -0,0 +1,223 @@
+//! Interned RFC 6901 JSON pointers.
+//!
+//! A [`JsonPointer`] is parsed once, after which the pointer itself and each of its reference
+//! tokens are interned strings: comparing two pointers is a pointer comparison, and applying one
+//! to a document (with the `json` feature) walks the tokens without re-parsing or unescaping.
+//!
+//! ```
+//! use lsi::json_pointer::JsonPointer;
+//!
+//! let p = JsonPointer::parse("/servers/0/a~1b").unwrap();
+//! assert_eq!(p.tokens(), ["servers", "0", "a/b"]);
+//! assert_eq!(p, JsonPointer::parse("/servers/0/a~1b").unwrap());
+//! ```
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+use crate::{Error, Istr};
+
+static TOKENS: RwLock<Option<HashMap<Istr, &'static [Istr]>>> = RwLock::new(None);
+
+/// A parsed, interned JSON pointer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct JsonPointer {
+    pointer: Istr,
+    tokens: &'static [Istr],
+}
+
+impl JsonPointer {
+    /// Parse a pointer in its string form, e.g. `/a/b~1c`. The empty string is the pointer to
+    /// the whole document. Returns `None` if the pointer is not well-formed.
+    ///
+    /// # Panics
+    ///
+    /// If the pointer or one of its tokens can't be interned, see [`JsonPointer::try_parse`].
+    pub fn parse(s: &str) -> Option<Self> {
+        match Self::try_parse(s) {
+            Ok(pointer) => pointer,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [`JsonPointer::parse`], but returns the error of [`Istr::try_new`] if the pointer
+    /// or one of its tokens can't be interned, instead of panicking.
+    pub fn try_parse(s: &str) -> Result<Option<Self>, Error> {
+        let pointer = Istr::try_new(s)?;
+        if let Some(&tokens) = TOKENS
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|m| m.get(&pointer))
+        {
+            return Ok(Some(JsonPointer { pointer, tokens }));
+        }
+        let tokens = match s.strip_prefix('/') {
+            Some(rest) => match rest.split('/').map(unescape).collect::<Option<Vec<_>>>() {
+                Some(tokens) => tokens,
+                None => return Ok(None),
+            },
+            None if s.is_empty() => Vec::new(),
+            None => return Ok(None),
+        };
+        let tokens = tokens
+            .iter()
+            .map(|token| Istr::try_new(token))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut lock = TOKENS.write().unwrap();
+        let tokens = *lock
+            .get_or_insert_with(HashMap::new)
+            .entry(pointer)
+            .or_insert_with(|| Box::leak(tokens.into_boxed_slice()));
+        Ok(Some(JsonPointer { pointer, tokens }))
+    }
+
+    /// The pointer to the whole document.
+    pub fn root() -> Self {
+        JsonPointer {
+            pointer: Istr::new(""),
+            tokens: &[],
+        }
+    }
+
+    pub fn as_istr(&self) -> Istr {
+        self.pointer
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.pointer.as_str()
+    }
+
+    /// The unescaped reference tokens, e.g. `["a", "b/c"]` for `/a/b~1c`.
+    pub fn tokens(&self) -> &'static [Istr] {
+        self.tokens
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// The pointer to the value containing this one, or `None` for the root.
+    ///
+    /// # Panics
+    ///
+    /// If the parent can't be interned, see [`JsonPointer::try_parent`].
+    pub fn parent(&self) -> Option<JsonPointer> {
+        let (last, _) = self.as_str().rmatch_indices('/').next()?;
+        JsonPointer::parse(&self.as_str()[..last])
+    }
+
+    /// Like [`JsonPointer::parent`], but returns the error of [`Istr::try_new`] if the parent
+    /// can't be interned, instead of panicking.
+    pub fn try_parent(&self) -> Result<Option<JsonPointer>, Error> {
+        match self.as_str().rmatch_indices('/').next() {
+            Some((last, _)) => JsonPointer::try_parse(&self.as_str()[..last]),
+            None => Ok(None),
+        }
+    }
+
+    /// The value this pointer refers to in `value`, if there is one.
+    #[cfg(feature = "json")]
+    pub fn get<'v>(&self, value: &'v serde_json::Value) -> Option<&'v serde_json::Value> {
+        self.tokens
+            .iter()
+            .try_fold(value, |value, token| match value {
+                serde_json::Value::Object(map) => map.get(token.as_str()),
+                serde_json::Value::Array(list) => list.get(index(token.as_str())?),
+                _ => None,
+            })
+    }
+
+    #[cfg(feature = "json")]
+    pub fn get_mut<'v>(
+        &self,
+        value: &'v mut serde_json::Value,
+    ) -> Option<&'v mut serde_json::Value> {
+        self.tokens
+            .iter()
+            .try_fold(value, |value, token| match value {
+                serde_json::Value::Object(map) => map.get_mut(token.as_str()),
+                serde_json::Value::Array(list) => list.get_mut(index(token.as_str())?),
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Undo the `~0` and `~1` escapes of a reference token.
+fn unescape(token: &str) -> Option<Cow<'_, str>> {
+    if !token.contains('~') {
+        return Some(Cow::Borrowed(token));
+    }
+    let mut out = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next()? {
+                '0' => out.push('~'),
+                '1' => out.push('/'),
+                _ => return None,
+            },
+            c => out.push(c),
+        }
+    }
+    Some(Cow::Owned(out))
+}
+
+/// Parse an array index token: decimal, without leading zeros.
+#[cfg(feature = "json")]
+fn index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    if !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let p = JsonPointer::parse("/a~0b/~1/").unwrap();
+        assert_eq!(p.tokens(), ["a~b", "/", ""]);
+        assert_eq!(p.as_str(), "/a~0b/~1/");
+        assert_eq!(p.parent().unwrap().tokens(), ["a~b", "/"]);
+        assert!(JsonPointer::parse("").unwrap().is_root());
+        assert_eq!(
+            JsonPointer::parse("/x").unwrap().parent(),
+            Some(JsonPointer::root())
+        );
+        assert_eq!(JsonPointer::root().parent(), None);
+        assert_eq!(JsonPointer::parse("a"), None);
+        assert_eq!(JsonPointer::parse("/~2"), None);
+        assert_eq!(JsonPointer::parse("/~"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_get() {
+        let mut doc = serde_json::json!({"a": [{"b/c": 1}, 2], "": 3});
+        let get = |p: &str| JsonPointer::parse(p).unwrap().get(&doc).cloned();
+        assert_eq!(get("/a/0/b~1c"), Some(serde_json::json!(1)));
+        assert_eq!(get("/"), Some(serde_json::json!(3)));
+        assert_eq!(get(""), Some(doc.clone()));
+        assert_eq!(get("/a/01"), None);
+        assert_eq!(get("/a/2"), None);
+        assert_eq!(get("/a/-"), None);
+        *JsonPointer::parse("/a/1")
+            .unwrap()
+            .get_mut(&mut doc)
+            .unwrap() = serde_json::json!(5);
+        assert_eq!(doc["a"][1], 5);
+    }
+}
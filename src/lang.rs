@@ -0,0 +1,242 @@
+//! BCP-47 language tags.
+//!
+//! Tags are canonicalized with the casing conventions of RFC 5646 (`en-us` becomes `en-US`,
+//! `zh-hant-tw` becomes `zh-Hant-TW`) before being interned, and each tag keeps its subtags
+//! as interned strings, so matching a tag against a language range is a handful of pointer
+//! comparisons rather than a case-insensitive string walk.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+use crate::Istr;
+
+/// Tags that are interned by [`prefill`]. The list covers the locales most commonly seen in
+/// `Accept-Language` headers.
+pub static COMMON_TAGS: &[&str] = &[
+    "ar",
+    "ar-EG",
+    "ar-SA",
+    "bn",
+    "cs",
+    "cs-CZ",
+    "da",
+    "da-DK",
+    "de",
+    "de-AT",
+    "de-CH",
+    "de-DE",
+    "el",
+    "el-GR",
+    "en",
+    "en-AU",
+    "en-CA",
+    "en-GB",
+    "en-IE",
+    "en-IN",
+    "en-NZ",
+    "en-US",
+    "en-ZA",
+    "es",
+    "es-419",
+    "es-AR",
+    "es-ES",
+    "es-MX",
+    "es-US",
+    "fa",
+    "fi",
+    "fi-FI",
+    "fr",
+    "fr-BE",
+    "fr-CA",
+    "fr-CH",
+    "fr-FR",
+    "he",
+    "he-IL",
+    "hi",
+    "hi-IN",
+    "hu",
+    "hu-HU",
+    "id",
+    "id-ID",
+    "it",
+    "it-IT",
+    "ja",
+    "ja-JP",
+    "ko",
+    "ko-KR",
+    "ms",
+    "nb",
+    "nb-NO",
+    "nl",
+    "nl-BE",
+    "nl-NL",
+    "pl",
+    "pl-PL",
+    "pt",
+    "pt-BR",
+    "pt-PT",
+    "ro",
+    "ro-RO",
+    "ru",
+    "ru-RU",
+    "sk",
+    "sv",
+    "sv-SE",
+    "th",
+    "th-TH",
+    "tr",
+    "tr-TR",
+    "uk",
+    "uk-UA",
+    "vi",
+    "vi-VN",
+    "zh",
+    "zh-CN",
+    "zh-HK",
+    "zh-Hans",
+    "zh-Hans-CN",
+    "zh-Hant",
+    "zh-Hant-HK",
+    "zh-Hant-TW",
+    "zh-TW",
+];
+
+static SUBTAGS: RwLock<Option<HashMap<Istr, &'static [Istr]>>> = RwLock::new(None);
+
+/// A canonicalized, interned BCP-47 language tag.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LanguageTag {
+    tag: Istr,
+    subtags: &'static [Istr],
+}
+
+impl LanguageTag {
+    /// Parse and canonicalize a tag. Both `-` and `_` are accepted as separators.
+    /// Returns `None` if the tag is not well-formed.
+    pub fn parse(s: &str) -> Option<Self> {
+        let tag = canonicalize(s)?;
+        if let Some(&subtags) = SUBTAGS.read().unwrap().as_ref().and_then(|m| m.get(&tag)) {
+            return Some(LanguageTag { tag, subtags });
+        }
+        let subtags: Vec<Istr> = tag.as_str().split('-').map(Istr::new).collect();
+        let mut lock = SUBTAGS.write().unwrap();
+        let subtags = *lock
+            .get_or_insert_with(HashMap::new)
+            .entry(tag)
+            .or_insert_with(|| Box::leak(subtags.into_boxed_slice()));
+        Some(LanguageTag { tag, subtags })
+    }
+
+    pub fn as_istr(&self) -> Istr {
+        self.tag
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.tag.as_str()
+    }
+
+    /// The interned subtags of this tag, e.g. `["zh", "Hant", "TW"]`.
+    pub fn subtags(&self) -> &'static [Istr] {
+        self.subtags
+    }
+
+    /// The primary language subtag, e.g. `en` for `en-US`.
+    pub fn language(&self) -> Istr {
+        self.subtags[0]
+    }
+
+    /// Basic filtering as described in RFC 4647 §3.3.1: the range `*` matches every tag,
+    /// otherwise the range must equal the tag or be a prefix of it ending at a subtag boundary.
+    pub fn matches(&self, range: &LanguageTag) -> bool {
+        if range.subtags.len() == 1 && range.subtags[0].as_str() == "*" {
+            return true;
+        }
+        range.subtags.len() <= self.subtags.len()
+            && range
+                .subtags
+                .iter()
+                .zip(self.subtags)
+                .all(|(r, t)| r.0 == t.0)
+    }
+}
+
+impl fmt::Display for LanguageTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Intern every tag in [`COMMON_TAGS`].
+pub fn prefill() {
+    for tag in COMMON_TAGS {
+        LanguageTag::parse(tag);
+    }
+}
+
+/// Canonicalize the casing of a language tag and intern the result. Returns `None` if the tag
+/// is not well-formed.
+pub fn canonicalize(s: &str) -> Option<Istr> {
+    let mut out = String::with_capacity(s.len());
+    // Script and region casing only applies before the first singleton (extensions and
+    // private use subtags are always lowercase).
+    let mut after_singleton = false;
+    for (i, subtag) in s.split(['-', '_']).enumerate() {
+        // `*` is only meaningful in language ranges, but accepting it lets ranges share this path.
+        let valid = subtag == "*"
+            || (!subtag.is_empty()
+                && subtag.len() <= 8
+                && subtag.bytes().all(|b| b.is_ascii_alphanumeric()));
+        if !valid {
+            return None;
+        }
+        if i > 0 {
+            out.push('-');
+        }
+        let alpha = subtag.bytes().all(|b| b.is_ascii_alphabetic());
+        if i == 0 || after_singleton {
+            out.extend(subtag.chars().map(|c| c.to_ascii_lowercase()));
+        } else if subtag.len() == 4 && alpha {
+            let mut chars = subtag.chars();
+            out.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+            out.extend(chars.map(|c| c.to_ascii_lowercase()));
+        } else if subtag.len() == 2 && alpha {
+            out.extend(subtag.chars().map(|c| c.to_ascii_uppercase()));
+        } else {
+            out.extend(subtag.chars().map(|c| c.to_ascii_lowercase()));
+        }
+        if subtag.len() == 1 {
+            after_singleton = true;
+        }
+    }
+    Some(Istr::new(&out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(canonicalize("en-us").unwrap(), "en-US");
+        assert_eq!(canonicalize("ZH_hant_tw").unwrap(), "zh-Hant-TW");
+        assert_eq!(canonicalize("de-ch-x-phonebk").unwrap(), "de-CH-x-phonebk");
+        assert_eq!(canonicalize("es-419").unwrap(), "es-419");
+        assert!(canonicalize("en--us").is_none());
+        assert!(canonicalize("en-toolongsubtag").is_none());
+    }
+
+    #[test]
+    fn test_matches() {
+        prefill();
+        let tag = LanguageTag::parse("zh-hant-tw").unwrap();
+        assert!(tag.matches(&LanguageTag::parse("zh").unwrap()));
+        assert!(tag.matches(&LanguageTag::parse("ZH-Hant").unwrap()));
+        assert!(tag.matches(&LanguageTag::parse("*").unwrap()));
+        assert!(!tag.matches(&LanguageTag::parse("zh-Hans").unwrap()));
+        assert!(!LanguageTag::parse("zh").unwrap().matches(&tag));
+        assert_eq!(
+            tag.subtags()[0].0,
+            LanguageTag::parse("zh").unwrap().language().0
+        );
+    }
+}
@@ -1,62 +1,503 @@
-use std::alloc::{alloc, Layout, LayoutError};
+use std::alloc::{alloc, handle_alloc_error, Layout, LayoutError};
+#[cfg(feature = "global")]
+use std::borrow::Cow;
 use std::hash::BuildHasher;
+use std::mem::size_of;
 use std::ptr::{addr_of, addr_of_mut, NonNull};
-use std::sync::RwLock;
-use hashbrown::hash_map::DefaultHashBuilder;
 use hashbrown::HashSet;
 
+use crate::hasher::TABLE_HASHER;
+use crate::lock::RwLock;
+
+pub mod analysis;
+mod arena;
+#[cfg(feature = "global")]
+pub mod atoms;
+#[cfg(feature = "global")]
+pub mod bake;
+mod backend;
+#[cfg(feature = "global")]
+pub mod budget;
+#[cfg(feature = "stable-hash")]
+pub mod buckets;
+pub mod case;
+#[cfg(feature = "checkpoint")]
+pub mod checkpoint;
+pub mod codes;
+pub mod collections;
+#[cfg(all(feature = "serde", feature = "global"))]
+pub mod config;
+#[cfg(feature = "counting-alloc")]
+pub mod counting;
+mod cow;
+#[cfg(feature = "global")]
+mod convert;
+#[cfg(any(feature = "rustc-demangle", feature = "cpp_demangle"))]
+pub mod demangle;
+#[cfg(all(feature = "serde", feature = "global"))]
+pub mod dict;
+#[cfg(feature = "global")]
+pub mod domain;
+#[cfg(feature = "background")]
+mod deferred;
+mod diff;
+mod error;
+mod fat;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod filter;
+#[cfg(feature = "global")]
+pub mod frozen;
+#[cfg(feature = "flags")]
+pub mod flags;
+#[cfg(feature = "async")]
+mod future;
+#[cfg(feature = "hot-tier")]
+pub mod hot;
+#[cfg(feature = "unicode-ident")]
+pub mod ident;
+mod hasher;
+mod index;
+#[cfg(feature = "global")]
+mod init;
+#[cfg(feature = "global")]
+pub mod intercept;
+mod interner;
+mod istr32;
+pub mod iter;
+#[cfg(feature = "global")]
+pub mod json_pointer;
+#[cfg(feature = "global")]
+pub mod lang;
+mod local;
+mod lock;
+mod maybe;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "global")]
+pub mod newtype;
+#[cfg(feature = "global")]
+pub mod observe;
+#[cfg(feature = "persist")]
+pub mod persist;
+pub mod pool;
+pub mod pretty;
+#[cfg(feature = "profiling")]
+pub mod profiling;
+mod raw;
+pub mod realtime;
+#[cfg(feature = "refcount")]
+pub mod rc;
+pub mod replicate;
+pub mod sample;
+#[cfg(feature = "global")]
+pub mod schema;
+#[cfg(feature = "global")]
+mod shared;
+mod small;
+mod sort;
+mod table_stats;
+#[cfg(feature = "stats")]
+pub mod stats;
+#[cfg(feature = "suffix-index")]
+pub mod suffix;
+pub mod tag;
+#[cfg(feature = "global")]
+pub mod thread_cache;
+#[cfg(feature = "toml-edit")]
+pub mod toml_edit;
+#[cfg(feature = "tools")]
+pub mod tools;
+pub mod verify;
+pub mod vocab;
+#[cfg(feature = "wide")]
+pub mod wide;
+#[cfg(any(feature = "async", feature = "background"))]
+mod worker;
+
+#[cfg(feature = "background")]
+pub use deferred::{intern_deferred, ResolveLater};
+pub use analysis::analyze;
+#[cfg(feature = "global")]
+pub use backend::ActiveTable;
+pub use backend::InternerBackend;
+pub use collections::{
+    BuildIstrHasher, IstrEntry, IstrHasher, IstrMap, IstrMultiMap, IstrSet, SmallSet,
+};
+pub use cow::CowIstr;
+#[cfg(feature = "global")]
+pub use convert::{bulk_load_unique, intern_table, intern_vec, resolve_vec};
+pub use diff::{diff, DiffOp};
+pub use error::Error;
+pub use fat::FatIstr;
+#[cfg(feature = "global")]
+pub use filter::filter_snapshot;
+pub use filter::XorFilter;
+#[cfg(feature = "global")]
+pub use frozen::freeze;
+pub use hasher::TableHasher;
+#[cfg(feature = "global")]
+pub use init::{init, Config, InitError};
+pub use interner::{Interner, Sym};
+pub use istr32::Istr32;
+pub use iter::{InternExt, Interned};
+#[cfg(feature = "async")]
+pub use future::{intern_async, InternFuture};
+pub use local::LocalInterner;
+pub use maybe::MaybeInterned;
+pub use raw::{abi_version, RawIstr, ABI_VERSION};
+#[cfg(feature = "global")]
+pub use shared::{active_table, attach, table_fingerprint, table_handle, AttachError, TableHandle};
+pub use small::{SmallIstr, INLINE_CAP};
+pub use sort::{sort, sort_by_cached_collation, Collator};
+#[cfg(feature = "global")]
+pub use table_stats::stats;
+pub use table_stats::TableStats;
+pub use tag::{tag, Tag};
+#[cfg(feature = "global")]
+pub use verify::verify;
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "phf")]
+    pub use phf;
+    #[cfg(feature = "serde")]
+    pub use serde;
+
+    /// A handle to `table` as if it were another copy's, for the tests of attaching.
+    #[cfg(feature = "global")]
+    pub fn table_handle_of(table: &'static crate::InternTable) -> crate::TableHandle {
+        crate::shared::handle_of(table)
+    }
+}
+
 /// The primary type of this crate.
 /// Internally, it is a pointer to a leaked [`InternedData`] struct, which itself
 /// is a representation of a string.
 /// Because it is a pointer, it is `Copy`, and equality checking is a single instruction.
+///
+/// It is `#[repr(transparent)]` over a non-null pointer, which is part of its contract: an
+/// `Istr` has the layout of a pointer, and `Option<Istr>` that of a nullable one, with `None`
+/// as null. See [`Istr::into_ptr`] for handing handles to code that only stores pointers.
 #[derive(Copy, Clone, Debug)]
-pub struct Istr(NonNull<*const u8>);
+#[cfg_attr(feature = "abi_stable", derive(abi_stable::StableAbi))]
+#[repr(transparent)]
+pub struct Istr(#[cfg_attr(feature = "abi_stable", sabi(unsafe_opaque_field))] NonNull<Header>);
 
 assert_eq_size!(Istr, usize);
 assert_eq_size!(Option<Istr>, usize);
 
+// SAFETY: an `Istr` is a shared reference to leaked data that is never mutated or freed
+// (except through `InternTable::restore`, whose contract forbids using the handle afterwards),
+// no matter which table or thread created it.
 unsafe impl Send for Istr {}
 
 unsafe impl Sync for Istr {}
 
+/// Panic with the error of interning `s`, naming `s` if it is missing from a table frozen with
+/// [`OnMiss::Panic`](frozen::OnMiss::Panic).
+#[cfg(feature = "global")]
+fn intern_failed(s: &str, e: Error) -> ! {
+    panic!("{}", check_on_miss(s, e))
+}
+
+/// The error of interning `s`, unless `s` is missing from a table frozen with
+/// [`OnMiss::Panic`](frozen::OnMiss::Panic), which panics naming it.
+#[cfg(feature = "global")]
+fn check_on_miss(s: &str, e: Error) -> Error {
+    if matches!(e, Error::TableFrozen) && frozen::on_miss() == frozen::OnMiss::Panic {
+        panic!("{:?} is not in the frozen table", s);
+    }
+    e
+}
+
+/// What interning a non-empty string into the active table comes to, see [`check`].
+#[cfg(feature = "global")]
+enum Checked<'a> {
+    /// The string is interned already, as this handle.
+    Found(Istr),
+    /// Insert this string, the one given or its canonical form, whose table hash is this.
+    Insert(Cow<'a, str>, u64),
+    /// Finding out whether the string is interned already would have blocked.
+    Contended,
+}
+
+/// Check the non-empty `s`, whose table hash is `hash`, before interning it into `table`, the
+/// active table: against the [maximum length](budget::set_max_len), the [`frozen`] table, the
+/// [`intercept`] hook and, for the global table, the [`budget`]. Every function interning into
+/// the active table goes through this, so that none of them gets past a check the others make.
+///
+/// Looking up whether a string is interned already may block unless `wait` is false, in which
+/// case [`Checked::Contended`] is returned instead.
+#[cfg(feature = "global")]
+fn check<'a>(table: &InternTable, s: &'a str, hash: u64, wait: bool) -> Result<Checked<'a>, Error> {
+    budget::check_len(s.len())?;
+    if let Some(found) = frozen::lookup(s, hash) {
+        return found.map(Checked::Found);
+    }
+    let hook = intercept::hook();
+    let limited = budget::is_limited(table);
+    if hook.is_none() && !limited {
+        return Ok(Checked::Insert(Cow::Borrowed(s), hash));
+    }
+    let known = |s: &str, hash| match wait {
+        true => Some(table.get_hashed(s, hash)),
+        false => table.try_get_hashed(s, hash),
+    };
+    match known(s, hash) {
+        Some(Some(fast_str)) => return Ok(Checked::Found(fast_str)),
+        Some(None) => {}
+        None => return Ok(Checked::Contended),
+    }
+    // The hook only runs for strings that aren't interned yet.
+    let (s, hash) = match hook {
+        Some(hook) => {
+            let canonical = hook(s).map_err(Error::Rejected)?;
+            if canonical.is_empty() {
+                return Ok(Checked::Found(EMPTY_FAST_STR));
+            }
+            if canonical == s {
+                (canonical, hash)
+            } else {
+                budget::check_len(canonical.len())?;
+                let hash = InternTable::hash(&canonical);
+                if let Some(found) = frozen::lookup(&canonical, hash) {
+                    return found.map(Checked::Found);
+                }
+                match known(&canonical, hash) {
+                    Some(Some(fast_str)) => return Ok(Checked::Found(fast_str)),
+                    Some(None) => {}
+                    None => return Ok(Checked::Contended),
+                }
+                (canonical, hash)
+            }
+        }
+        None => (Cow::Borrowed(s), hash),
+    };
+    if limited {
+        budget::admit(s.len())?;
+    }
+    Ok(Checked::Insert(s, hash))
+}
+
 impl Istr {
+    /// Intern `s` into the [`GLOBAL_TABLE`], or the table attached to with [`attach`].
+    ///
+    /// # Panics
+    ///
+    /// If `s` is longer than [`MAX_LEN`].
+    #[cfg(feature = "global")]
     pub fn new(s: &str) -> Self {
+        match Self::try_new(s) {
+            Ok(fast_str) => fast_str,
+            Err(e) => intern_failed(s, e),
+        }
+    }
+
+    /// Like [`Istr::new`], but returns an error instead of panicking: [`Error::TooLong`] for a
+    /// string longer than [`MAX_LEN`] or the [maximum length](budget::set_max_len),
+    /// [`Error::OverBudget`] if it doesn't fit the [`budget`], and [`Error::TableFrozen`] or
+    /// [`Error::Rejected`] if the table is [`frozen`] or the [`intercept`] hook rejects it.
+    #[cfg(feature = "global")]
+    pub fn try_new(s: &str) -> Result<Self, Error> {
         if s.is_empty() {
-            return EMPTY_FAST_STR;
+            return Ok(EMPTY_FAST_STR);
+        }
+        Self::try_intern_with(s).map(|(fast_str, _)| fast_str)
+    }
+
+    /// Like [`Istr::new`], also returning whether `s` was newly interned by this call, e.g. to
+    /// count the distinct strings of an input without looking each one up first.
+    ///
+    /// ```
+    /// use lsi::Istr;
+    ///
+    /// let (first, inserted) = Istr::intern_with("intern_with doc");
+    /// assert!(inserted);
+    /// assert_eq!(Istr::intern_with("intern_with doc"), (first, false));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// As [`Istr::new`].
+    #[cfg(feature = "global")]
+    pub fn intern_with(s: &str) -> (Self, bool) {
+        match Self::try_intern_with(s) {
+            Ok(entry) => entry,
+            Err(e) => intern_failed(s, e),
+        }
+    }
+
+    /// Like [`Istr::intern_with`], but returns the errors of [`Istr::try_new`] instead of
+    /// panicking.
+    #[cfg(feature = "global")]
+    pub fn try_intern_with(s: &str) -> Result<(Self, bool), Error> {
+        if s.is_empty() {
+            return Ok((EMPTY_FAST_STR, false));
         }
-        GLOBAL_TABLE.get_or_intern(s)
+        budget::check_len(s.len())?;
+        thread_cache::get_or_intern(s)
+    }
+
+    /// The handle of `s` if it has already been interned into the active table, without
+    /// interning it otherwise. Probing with strings that were never interned leaks nothing.
+    #[cfg(feature = "global")]
+    pub fn get(s: &str) -> Option<Self> {
+        frozen::table()
+            .and_then(|frozen| frozen.get(s))
+            .or_else(|| active_table().get(s))
     }
 
     pub fn as_str(&self) -> &'static str {
         if self.0 == EMPTY_FAST_STR.0 {
-            return "";
+            // Point at `EMPTY_DATA`, so that `data_ptr` agrees.
+            let slice = unsafe { std::slice::from_raw_parts(&EMPTY_DATA, 0) };
+            return unsafe { std::str::from_utf8_unchecked(slice) };
         }
         unsafe {
-            let ptr = self.0;
-            let len = addr_of!(*ptr.as_ptr()).read() as usize;
-            // let slice_ptr = ptr.as_ptr().add(size_of::<usize>());
-            let slice_ptr = ptr.as_ptr().add(1) as *const u8;
-            // let slice_ptr = addr_of!(*(ptr.as_ptr().add(1)));
-            let slice = std::slice::from_raw_parts(slice_ptr, len);
+            let ptr = self.0.as_ptr();
+            let len = widen(addr_of!((*ptr).len).read());
+            let slice = std::slice::from_raw_parts(self.data_ptr(), len);
             std::str::from_utf8_unchecked(slice)
         }
     }
 
+    /// The address of the string's bytes, the same as `self.as_str().as_ptr()`.
+    ///
+    /// The bytes of an interned string never move: the address stays valid and unchanged for
+    /// the rest of the process, however much the table grows, so it can be stored in place of
+    /// the handle and turned back into one with [`Istr::from_data_ptr`]. The only exception is
+    /// [`InternTable::restore`], which frees strings. All empty strings share one address.
+    pub fn data_ptr(&self) -> *const u8 {
+        if self.0 == EMPTY_FAST_STR.0 {
+            return &EMPTY_DATA;
+        }
+        // The string data directly follows the header, see `InternedData`.
+        unsafe { (self.0.as_ptr() as *const u8).add(size_of::<Header>()) }
+    }
+
+    /// Recover a handle from the address returned by [`Istr::data_ptr`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by [`Istr::data_ptr`].
+    pub unsafe fn from_data_ptr(ptr: *const u8) -> Istr {
+        if std::ptr::eq(ptr, &EMPTY_DATA) {
+            return EMPTY_FAST_STR;
+        }
+        Istr(NonNull::new_unchecked(ptr.sub(size_of::<Header>()) as *mut Header))
+    }
+
+    /// The handle as an untyped pointer, to cross an FFI boundary, sit in an
+    /// `AtomicPtr<()>`, or key a map of addresses, and come back with [`Istr::from_ptr`].
+    ///
+    /// The pointer is the handle itself: non-null, the same for every handle to the entry,
+    /// and valid for the rest of the process, except after [`InternTable::restore`]. It is
+    /// opaque, the address of the entry rather than of the string's bytes, and the empty
+    /// string's is a sentinel: don't dereference it. Casting it to a `usize` and back
+    /// round-trips too, but loses its provenance; use `expose_provenance` and
+    /// `with_exposed_provenance` for code that should run under Miri.
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicPtr, Ordering};
+    /// use lsi::{InternTable, Istr};
+    ///
+    /// let table = InternTable::new();
+    /// let s = table.get_or_intern("current");
+    /// let slot = AtomicPtr::new(s.into_ptr().cast_mut());
+    /// assert_eq!(unsafe { Istr::from_ptr(slot.load(Ordering::Acquire)) }, s);
+    /// ```
+    pub fn into_ptr(self) -> *const () {
+        self.0.as_ptr() as *const ()
+    }
+
+    /// Recover a handle from [`Istr::into_ptr`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by [`Istr::into_ptr`] in a copy of the crate with the same
+    /// [`table_fingerprint`](crate::table_fingerprint) as this one, and its entry must not
+    /// have been freed since.
+    pub unsafe fn from_ptr(ptr: *const ()) -> Istr {
+        Istr(NonNull::new_unchecked(ptr as *mut Header))
+    }
+
     pub fn len(&self) -> usize {
         if self.0 == EMPTY_FAST_STR.0 {
             return 0;
         }
-        unsafe {
-            let ptr = self.0;
-            let len = addr_of!(*ptr.as_ptr()).read() as usize;
-            len
+        widen(self.header().len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == EMPTY_FAST_STR.0
+    }
+
+    /// When this string was first interned. The empty string is never interned and reports
+    /// the zero epoch.
+    #[cfg(feature = "stats")]
+    pub fn created(&self) -> stats::Epoch {
+        if self.is_empty() {
+            return stats::Epoch::from_secs(0);
+        }
+        stats::Epoch::from_secs(self.header().created)
+    }
+
+    /// A hash of the string that is identical across processes, machines and versions of this
+    /// crate: 64-bit XXH3 with a seed of 0. It is computed once, when the string is interned,
+    /// and is independent of the hasher used by the table itself.
+    #[cfg(feature = "stable-hash")]
+    pub fn stable_hash(&self) -> u64 {
+        if self.is_empty() {
+            return stable_hash("");
+        }
+        self.header().stable_hash
+    }
+
+    /// The hash the tables file this string under, computed once when it was interned.
+    #[cfg(feature = "cached-hash")]
+    pub fn table_hash(&self) -> u64 {
+        if self.is_empty() {
+            return TABLE_HASHER.hash_one("");
         }
+        self.header().hash
+    }
+
+    /// The hash of the string under `hasher`, which must be a table's hasher. With the
+    /// `cached-hash` feature this reads the stored hash rather than rehashing the string.
+    #[cfg_attr(feature = "cached-hash", allow(unused_variables))]
+    fn hash_in(&self, hasher: &TableHasher) -> u64 {
+        #[cfg(feature = "cached-hash")]
+        return self.table_hash();
+        #[cfg(not(feature = "cached-hash"))]
+        hasher.hash_one(self.as_str())
+    }
+
+    /// A reference-counted copy of the string, for APIs that demand an `Arc<str>`. Entries are
+    /// leaked rather than reference counted, so this copies the bytes, in one allocation.
+    pub fn to_arc_str(&self) -> std::sync::Arc<str> {
+        std::sync::Arc::from(self.as_str())
+    }
+
+    /// The same as [`Istr::to_arc_str`] for `Rc<str>`.
+    pub fn to_rc_str(&self) -> std::rc::Rc<str> {
+        std::rc::Rc::from(self.as_str())
+    }
+
+    /// The header of a non-empty string.
+    fn header(&self) -> &'static Header {
+        debug_assert!(!self.is_empty());
+        unsafe { &*self.0.as_ptr() }
     }
 }
 
+/// Handles are equal if they point to the same entry. A table holds exactly one entry per
+/// string, so within a table this is string equality at the cost of a pointer comparison.
+/// Handles from different tables, e.g. a [`LocalInterner`] and [`GLOBAL_TABLE`], are unequal
+/// even if their strings are equal; the empty string is the exception, being shared by all.
 impl PartialEq for Istr {
     fn eq(&self, other: &Self) -> bool {
-        self.as_str() == other.as_str()
+        self.0 == other.0
     }
 }
 
@@ -68,9 +509,21 @@ impl PartialEq<&str> for Istr {
 
 impl Eq for Istr {}
 
-impl Into<String> for Istr {
-    fn into(self) -> String {
-        self.as_str().to_owned()
+impl From<Istr> for String {
+    fn from(s: Istr) -> String {
+        s.as_str().to_owned()
+    }
+}
+
+impl From<Istr> for std::sync::Arc<str> {
+    fn from(s: Istr) -> std::sync::Arc<str> {
+        s.to_arc_str()
+    }
+}
+
+impl From<Istr> for std::rc::Rc<str> {
+    fn from(s: Istr) -> std::rc::Rc<str> {
+        s.to_rc_str()
     }
 }
 
@@ -80,49 +533,958 @@ impl AsRef<str> for Istr {
     }
 }
 
+/// Hashes the address of the entry, consistently with `PartialEq`. To look up an `Istr`-keyed
+/// map by a `&str`, first look the string up with [`InternTable::get`], or enable the
+/// `equivalent` feature.
+///
+/// With the `equivalent` feature, hashes the string instead, as `str` does, so that maps keyed
+/// by `Istr` can be queried with a `&str` without interning it. Equal handles have equal
+/// strings, so this is still consistent with `PartialEq`, which stays by address.
 impl std::hash::Hash for Istr {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.as_str().hash(state)
+        #[cfg(feature = "equivalent")]
+        self.as_str().hash(state);
+        #[cfg(not(feature = "equivalent"))]
+        (self.0.as_ptr() as usize).hash(state);
     }
 }
 
+/// Lets `hashbrown` maps and sets keyed by `Istr` be queried with a `&str`, without interning
+/// it. `Istr` doesn't implement `Borrow<str>`, so std's maps don't offer this.
+#[cfg(feature = "equivalent")]
+impl hashbrown::Equivalent<Istr> for str {
+    fn equivalent(&self, key: &Istr) -> bool {
+        self == key.as_str()
+    }
+}
+
+/// The same for `indexmap` and other users of the `equivalent` crate.
+#[cfg(feature = "equivalent")]
+impl equivalent::Equivalent<Istr> for str {
+    fn equivalent(&self, key: &Istr) -> bool {
+        self == key.as_str()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Istr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Deserializing an `Istr` interns the string into the global table, failing with the error
+/// of [`Istr::try_new`] if it can't be interned.
+#[cfg(all(feature = "serde", feature = "global"))]
+impl<'de> serde::Deserialize<'de> for Istr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IstrVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for IstrVisitor {
+            type Value = Istr;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Istr, E> {
+                Istr::try_new(v).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(IstrVisitor)
+    }
+}
+
+/// A table of interned strings. [`GLOBAL_TABLE`] is the one backing [`Istr::new`]; without the
+/// `global` feature, explicit tables are the only way to intern.
+///
+/// The table is split into [`SHARDS`] shards, each behind its own lock and holding the strings
+/// whose hash selects it, so threads interning different strings rarely wait for each other.
+/// Lookups of strings that are already interned don't take the lock at all: each shard also
+/// publishes its entries in a lock-free index, and only misses fall back to the lock. The
+/// locks are `std::sync::RwLock`s, or `parking_lot`'s with the `parking_lot` feature.
+///
+/// # Lock discipline
+///
+/// The table never runs user code while holding a shard lock. Anything this crate runs on a
+/// caller's behalf (lazy initializers, caches layered on top of the table) runs before the
+/// lock is taken or after it is released, so such code may intern freely. Other locks in this
+/// crate are only ever taken before a shard lock, never while one is held. Operations that
+/// lock several shards at once take them in index order.
+///
+/// In debug builds, re-entering a table from a thread that already holds one of its locks
+/// panics instead of deadlocking.
+#[derive(Debug)]
+pub struct InternTable(
+    [Shard; SHARDS],
+    #[cfg(feature = "hot-tier")] hot::HotTier,
+);
+
+/// The number of shards of an [`InternTable`].
+pub const SHARDS: usize = 16;
+
+/// The longest string that can be interned, in bytes: an entry is the string and a small
+/// header, and no allocation can be larger than `isize::MAX` bytes. With the `compact-len`
+/// feature, the header stores the length as a `u32`, and strings are at most `u32::MAX` bytes.
+pub const MAX_LEN: usize = {
+    let max = isize::MAX as usize - size_of::<Header>() - (std::mem::align_of::<Header>() - 1);
+    let len_max = usize::MAX >> (usize::BITS - Len::BITS);
+    if max > len_max {
+        len_max
+    } else {
+        max
+    }
+};
+
+fn check_len(s: &str) -> Result<(), Error> {
+    if s.len() > MAX_LEN {
+        return Err(Error::TooLong(s.len()));
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
-pub struct InternTable(RwLock<HashSet<Istr>>);
+struct Shard {
+    lock: RwLock<Entries>,
+    /// The entries of `lock`, for lookups that don't take it. See the `index` module.
+    index: index::Index,
+    #[cfg(feature = "profiling")]
+    counters: profiling::Counters,
+}
+
+/// The contents of one shard of an [`InternTable`], guarded by its lock.
+#[derive(Debug)]
+struct Entries {
+    set: HashSet<Istr, TableHasher>,
+    /// Every string in the shard, in insertion order.
+    #[cfg(feature = "checkpoint")]
+    order: Vec<Istr>,
+    /// Lookup counts for promotion to the hot tier.
+    #[cfg(feature = "hot-tier")]
+    hot_counts: hot::Counts,
+    /// Where new entries are allocated.
+    arena: arena::Arena,
+}
+
+impl Entries {
+    const fn new() -> Self {
+        Entries {
+            set: HashSet::with_hasher(TABLE_HASHER),
+            #[cfg(feature = "checkpoint")]
+            order: Vec::new(),
+            #[cfg(feature = "hot-tier")]
+            hot_counts: hot::Counts::new(),
+            arena: arena::Arena::new(),
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        let hasher = TABLE_HASHER;
+        self.set
+            .raw_table_mut()
+            .reserve(additional, |&(x, _)| x.hash_in(&hasher));
+        #[cfg(feature = "checkpoint")]
+        self.order.reserve(additional);
+    }
+}
 
 impl InternTable {
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const SHARD: Shard = Shard {
+            lock: RwLock::new(Entries::new()),
+            index: index::Index::new(),
+            #[cfg(feature = "profiling")]
+            counters: profiling::Counters::new(),
+        };
+        InternTable(
+            [SHARD; SHARDS],
+            #[cfg(feature = "hot-tier")]
+            hot::HotTier::new(),
+        )
+    }
+
+    /// # Panics
+    ///
+    /// If `s` is longer than [`MAX_LEN`].
     pub fn get_or_intern(&self, s: &str) -> Istr {
-        let mut lock = self.0.write().unwrap();
-        let hasher = lock.hasher().clone();
-        let hash = hasher.hash_one(s);
-        let map = lock.raw_table_mut();
-        if let Some(fast_str) = map.get(hash, |&(q, _)| q.as_str() == s) {
-            return fast_str.0;
+        match self.get_or_intern_checked(s) {
+            Ok(fast_str) => fast_str,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [`InternTable::get_or_intern`], but returns [`Error::TooLong`] instead of
+    /// panicking. (The `try_` prefix is taken by the non-blocking
+    /// [`InternTable::try_get_or_intern`].)
+    pub fn get_or_intern_checked(&self, s: &str) -> Result<Istr, Error> {
+        self.intern_with_checked(s).map(|(fast_str, _)| fast_str)
+    }
+
+    /// Like [`InternTable::get_or_intern`], also returning whether `s` was newly inserted by
+    /// this call. The empty string is never inserted.
+    ///
+    /// # Panics
+    ///
+    /// If `s` is longer than [`MAX_LEN`].
+    pub fn intern_with(&self, s: &str) -> (Istr, bool) {
+        match self.intern_with_checked(s) {
+            Ok(entry) => entry,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [`InternTable::intern_with`], but returns [`Error::TooLong`] instead of panicking.
+    pub fn intern_with_checked(&self, s: &str) -> Result<(Istr, bool), Error> {
+        if s.is_empty() {
+            return Ok((EMPTY_FAST_STR, false));
+        }
+        check_len(s)?;
+        Ok(self.intern_hashed(s, Self::hash(s)))
+    }
+
+    /// [`InternTable::intern_with`] for a non-empty string whose table hash is `hash`.
+    fn intern_hashed(&self, s: &str, hash: u64) -> (Istr, bool) {
+        #[cfg(feature = "profiling")]
+        let start = std::time::Instant::now();
+        let shard = shard(hash);
+        #[cfg(feature = "hot-tier")]
+        if let Some(fast_str) = self.1.get(hash, s) {
+            #[cfg(feature = "profiling")]
+            self.0[shard].counters.count(false);
+            return (fast_str, false);
+        }
+        if let Some(fast_str) = self.0[shard].index.get(hash, s) {
+            #[cfg(feature = "profiling")]
+            self.0[shard].counters.count(false);
+            // Hits don't take the lock, so only a sample of them is counted, when it's free.
+            #[cfg(feature = "hot-tier")]
+            if self.1.sample_hit() {
+                self.try_write(shard, |entries| {
+                    self.1.record_hit(&mut entries.hot_counts, fast_str, hash)
+                });
+            }
+            return (fast_str, false);
+        }
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("intern_miss", len = s.len(), shard).entered();
+        #[cfg(not(feature = "hot-tier"))]
+        let (fast_str, inserted) =
+            self.write(shard, |entries| self.insert(shard, entries, s, hash));
+        #[cfg(feature = "hot-tier")]
+        let (fast_str, inserted) = self.write(shard, |entries| {
+            let (fast_str, inserted) = self.insert(shard, entries, s, hash);
+            self.1.record(&mut entries.hot_counts, fast_str, hash);
+            (fast_str, inserted)
+        });
+        #[cfg(feature = "profiling")]
+        self.0[shard].counters.count(inserted);
+        if inserted {
+            self.inserted(fast_str);
+            #[cfg(feature = "profiling")]
+            profiling::missed(self, shard, fast_str, start);
+        }
+        (fast_str, inserted)
+    }
+
+    /// Like [`InternTable::get_or_intern`], but never blocks: returns `None` if the lock of the
+    /// string's shard is contended.
+    pub fn try_get_or_intern(&self, s: &str) -> Option<Istr> {
+        if s.is_empty() {
+            return Some(EMPTY_FAST_STR);
+        }
+        self.try_intern_hashed(s, Self::hash(s))
+    }
+
+    /// [`InternTable::try_get_or_intern`] for a non-empty string whose table hash is `hash`.
+    pub(crate) fn try_intern_hashed(&self, s: &str, hash: u64) -> Option<Istr> {
+        let shard = shard(hash);
+        if let Some(fast_str) = self.lookup(shard, hash, s) {
+            #[cfg(feature = "profiling")]
+            self.0[shard].counters.count(false);
+            return Some(fast_str);
+        }
+        #[cfg(feature = "profiling")]
+        let start = std::time::Instant::now();
+        let (fast_str, inserted) =
+            self.try_write(shard, |entries| self.insert(shard, entries, s, hash))?;
+        #[cfg(feature = "profiling")]
+        self.0[shard].counters.count(inserted);
+        if inserted {
+            self.inserted(fast_str);
+            #[cfg(feature = "profiling")]
+            profiling::missed(self, shard, fast_str, start);
+        }
+        Some(fast_str)
+    }
+
+    /// Intern every string of `strings`, returning the handles in the same order. Strings that
+    /// are already interned are found without locking, and the rest are inserted under a single
+    /// acquisition of each of their shards' locks.
+    pub fn get_or_intern_many<S: AsRef<str>>(
+        &self,
+        strings: impl IntoIterator<Item = S>,
+    ) -> Vec<Istr> {
+        self.intern_batch(strings.into_iter().collect(), false)
+    }
+
+    /// Like [`InternTable::get_or_intern_many`], for loading a batch of strings that are
+    /// expected to be new, e.g. the symbols of the first file a compiler reads. Each shard is
+    /// grown once up front to fit all of its strings, instead of doubling repeatedly as they
+    /// are inserted. Strings that are already interned are still found, not duplicated.
+    pub fn bulk_load_unique<S: AsRef<str>>(
+        &self,
+        strings: impl IntoIterator<Item = S>,
+    ) -> Vec<Istr> {
+        self.intern_batch(strings.into_iter().collect(), true)
+    }
+
+    /// Intern `strings` locking each shard with misses once. If `reserve` is set, the strings
+    /// aren't looked up first, and each shard makes room for all of its strings.
+    fn intern_batch<S: AsRef<str>>(&self, strings: Vec<S>, reserve: bool) -> Vec<Istr> {
+        #[cfg(feature = "profiling")]
+        let start = std::time::Instant::now();
+        let mut handles = vec![EMPTY_FAST_STR; strings.len()];
+        // Group the non-empty strings that missed by shard, keeping their order within each
+        // shard.
+        let mut by_shard: Vec<(usize, u64, usize)> = Vec::new();
+        for (i, s) in strings.iter().enumerate() {
+            let s = s.as_ref();
+            if s.is_empty() {
+                continue;
+            }
+            let hash = Self::hash(s);
+            let shard = shard(hash);
+            let hit = if reserve { None } else { self.lookup(shard, hash, s) };
+            match hit {
+                Some(fast_str) => handles[i] = fast_str,
+                None => by_shard.push((shard, hash, i)),
+            }
+        }
+        by_shard.sort_by_key(|&(shard, ..)| shard);
+        let mut new = Vec::new();
+        for group in by_shard.chunk_by(|a, b| a.0 == b.0) {
+            let shard = group[0].0;
+            self.write(shard, |entries| {
+                if reserve {
+                    entries.reserve(group.len());
+                    self.0[shard].index.reserve(group.len());
+                }
+                for &(_, hash, i) in group {
+                    let (fast_str, inserted) =
+                        self.insert(shard, entries, strings[i].as_ref(), hash);
+                    if inserted {
+                        new.push((shard, fast_str));
+                    }
+                    handles[i] = fast_str;
+                }
+            });
+        }
+        #[cfg_attr(not(feature = "profiling"), allow(unused_variables))]
+        for (shard, fast_str) in new {
+            self.inserted(fast_str);
+            #[cfg(feature = "profiling")]
+            profiling::missed(self, shard, fast_str, start);
+        }
+        handles
+    }
+
+    /// Look `s` up without inserting it.
+    pub fn get(&self, s: &str) -> Option<Istr> {
+        if s.is_empty() {
+            return Some(EMPTY_FAST_STR);
+        }
+        self.get_hashed(s, Self::hash(s))
+    }
+
+    /// Like [`InternTable::get`], for the non-empty `s` whose table hash is `hash`.
+    pub(crate) fn get_hashed(&self, s: &str, hash: u64) -> Option<Istr> {
+        let shard = shard(hash);
+        if let Some(fast_str) = self.lookup(shard, hash, s) {
+            return Some(fast_str);
+        }
+        self.read(shard, |entries| Self::find(entries, s, hash))
+    }
+
+    /// Look `s` up without inserting it or blocking. Returns `None` if the lock is contended.
+    #[cfg(feature = "background")]
+    pub(crate) fn try_get(&self, s: &str) -> Option<Option<Istr>> {
+        self.try_get_hashed(s, Self::hash(s))
+    }
+
+    /// Like [`InternTable::try_get`], for the non-empty `s` whose table hash is `hash`.
+    #[cfg(feature = "global")]
+    pub(crate) fn try_get_hashed(&self, s: &str, hash: u64) -> Option<Option<Istr>> {
+        let shard = shard(hash);
+        if let Some(fast_str) = self.lookup(shard, hash, s) {
+            return Some(Some(fast_str));
+        }
+        self.try_read(shard, |entries| Self::find(entries, s, hash))
+    }
+
+    /// Look `s` up without taking a lock. `None` means it wasn't found, not that it isn't
+    /// interned: it may be being inserted concurrently.
+    fn lookup(&self, shard: usize, hash: u64, s: &str) -> Option<Istr> {
+        #[cfg(feature = "hot-tier")]
+        if let Some(fast_str) = self.1.get(hash, s) {
+            return Some(fast_str);
+        }
+        self.0[shard].index.get(hash, s)
+    }
+
+    /// The hash of `s` under the tables' hasher, which also selects its shard.
+    fn hash(s: &str) -> u64 {
+        TABLE_HASHER.hash_one(s)
+    }
+
+    fn find(entries: &Entries, s: &str, hash: u64) -> Option<Istr> {
+        entries
+            .set
+            .raw_table()
+            .get(hash, |&(q, _)| q.as_str() == s)
+            .map(|&(q, _)| q)
+    }
+
+    /// Find or insert `s` into `entries`, the locked contents of shard `shard`, and publish it
+    /// in the shard's index if it is new.
+    fn insert(&self, shard: usize, entries: &mut Entries, s: &str, hash: u64) -> (Istr, bool) {
+        let (fast_str, inserted) = Self::insert_hashed(entries, s, hash);
+        if inserted {
+            self.0[shard].index.insert(hash, fast_str);
+        }
+        (fast_str, inserted)
+    }
+
+    /// Find or insert `s`, whose hash under the table's hasher is `hash`, returning whether it
+    /// was newly inserted. A single probe of the set finds either the string or the slot it
+    /// goes in, so a miss doesn't search the set a second time to insert.
+    fn insert_hashed(entries: &mut Entries, s: &str, hash: u64) -> (Istr, bool) {
+        #[cfg(feature = "counting-alloc")]
+        let _scope = counting::Scope::enter();
+        let hasher = TABLE_HASHER;
+        let map = entries.set.raw_table_mut();
+        // The set is full, so making room for the string rehashes it into one twice as large.
+        #[cfg(feature = "tracing")]
+        let _span = (map.len() == map.capacity()).then(|| {
+            tracing::debug_span!("rehash", len = map.len(), string_len = s.len()).entered()
+        });
+        // The set is keyed by the hash of the string, not that of the handle.
+        let slot = match map.find_or_find_insert_slot(
+            hash,
+            |&(q, _)| q.as_str() == s,
+            |&(x, _)| x.hash_in(&hasher),
+        ) {
+            // SAFETY: the bucket was just found, and the set hasn't changed since.
+            Ok(bucket) => return (unsafe { bucket.as_ref() }.0, false),
+            Err(slot) => slot,
+        };
+        let fast_str = InternedData::construct_in(&mut entries.arena, s);
+        // SAFETY: the slot was just found, and the set hasn't changed since.
+        unsafe { map.insert_in_slot(hash, slot, (fast_str, ())) };
+        #[cfg(feature = "checkpoint")]
+        entries.order.push(fast_str);
+        #[cfg(debug_assertions)]
+        NEW_INTERNS.with(|n| n.set(n.get() + 1));
+        (fast_str, true)
+    }
+
+    /// Called once the lock has been released after inserting a new string, so that the
+    /// consumers here can intern or take their own locks.
+    #[cfg_attr(not(feature = "global"), allow(unused_variables))]
+    fn inserted(&self, fast_str: Istr) {
+        #[cfg(feature = "metrics")]
+        metrics::inserted(fast_str);
+        #[cfg(feature = "global")]
+        if std::ptr::eq(self, &GLOBAL_TABLE) {
+            budget::inserted(fast_str);
+            #[cfg(feature = "metrics")]
+            metrics::global_inserted();
+            observe::inserted(fast_str);
+        }
+        #[cfg(feature = "global")]
+        if std::ptr::eq(self, active_table()) {
+            #[cfg(feature = "persist")]
+            persist::inserted(fast_str);
+            #[cfg(feature = "suffix-index")]
+            suffix::inserted(fast_str);
+        }
+    }
+
+    /// Called once the lock has been released after emptying the table by
+    /// [`InternTable::reset`], before its strings are freed.
+    fn cleared(&self) {
+        #[cfg(feature = "global")]
+        if std::ptr::eq(self, &GLOBAL_TABLE) {
+            budget::cleared();
+            #[cfg(feature = "metrics")]
+            metrics::global_cleared();
+        }
+        #[cfg(feature = "global")]
+        if std::ptr::eq(self, active_table()) {
+            #[cfg(feature = "persist")]
+            persist::cleared();
+            #[cfg(feature = "suffix-index")]
+            suffix::cleared();
+        }
+    }
+
+    /// Called once the lock has been released after removing a string, before it is freed.
+    #[cfg_attr(not(feature = "global"), allow(unused_variables))]
+    fn removed(&self, fast_str: Istr) {
+        #[cfg(feature = "global")]
+        if std::ptr::eq(self, &GLOBAL_TABLE) {
+            budget::removed(fast_str);
+            #[cfg(feature = "metrics")]
+            metrics::global_removed();
+        }
+        #[cfg(feature = "global")]
+        if std::ptr::eq(self, active_table()) {
+            #[cfg(feature = "persist")]
+            persist::removed(fast_str);
+            #[cfg(feature = "suffix-index")]
+            suffix::removed(fast_str);
         }
-        let fast_str = InternedData::construct(s);
-        let inserted = map.insert_entry(hash, (fast_str, ()), |&(x, _)| hasher.hash_one(x));
-        inserted.0
     }
 
     pub fn len(&self) -> usize {
-        self.0.read().unwrap().len()
+        (0..SHARDS).map(|i| self.read(i, |entries| entries.set.len())).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Make room for at least `additional` more strings, spread evenly over the shards, so
+    /// interning them doesn't grow the shards' sets and indexes again and again. A shard that
+    /// gets more than its share still grows as usual.
+    pub fn reserve(&self, additional: usize) {
+        let per_shard = additional.div_ceil(SHARDS);
+        for (i, shard) in self.0.iter().enumerate() {
+            self.write(i, |entries| {
+                entries.reserve(per_shard);
+                shard.index.reserve(per_shard);
+            });
+        }
+    }
+
+    /// A copy of every string currently in the table, in no particular order.
+    pub fn to_vec(&self) -> Vec<Istr> {
+        self.iter().collect()
+    }
+
+    /// Every string in the table, in no particular order. Each shard is copied out under its
+    /// lock when the iterator reaches it, so strings interned meanwhile may or may not be seen.
+    pub fn iter(&self) -> impl Iterator<Item = Istr> + '_ {
+        (0..SHARDS)
+            .flat_map(|i| self.read(i, |entries| entries.set.iter().copied().collect::<Vec<_>>()))
+    }
+
+    /// Remove and free every string in the table, including the chunks they were allocated
+    /// from, returning the table to its state when it was created.
+    ///
+    /// # Safety
+    ///
+    /// No handle to a string of the table may be used afterwards, on any thread. This includes
+    /// handles cached by this crate, e.g. by [`istr!`](crate::istr) or an
+    /// [`AtomSet`](crate::atoms::AtomSet), and by the caller's own maps. Lookups that hit don't
+    /// take the table's locks, so no lookup may run concurrently with the reset.
+    pub unsafe fn reset(&self) {
+        #[cfg(feature = "counting-alloc")]
+        let _scope = counting::Scope::enter();
+        let removed: Vec<Entries> = self.write_all(|shards| {
+            let removed = shards
+                .iter_mut()
+                .enumerate()
+                .map(|(i, entries)| {
+                    self.0[i].index.rebuild(std::iter::empty());
+                    std::mem::replace(&mut **entries, Entries::new())
+                })
+                .collect();
+            #[cfg(feature = "hot-tier")]
+            self.1.clear(shards.iter_mut().map(|entries| &mut entries.hot_counts));
+            removed
+        });
+        self.freeing();
+        self.cleared();
+        for mut entries in removed {
+            #[cfg(feature = "wide")]
+            entries.set.iter().for_each(|&s| wide::free_cached(s));
+            entries.arena.free(entries.set.iter().copied());
+        }
+    }
+
+    /// Remove `s` from the table, returning whether it was interned. Its memory is freed if it
+    /// has an allocation of its own: with the `checkpoint` feature, or if it is long. Shorter
+    /// entries share chunks with their neighbours, which are only freed by
+    /// [`InternTable::reset`].
+    ///
+    /// Interning `s` again afterwards creates a new entry, with a different address.
+    ///
+    /// # Safety
+    ///
+    /// As for [`InternTable::reset`], for the handle of `s`: it may not be used afterwards, on
+    /// any thread, including copies cached by this crate, and no lookup may run concurrently
+    /// with the removal. With the `checkpoint` feature, checkpoints taken before the removal
+    /// may not be restored.
+    pub unsafe fn remove(&self, s: &str) -> bool {
+        match self.get(s) {
+            Some(fast_str) if !fast_str.is_empty() => self.remove_all(vec![fast_str]) == 1,
+            _ => false,
+        }
+    }
+
+    /// Remove every string for which `f` returns `false`, returning how many were removed,
+    /// and free them as [`InternTable::remove`] does. `f` is called without any lock held,
+    /// on a copy of each shard's strings, so strings interned meanwhile are kept.
+    ///
+    /// # Safety
+    ///
+    /// As for [`InternTable::remove`], for every string removed.
+    pub unsafe fn retain(&self, mut f: impl FnMut(Istr) -> bool) -> usize {
+        let doomed: Vec<Istr> = self.iter().filter(|&s| !f(s)).collect();
+        self.remove_all(doomed)
+    }
+
+    /// Remove and free the entries `doomed`, returning how many were in the table.
+    unsafe fn remove_all(&self, doomed: Vec<Istr>) -> usize {
+        if doomed.is_empty() {
+            return 0;
+        }
+        #[cfg(feature = "counting-alloc")]
+        let _scope = counting::Scope::enter();
+        let removed = self.write_all(|shards| {
+            let mut removed = Vec::new();
+            let mut touched = [false; SHARDS];
+            for s in doomed {
+                let hash = Self::hash(s.as_str());
+                let i = shard(hash);
+                let map = shards[i].set.raw_table_mut();
+                if map.remove_entry(hash, |&(q, _)| q.0 == s.0).is_some() {
+                    touched[i] = true;
+                    removed.push(s);
+                }
+            }
+            #[cfg(feature = "checkpoint")]
+            let gone: std::collections::HashSet<Istr> = removed.iter().copied().collect();
+            for (i, entries) in shards.iter_mut().enumerate() {
+                if !touched[i] {
+                    continue;
+                }
+                #[cfg(feature = "checkpoint")]
+                entries.order.retain(|s| !gone.contains(s));
+                let hasher = TABLE_HASHER;
+                let kept = entries.set.iter().map(|s| (s.hash_in(&hasher), *s));
+                self.0[i].index.rebuild(kept);
+            }
+            #[cfg(feature = "hot-tier")]
+            if !removed.is_empty() {
+                self.1.clear(shards.iter_mut().map(|entries| &mut entries.hot_counts));
+            }
+            removed
+        });
+        self.freeing();
+        for &s in &removed {
+            self.removed(s);
+            #[cfg(feature = "wide")]
+            wide::free_cached(s);
+            arena::free_entry(s);
+        }
+        removed.len()
+    }
+
+    /// Drop the handles this crate caches for the active table, if this is it: every thread's
+    /// cache, and the frozen table. Called by restores, resets and removals before they free
+    /// entries.
+    fn freeing(&self) {
+        #[cfg(feature = "global")]
+        if std::ptr::eq(self, active_table()) {
+            thread_cache::invalidate();
+            frozen::thaw();
+        }
+    }
+
+    fn read<R>(&self, shard: usize, f: impl FnOnce(&Entries) -> R) -> R {
+        let _guard = ReentrancyGuard::enter(self);
+        f(&self.0[shard].lock.read())
+    }
+
+    fn write<R>(&self, shard: usize, f: impl FnOnce(&mut Entries) -> R) -> R {
+        let _guard = ReentrancyGuard::enter(self);
+        #[cfg(any(feature = "metrics", feature = "tracing"))]
+        if let Some(mut lock) = self.0[shard].lock.try_write() {
+            return f(&mut lock);
+        }
+        #[cfg(feature = "metrics")]
+        metrics::contended();
+        #[cfg(feature = "tracing")]
+        let span = tracing::trace_span!("lock_wait", shard).entered();
+        let mut lock = self.0[shard].lock.write();
+        #[cfg(feature = "tracing")]
+        drop(span);
+        f(&mut lock)
+    }
+
+    /// Run `f` with every shard write-locked, in index order.
+    fn write_all<R>(&self, f: impl FnOnce(&mut [&mut Entries]) -> R) -> R {
+        let _guard = ReentrancyGuard::enter(self);
+        let mut locks: Vec<_> = self.0.iter().map(|shard| shard.lock.write()).collect();
+        let mut entries: Vec<&mut Entries> = locks.iter_mut().map(|lock| &mut **lock).collect();
+        f(&mut entries)
+    }
+
+    #[cfg(feature = "global")]
+    fn try_read<R>(&self, shard: usize, f: impl FnOnce(&Entries) -> R) -> Option<R> {
+        let _guard = ReentrancyGuard::enter(self);
+        self.0[shard].lock.try_read().map(|lock| f(&lock))
+    }
+
+    fn try_write<R>(&self, shard: usize, f: impl FnOnce(&mut Entries) -> R) -> Option<R> {
+        let _guard = ReentrancyGuard::enter(self);
+        self.0[shard].lock.try_write().map(|mut lock| f(&mut lock))
+    }
+}
+
+/// The shard holding strings with table hash `hash`. The hot tier, the index and hashbrown use
+/// the top and bottom bits, so the shard is picked from the middle.
+fn shard(hash: u64) -> usize {
+    (hash >> 32) as usize % SHARDS
+}
+
+impl Default for InternTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The process-wide table. Only available with the `global` feature, which is on by default.
+#[cfg(feature = "global")]
+pub static GLOBAL_TABLE: InternTable = InternTable::new();
+
+/// The hash [`Istr::stable_hash`] returns for `s`, without interning it.
+#[cfg(feature = "stable-hash")]
+pub fn stable_hash(s: &str) -> u64 {
+    xxhash_rust::xxh3::xxh3_64(s.as_bytes())
+}
+
+/// Remove `s` from the active table.
+///
+/// # Safety
+///
+/// See [`InternTable::remove`].
+#[cfg(feature = "global")]
+pub unsafe fn remove(s: &str) -> bool {
+    active_table().remove(s)
+}
+
+/// Remove every string of the active table for which `f` returns `false`.
+///
+/// # Safety
+///
+/// See [`InternTable::retain`].
+#[cfg(feature = "global")]
+pub unsafe fn retain(f: impl FnMut(Istr) -> bool) -> usize {
+    active_table().retain(f)
+}
+
+/// Whether `s` has been interned into the active table. Never interns it.
+#[cfg(feature = "global")]
+pub fn contains(s: &str) -> bool {
+    Istr::get(s).is_some()
+}
+
+/// Intern `s` without blocking. Returns `None` instead of waiting when another thread holds the
+/// lock of the string's shard, e.g. a bulk loader inserting many strings, and when
+/// [`Istr::try_new`] would fail.
+#[cfg(feature = "global")]
+pub fn try_intern(s: &str) -> Option<Istr> {
+    if s.is_empty() {
+        return Some(EMPTY_FAST_STR);
+    }
+    let table = active_table();
+    match check(table, s, InternTable::hash(s), false).ok()? {
+        Checked::Found(fast_str) => Some(fast_str),
+        Checked::Insert(s, hash) => table.try_intern_hashed(&s, hash),
+        Checked::Contended => None,
     }
 }
 
-pub static GLOBAL_TABLE: InternTable = InternTable(RwLock::new(HashSet::with_hasher(unsafe {
-    std::mem::transmute::<_, DefaultHashBuilder>(())
-})));
+/// Reset the active table, freeing every string interned so far.
+///
+/// # Safety
+///
+/// See [`InternTable::reset`]: no [`Istr`] created before the reset may be used afterwards,
+/// including by other threads and by tests running in parallel.
+#[cfg(feature = "global")]
+pub unsafe fn reset() {
+    active_table().reset()
+}
 
+/// Intern `s` if that can be done without blocking, otherwise borrow it.
+#[cfg(feature = "global")]
+pub fn intern_or_borrow(s: &str) -> MaybeInterned<'_> {
+    match try_intern(s) {
+        Some(fast_str) => MaybeInterned::Interned(fast_str),
+        None => MaybeInterned::Borrowed(s),
+    }
+}
+
+#[cfg(debug_assertions)]
+thread_local! {
+    /// The address of the table whose lock this thread currently holds, or 0.
+    static HELD_TABLE: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    /// The number of strings this thread has inserted into any table, for
+    /// `assert_no_new_interns`.
+    static NEW_INTERNS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Run `f`, panicking if it inserts a new string into any table from this thread.
+///
+/// Intended for tests asserting that a steady-state code path only meets strings that are
+/// already interned. Strings interned on other threads on `f`'s behalf, e.g. by the background
+/// worker behind `intern_deferred`, go unnoticed. The check is only made in debug builds; in
+/// release builds `f` simply runs.
+///
+/// ```
+/// let table = lsi::InternTable::new();
+/// table.get_or_intern("GET");
+/// lsi::assert_no_new_interns(|| table.get_or_intern("GET"));
+/// ```
+pub fn assert_no_new_interns<R>(f: impl FnOnce() -> R) -> R {
+    #[cfg(debug_assertions)]
+    let before = NEW_INTERNS.with(|n| n.get());
+    let result = f();
+    #[cfg(debug_assertions)]
+    {
+        let new = NEW_INTERNS.with(|n| n.get()) - before;
+        assert!(new == 0, "closure interned {} new string(s)", new);
+    }
+    result
+}
+
+/// Detects a thread re-entering a table it has already locked. Compiles to nothing in
+/// release builds.
+struct ReentrancyGuard {
+    #[cfg(debug_assertions)]
+    prev: usize,
+}
+
+impl ReentrancyGuard {
+    #[cfg_attr(not(debug_assertions), allow(unused_variables))]
+    fn enter(table: &InternTable) -> Self {
+        #[cfg(debug_assertions)]
+        {
+            let addr = table as *const InternTable as usize;
+            let prev = HELD_TABLE.with(|held| held.replace(addr));
+            assert_ne!(prev, addr, "re-entrant call into an InternTable while its lock is held");
+            ReentrancyGuard { prev }
+        }
+        #[cfg(not(debug_assertions))]
+        ReentrancyGuard {}
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Drop for ReentrancyGuard {
+    fn drop(&mut self) {
+        HELD_TABLE.with(|held| held.set(self.prev));
+    }
+}
+
+/// The address [`Istr::data_ptr`] returns for the empty string.
+static EMPTY_DATA: u8 = 0;
+
+#[cfg(not(feature = "strict-provenance"))]
 const EMPTY_FAST_STR: Istr = Istr(unsafe {
     // we're okay doing this because if the pointer pointed to the end
     // of memory, we'd be OOM anyway.
-    NonNull::new_unchecked(usize::MAX as *mut *const u8)
+    NonNull::new_unchecked(usize::MAX as *mut Header)
 });
+/// The same address, as a pointer without provenance, which is never dereferenced.
+#[cfg(feature = "strict-provenance")]
+const EMPTY_FAST_STR: Istr =
+    Istr(unsafe { NonNull::new_unchecked(std::ptr::without_provenance_mut(usize::MAX)) });
+
+/// The type of an entry's length: `u32` with the `compact-len` feature, which saves up to 7
+/// bytes per entry on 64-bit targets and limits strings to [`MAX_LEN`].
+#[cfg(feature = "compact-len")]
+pub(crate) type Len = u32;
+#[cfg(not(feature = "compact-len"))]
+pub(crate) type Len = usize;
+
+/// A [`Len`] as a `usize`, which it always fits.
+#[allow(clippy::unnecessary_cast)]
+pub(crate) const fn widen(len: Len) -> usize {
+    len as usize
+}
+
+/// The fixed-size part of [`InternedData`]. An [`Istr`] points here.
+pub(crate) type Header = HeaderWith<{ len_fill() }>;
+
+/// The padding a [`Header`] needs before its length for the length to end it, directly
+/// followed by the string's bytes: the tail padding it would have otherwise, which a `u32`
+/// length after 8-byte fields leaves.
+const fn len_fill() -> usize {
+    size_of::<HeaderWith<0>>() - std::mem::offset_of!(HeaderWith<0>, len) - size_of::<Len>()
+}
+
+#[repr(C)]
+pub(crate) struct HeaderWith<const FILL: usize> {
+    /// The hash of the string under the tables' hasher.
+    #[cfg(feature = "cached-hash")]
+    hash: u64,
+    #[cfg(feature = "stable-hash")]
+    stable_hash: u64,
+    #[cfg(feature = "stats")]
+    created: u32,
+    /// Lookups since the last promotion to the hot tier, see the `hot` module.
+    #[cfg(feature = "hot-tier")]
+    lookups: std::sync::atomic::AtomicU32,
+    /// Cached identifier checks, see the `ident` module.
+    #[cfg(feature = "unicode-ident")]
+    ident: std::sync::atomic::AtomicU8,
+    /// User flag bits, see the `flags` module.
+    #[cfg(feature = "flags")]
+    flags: std::sync::atomic::AtomicU8,
+    /// The cached UTF-16 encoding, see the `wide` module.
+    #[cfg(feature = "wide")]
+    wide: std::sync::atomic::AtomicPtr<&'static [u16]>,
+    /// The number of handles to an entry of the reference-counted table, see the `rc` module.
+    #[cfg(feature = "refcount")]
+    refs: std::sync::atomic::AtomicUsize,
+    fill: [u8; FILL],
+    len: Len,
+}
 
+impl Header {
+    fn new(s: &str) -> Self {
+        Header {
+            #[cfg(feature = "cached-hash")]
+            hash: TABLE_HASHER.hash_one(s),
+            #[cfg(feature = "stable-hash")]
+            stable_hash: stable_hash(s),
+            #[cfg(feature = "stats")]
+            created: stats::Epoch::now().as_secs(),
+            #[cfg(feature = "hot-tier")]
+            lookups: std::sync::atomic::AtomicU32::new(0),
+            #[cfg(feature = "unicode-ident")]
+            ident: std::sync::atomic::AtomicU8::new(0),
+            #[cfg(feature = "flags")]
+            flags: std::sync::atomic::AtomicU8::new(0),
+            #[cfg(feature = "wide")]
+            wide: std::sync::atomic::AtomicPtr::new(std::ptr::null_mut()),
+            #[cfg(feature = "refcount")]
+            refs: std::sync::atomic::AtomicUsize::new(0),
+            fill: [0; len_fill()],
+            len: Len::try_from(s.len()).expect("string longer than MAX_LEN"),
+        }
+    }
+}
 
 /// This is the data that gets interned by the library.
 #[repr(C)]
 pub struct InternedData {
-    len: usize,
+    header: Header,
     data: [u8],
 }
 
@@ -133,19 +1495,31 @@ impl InternedData {
         let leaked = Box::leak(interned);
         let ptr = leaked as *const InternedData;
         Istr(unsafe {
-            NonNull::new_unchecked(ptr as *mut *const u8)
+            NonNull::new_unchecked(ptr as *mut Header)
         })
     }
 
+    /// Like [`InternedData::construct`], allocating the entry from `arena`.
+    pub(crate) fn construct_in(arena: &mut arena::Arena, s: &str) -> Istr {
+        let layout = Self::layout_of(s.len()).unwrap();
+        let ptr = std::ptr::slice_from_raw_parts_mut(arena.alloc(layout), s.len());
+        let ptr = ptr as *mut InternedData;
+        unsafe {
+            Self::initialize_self(ptr, s);
+            Istr(NonNull::new_unchecked(ptr as *mut Header))
+        }
+    }
+
     pub fn new(s: &str) -> Box<Self> {
         let ptr = unsafe { Self::alloc_self(s.len()) };
-        Self::initialize_self(ptr, s);
-        let b = unsafe { Box::from_raw(ptr) };
-        b
+        unsafe {
+            Self::initialize_self(ptr, s);
+            Box::from_raw(ptr)
+        }
     }
 
     pub(crate) fn layout_of(n: usize) -> Result<Layout, LayoutError> {
-        let (layout, _) = Layout::new::<usize>()
+        let (layout, _) = Layout::new::<Header>()
             .extend(Layout::array::<u8>(n)?)?;
         let layout = layout.pad_to_align();
         Ok(layout)
@@ -156,6 +1530,9 @@ impl InternedData {
         let layout = Self::layout_of(n).unwrap();
         // Make a heap allocation.
         let ptr = alloc(layout);
+        if ptr.is_null() {
+            handle_alloc_error(layout);
+        }
         // Construct a fat pointer by making a fake slice.
         // The first argument is the pointer, the second argument is the metadata.
         // In this case, its just the length of the slice.
@@ -165,10 +1542,14 @@ impl InternedData {
         ptr
     }
 
+    /// `ptr` must point to writable memory with the layout of an `InternedData` of `s.len()`
+    /// bytes.
+    // Safe to call since the first release, which callers outside the crate rely on.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn initialize_self(ptr: *mut Self, s: &str) {
         // Initialize its fields one-by-one.
         unsafe {
-            addr_of_mut!((*ptr).len).write(s.len());
+            addr_of_mut!((*ptr).header).write(Header::new(s));
         }
         unsafe {
             let slice_ptr = addr_of_mut!((*ptr).data) as *mut u8;
@@ -177,6 +1558,24 @@ impl InternedData {
     }
 }
 
+/// Intern a string literal once per call site.
+///
+/// The first evaluation interns the literal and caches the handle in a static, so later
+/// evaluations don't touch the table.
+///
+/// ```
+/// let s = lsi::istr!("content-type");
+/// assert_eq!(s, lsi::Istr::new("content-type"));
+/// ```
+#[cfg(feature = "global")]
+#[macro_export]
+macro_rules! istr {
+    ($s:literal) => {{
+        static CACHE: ::std::sync::OnceLock<$crate::Istr> = ::std::sync::OnceLock::new();
+        *CACHE.get_or_init(|| $crate::Istr::new($s))
+    }};
+}
+
 #[macro_export]
 macro_rules! assert_eq_size {
     ($x:ty, $($xs:ty),+ $(,)?) => {
@@ -191,16 +1590,322 @@ mod tests {
     use super::*;
 
     #[test]
+    #[cfg(feature = "global")]
     fn test_new() {
         let s = Istr::new("Hello");
         assert_eq!(s, "Hello");
     }
 
     #[test]
+    fn test_pointer_equality() {
+        let (a, b) = (InternTable::new(), InternTable::new());
+        let x = a.get_or_intern("pointer key");
+        assert_eq!(x, a.get_or_intern("pointer key"));
+        assert_ne!(x, b.get_or_intern("pointer key"));
+        assert_eq!(a.get_or_intern(""), b.get_or_intern(""));
+        assert_eq!(LocalInterner::new().get_or_intern(""), EMPTY_FAST_STR);
+        let hasher = TABLE_HASHER;
+        assert_eq!(hasher.hash_one(x), hasher.hash_one(a.get("pointer key").unwrap()));
+        let mut map = IstrMap::default();
+        map.insert(x, 1);
+        assert_eq!(map.get(&a.get("pointer key").unwrap()), Some(&1));
+        assert_eq!(map.get(&b.get("pointer key").unwrap()), None);
+    }
+
+    #[test]
+    fn test_shards() {
+        let table = InternTable::new();
+        let strings: Vec<String> = (0..1000).map(|i| format!("shard {}", i)).collect();
+        let handles = table.get_or_intern_many(strings.iter().chain([&String::new()]));
+        assert_eq!(handles.len(), 1001);
+        assert!(handles[1000].is_empty());
+        assert!(strings.iter().zip(&handles).all(|(s, h)| h.as_str() == s));
+        assert_eq!(table.len(), 1000);
+        assert_eq!(table.iter().count(), 1000);
+        let used = (0..SHARDS)
+            .filter(|&i| table.read(i, |entries| !entries.set.is_empty()))
+            .count();
+        assert_eq!(used, SHARDS);
+    }
+
+    #[test]
+    fn test_bulk_load_unique() {
+        let table = InternTable::new();
+        let known = table.get_or_intern("known");
+        let strings: Vec<String> = (0..500).map(|i| format!("bulk {}", i)).collect();
+        let handles = table.bulk_load_unique(strings.iter().chain([&"known".into(), &"".into()]));
+        assert_eq!(handles[500], known);
+        assert!(handles[501].is_empty());
+        assert!(strings.iter().zip(&handles).all(|(s, h)| h.as_str() == s));
+        assert_eq!(table.len(), 501);
+        assert_eq!(table.get("bulk 7"), Some(handles[7]));
+    }
+
+    #[test]
+    fn test_intern_with() {
+        let table = InternTable::new();
+        let (first, inserted) = table.intern_with("first seen");
+        assert!(inserted);
+        assert_eq!(table.intern_with("first seen"), (first, false));
+        assert_eq!(table.get_or_intern("first seen"), first);
+        assert_eq!(table.intern_with(""), (EMPTY_FAST_STR, false));
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn test_reserve() {
+        let table = InternTable::new();
+        table.reserve(1000);
+        let capacity = table.read(0, |entries| entries.set.capacity());
+        assert!(capacity * SHARDS >= 1000);
+        for i in 0..100 {
+            table.get_or_intern(&i.to_string());
+        }
+        assert_eq!(table.read(0, |entries| entries.set.capacity()), capacity);
+        assert_eq!(table.len(), 100);
+        assert_eq!(table.get("42").unwrap(), "42");
+    }
+
+    #[test]
+    #[cfg(feature = "global")]
+    fn test_get() {
+        assert_eq!(Istr::get("never interned by get"), None);
+        assert!(!contains("never interned by get"));
+        // Neither probe interned it.
+        assert!(active_table().iter().all(|s| s != "never interned by get"));
+        let s = Istr::new("interned for get");
+        assert_eq!(Istr::get("interned for get"), Some(s));
+        assert!(contains("interned for get"));
+        assert_eq!(Istr::get(""), Some(Istr::new("")));
+    }
+
+    #[test]
+    fn test_remove() {
+        let table = InternTable::new();
+        let kept: Vec<Istr> = (0..100)
+            .map(|i| table.get_or_intern(&format!("kept {}", i)))
+            .collect();
+        let long = "scratch".repeat(10_000);
+        table.get_or_intern(&long);
+        for i in 0..100 {
+            table.get_or_intern(&format!("scratch {}", i));
+        }
+        assert!(unsafe { table.remove(&long) });
+        assert!(!unsafe { table.remove(&long) });
+        assert!(!unsafe { table.remove("") });
+        assert_eq!(table.get(&long), None);
+        let removed = unsafe { table.retain(|s| !s.as_str().starts_with("scratch")) };
+        assert_eq!(removed, 100);
+        assert_eq!(table.len(), 100);
+        assert_eq!(table.get("scratch 5"), None);
+        assert!(kept.iter().all(|&s| table.get(s.as_str()) == Some(s)));
+        assert_eq!(table.get_or_intern("scratch 5"), "scratch 5");
+        assert_eq!(table.len(), 101);
+    }
+
+    #[test]
+    fn test_intern_in_retain() {
+        let table = InternTable::new();
+        for i in 0..100 {
+            table.get_or_intern(&format!("retained {}", i));
+        }
+        // The closure runs without a lock held, so it can intern into the table itself.
+        let removed = unsafe {
+            table.retain(|s| {
+                if !s.as_str().ends_with(" again") {
+                    table.get_or_intern(&format!("{} again", s.as_str()));
+                }
+                s.as_str() != "retained 0"
+            })
+        };
+        assert_eq!(removed, 1);
+        assert_eq!(table.len(), 199);
+        assert!(table.get("retained 7 again").is_some());
+    }
+
+    #[test]
+    fn test_reset() {
+        let table = InternTable::new();
+        let strings: Vec<String> = (0..2000).map(|i| format!("reset {}", i)).collect();
+        table.get_or_intern_many(&strings);
+        table.get_or_intern(&"long".repeat(10_000));
+        #[cfg(feature = "wide")]
+        table.get_or_intern("reset 1").to_wide_cached();
+        unsafe { table.reset() };
+        assert!(table.is_empty());
+        assert_eq!(table.get("reset 7"), None);
+        let again = table.get_or_intern("reset 7");
+        assert_eq!(table.get_or_intern("reset 7"), again);
+        assert_eq!(table.to_vec(), [again]);
+    }
+
+    #[test]
+    fn test_hits_dont_lock() {
+        let table = InternTable::new();
+        let known: Vec<Istr> = (0..200)
+            .map(|i| table.get_or_intern(&format!("known {}", i)))
+            .collect();
+        let locks: Vec<_> = table.0.iter().map(|s| s.lock.write()).collect();
+        for s in &known {
+            assert_eq!(table.get_or_intern(s.as_str()), *s);
+            assert_eq!(table.try_get_or_intern(s.as_str()), Some(*s));
+        }
+        assert_eq!(table.try_get_or_intern("unknown"), None);
+        drop(locks);
+    }
+
+    #[cfg(feature = "equivalent")]
+    #[test]
+    fn test_equivalent() {
+        let table = InternTable::new();
+        let key = table.get_or_intern("equivalent key");
+        let mut map = IstrMap::default();
+        map.insert(key, 1);
+        assert_eq!(map.get("equivalent key"), Some(&1));
+        assert_eq!(map.get("missing key"), None);
+        let mut map = hashbrown::HashMap::new();
+        map.insert(key, 2);
+        assert_eq!(map.get("equivalent key"), Some(&2));
+        // Still by address: the same string from another table is a different key.
+        assert_eq!(map.get(&InternTable::new().get_or_intern("equivalent key")), None);
+    }
+
+    #[test]
+    fn test_concurrent_interning_is_canonical() {
+        let table = InternTable::new();
+        let handles: Vec<Vec<Istr>> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..500)
+                            .map(|i| table.get_or_intern(&format!("race-{}", i % 50)))
+                            .collect()
+                    })
+                })
+                .collect();
+            threads.into_iter().map(|t| t.join().unwrap()).collect()
+        });
+        assert_eq!(table.len(), 50);
+        for thread in &handles[1..] {
+            assert_eq!(thread, &handles[0]);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "cached-hash")]
+    fn test_table_hash() {
+        let table = InternTable::new();
+        let s = table.get_or_intern("cached");
+        let hasher = TABLE_HASHER;
+        assert_eq!(s.table_hash(), hasher.hash_one("cached"));
+        assert_eq!(EMPTY_FAST_STR.table_hash(), hasher.hash_one(""));
+    }
+
+    #[test]
+    fn test_to_arc_str() {
+        let table = InternTable::new();
+        let s = table.get_or_intern("shared");
+        let arc: std::sync::Arc<str> = s.into();
+        assert_eq!(&*arc, "shared");
+        assert_eq!(&*s.to_rc_str(), "shared");
+        assert_eq!(&*EMPTY_FAST_STR.to_arc_str(), "");
+    }
+
+    #[test]
+    fn test_data_ptr_stable() {
+        let table = InternTable::new();
+        let s = table.get_or_intern("pinned");
+        let ptr = s.data_ptr();
+        assert_eq!(s.as_str().as_ptr(), ptr);
+        // Grow the table through several rehashes.
+        for i in 0..10_000 {
+            table.get_or_intern(&i.to_string());
+        }
+        assert_eq!(table.get_or_intern("pinned").data_ptr(), ptr);
+        let t = unsafe { Istr::from_data_ptr(ptr) };
+        assert_eq!(t.0, s.0);
+        assert_eq!(t.as_str(), "pinned");
+        let empty = table.get_or_intern("");
+        assert_eq!(empty.as_str().as_ptr(), empty.data_ptr());
+        assert!(unsafe { Istr::from_data_ptr(empty.data_ptr()) }.is_empty());
+    }
+
+    #[test]
+    fn test_ptr_round_trip() {
+        let table = InternTable::new();
+        for s in [table.get_or_intern("ffi"), table.get_or_intern("")] {
+            assert_eq!(unsafe { Istr::from_ptr(s.into_ptr()) }, s);
+            let addr = s.into_ptr().expose_provenance();
+            let t = unsafe { Istr::from_ptr(std::ptr::with_exposed_provenance(addr)) };
+            assert_eq!((t, t.as_str()), (s, s.as_str()));
+        }
+        assert_eq!(table.get_or_intern("ffi").into_ptr(), table.get_or_intern("ffi").into_ptr());
+        let as_ptr = |s: Option<Istr>| unsafe { std::mem::transmute::<_, *const ()>(s) };
+        assert_eq!(as_ptr(None), std::ptr::null());
+        let s = table.get_or_intern("ffi");
+        assert_eq!(as_ptr(Some(s)), s.into_ptr());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "interned 1 new string")]
+    fn test_assert_no_new_interns() {
+        let table = InternTable::new();
+        table.get_or_intern("seen");
+        assert_eq!(assert_no_new_interns(|| table.get_or_intern("seen").len()), 4);
+        assert_no_new_interns(|| table.get_or_intern("unseen"));
+    }
+
+    #[test]
+    fn test_table_empty_string() {
+        let table = InternTable::new();
+        assert!(table.get_or_intern("").is_empty());
+        assert_eq!(table.try_get_or_intern(""), Some(EMPTY_FAST_STR));
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "global")]
     fn test_new_same_address() {
         let s = Istr::new("Hello");
         assert_eq!(s, "Hello");
         let t = Istr::new("Hello");
         assert_eq!(s.0, t.0);
     }
+
+    #[test]
+    #[cfg(feature = "stable-hash")]
+    fn test_stable_hash() {
+        // Reference value from the XXH3 specification's test vectors.
+        assert_eq!(stable_hash(""), 0x2d06800538d394c2);
+        let table = InternTable::new();
+        let s = table.get_or_intern("stable");
+        assert_eq!(s.stable_hash(), stable_hash("stable"));
+        assert_eq!(table.get_or_intern("").stable_hash(), stable_hash(""));
+    }
+
+    #[test]
+    #[cfg(feature = "global")]
+    fn test_try_intern_contended() {
+        let s = Istr::new("contended");
+        let locks: Vec<_> = GLOBAL_TABLE.0.iter().map(|s| s.lock.write()).collect();
+        let handle = std::thread::spawn(move || {
+            // Hits don't take the lock, so only misses are refused.
+            assert_eq!(try_intern("contended"), Some(s));
+            intern_or_borrow("not yet interned")
+        });
+        let borrowed = handle.join().unwrap();
+        drop(locks);
+        assert!(!borrowed.is_interned());
+        assert_eq!(borrowed, "not yet interned");
+        assert_eq!(try_intern("contended"), Some(s));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "re-entrant")]
+    fn test_reentrant_lock_panics() {
+        let table = InternTable::new();
+        table.write(0, |_| table.get_or_intern("reentrant"));
+    }
 }
@@ -0,0 +1,90 @@
+//! A single-threaded interner.
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+use crate::{Entries, InternTable, Istr, EMPTY_FAST_STR};
+
+/// An interner owned by a single thread.
+///
+/// Its table is guarded by a `RefCell` rather than a lock, so interning performs no atomic
+/// operations. It is deliberately neither `Send` nor `Sync`:
+///
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<lsi::LocalInterner>();
+/// ```
+///
+/// The handles it returns are ordinary [`Istr`]s, which may be sent to other threads and
+/// outlive the interner. They are not deduplicated against other tables: interning the same
+/// string here and in the global table gives two distinct entries.
+#[derive(Debug)]
+pub struct LocalInterner {
+    entries: RefCell<Entries>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl LocalInterner {
+    pub const fn new() -> Self {
+        LocalInterner {
+            entries: RefCell::new(Entries::new()),
+            _not_send: PhantomData,
+        }
+    }
+
+    pub fn get_or_intern(&self, s: &str) -> Istr {
+        if s.is_empty() {
+            return EMPTY_FAST_STR;
+        }
+        let hash = InternTable::hash(s);
+        if let Some(fast_str) = InternTable::find(&self.entries.borrow(), s, hash) {
+            return fast_str;
+        }
+        InternTable::insert_hashed(&mut self.entries.borrow_mut(), s, hash).0
+    }
+
+    /// Look `s` up without inserting it.
+    pub fn get(&self, s: &str) -> Option<Istr> {
+        if s.is_empty() {
+            return Some(EMPTY_FAST_STR);
+        }
+        InternTable::find(&self.entries.borrow(), s, InternTable::hash(s))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A copy of every string currently in the interner, in no particular order.
+    pub fn to_vec(&self) -> Vec<Istr> {
+        self.entries.borrow().set.iter().copied().collect()
+    }
+}
+
+impl Default for LocalInterner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_interner() {
+        let interner = LocalInterner::new();
+        let a = interner.get_or_intern("local");
+        assert_eq!(
+            a.as_str().as_ptr(),
+            interner.get_or_intern("local").as_str().as_ptr()
+        );
+        assert!(interner.get_or_intern("").is_empty());
+        assert_eq!(interner.len(), 1);
+        // Handles are `Send` even though the interner isn't.
+        assert_eq!(std::thread::spawn(move || a.len()).join().unwrap(), 5);
+    }
+}
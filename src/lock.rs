@@ -0,0 +1,75 @@
+//! The shard locks of [`InternTable`](crate::InternTable): `std::sync::RwLock` by default, or
+//! `parking_lot::RwLock` with the `parking_lot` feature.
+//!
+//! Both are wrapped in the same small API. The std lock's poisoning is turned into a panic:
+//! the table never panics while holding a lock, so a poisoned lock means the table's
+//! contents can't be trusted.
+use std::ops::{Deref, DerefMut};
+
+#[derive(Debug)]
+pub(crate) struct RwLock<T>(
+    #[cfg(not(feature = "parking_lot"))] std::sync::RwLock<T>,
+    #[cfg(feature = "parking_lot")] parking_lot::RwLock<T>,
+);
+
+#[cfg(not(feature = "parking_lot"))]
+impl<T> RwLock<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        RwLock(std::sync::RwLock::new(value))
+    }
+
+    pub(crate) fn read(&self) -> impl Deref<Target = T> + '_ {
+        self.0.read().unwrap()
+    }
+
+    pub(crate) fn write(&self) -> impl DerefMut<Target = T> + '_ {
+        self.0.write().unwrap()
+    }
+
+    /// `None` if the lock is held by a writer.
+    #[cfg(feature = "global")]
+    pub(crate) fn try_read(&self) -> Option<impl Deref<Target = T> + '_> {
+        use std::sync::TryLockError;
+        match self.0.try_read() {
+            Ok(lock) => Some(lock),
+            Err(TryLockError::WouldBlock) => None,
+            Err(TryLockError::Poisoned(e)) => panic!("{}", e),
+        }
+    }
+
+    /// `None` if the lock is held.
+    pub(crate) fn try_write(&self) -> Option<impl DerefMut<Target = T> + '_> {
+        use std::sync::TryLockError;
+        match self.0.try_write() {
+            Ok(lock) => Some(lock),
+            Err(TryLockError::WouldBlock) => None,
+            Err(TryLockError::Poisoned(e)) => panic!("{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "parking_lot")]
+impl<T> RwLock<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        RwLock(parking_lot::RwLock::new(value))
+    }
+
+    pub(crate) fn read(&self) -> impl Deref<Target = T> + '_ {
+        self.0.read()
+    }
+
+    pub(crate) fn write(&self) -> impl DerefMut<Target = T> + '_ {
+        self.0.write()
+    }
+
+    /// `None` if the lock is held by a writer.
+    #[cfg(feature = "global")]
+    pub(crate) fn try_read(&self) -> Option<impl Deref<Target = T> + '_> {
+        self.0.try_read()
+    }
+
+    /// `None` if the lock is held.
+    pub(crate) fn try_write(&self) -> Option<impl DerefMut<Target = T> + '_> {
+        self.0.try_write()
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+use crate::Istr;
+
+/// A string that may or may not have been interned, returned by the non-blocking interning
+/// paths such as [`intern_or_borrow`](crate::intern_or_borrow).
+///
+/// Equality and hashing are by string contents, so an interned and a borrowed value of the
+/// same string compare equal.
+#[derive(Copy, Clone, Debug)]
+pub enum MaybeInterned<'a> {
+    Interned(Istr),
+    Borrowed(&'a str),
+}
+
+impl<'a> MaybeInterned<'a> {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MaybeInterned::Interned(s) => s.as_str(),
+            MaybeInterned::Borrowed(s) => s,
+        }
+    }
+
+    pub fn is_interned(&self) -> bool {
+        matches!(self, MaybeInterned::Interned(_))
+    }
+
+    pub fn interned(&self) -> Option<Istr> {
+        match self {
+            MaybeInterned::Interned(s) => Some(*s),
+            MaybeInterned::Borrowed(_) => None,
+        }
+    }
+
+    /// Intern the string if it isn't already, blocking on the table lock if necessary.
+    #[cfg(feature = "global")]
+    pub fn into_istr(self) -> Istr {
+        match self {
+            MaybeInterned::Interned(s) => s,
+            MaybeInterned::Borrowed(s) => Istr::new(s),
+        }
+    }
+}
+
+impl Deref for MaybeInterned<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for MaybeInterned<'_> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<Istr> for MaybeInterned<'_> {
+    fn from(s: Istr) -> Self {
+        MaybeInterned::Interned(s)
+    }
+}
+
+impl PartialEq for MaybeInterned<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl PartialEq<&str> for MaybeInterned<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Eq for MaybeInterned<'_> {}
+
+impl Hash for MaybeInterned<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Display for MaybeInterned<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
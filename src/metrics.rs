@@ -0,0 +1,74 @@
+//! Interner health through the [`metrics`] facade.
+//!
+//! With the `metrics` feature, the crate records the metrics below with whatever recorder the
+//! process installed, e.g. `metrics-exporter-prometheus`. Call [`describe`] once after
+//! installing it to give each metric its unit and help text.
+//!
+//! | Metric | Kind | |
+//! |---|---|---|
+//! | [`INTERNED_TOTAL`] | counter | Strings inserted into any table. |
+//! | [`BYTES_TOTAL`] | counter | The bytes of those strings. |
+//! | [`LOCK_CONTENTION`] | counter | Shard lock acquisitions that waited for another thread. |
+//! | [`EVICTIONS`] | counter | Strings [evicted](crate::rc::set_capacity) from the `rc` table. |
+//! | [`STRINGS`] | gauge | Strings in the global table. |
+//!
+//! Hits aren't recorded: they are the hot path, and a recorder call costs more than a hit.
+#[cfg(feature = "global")]
+use ::metrics::gauge;
+use ::metrics::{counter, describe_counter, describe_gauge, Unit};
+
+use crate::Istr;
+
+pub const INTERNED_TOTAL: &str = "lsi_interned_total";
+pub const BYTES_TOTAL: &str = "lsi_bytes_total";
+pub const LOCK_CONTENTION: &str = "lsi_lock_contention_total";
+pub const EVICTIONS: &str = "lsi_evictions_total";
+pub const STRINGS: &str = "lsi_strings";
+
+/// Describe every metric to the installed recorder.
+pub fn describe() {
+    describe_counter!(INTERNED_TOTAL, Unit::Count, "Strings inserted into any table.");
+    describe_counter!(BYTES_TOTAL, Unit::Bytes, "The bytes of the strings inserted.");
+    describe_counter!(
+        LOCK_CONTENTION,
+        Unit::Count,
+        "Shard lock acquisitions that had to wait for another thread."
+    );
+    describe_counter!(EVICTIONS, Unit::Count, "Strings evicted from the RcIstr table.");
+    describe_gauge!(STRINGS, Unit::Count, "Strings in the global table.");
+}
+
+/// Called when `s` is inserted into a table.
+pub(crate) fn inserted(s: Istr) {
+    counter!(INTERNED_TOTAL).increment(1);
+    counter!(BYTES_TOTAL).increment(s.len() as u64);
+}
+
+/// Called when a string is inserted into the global table.
+#[cfg(feature = "global")]
+pub(crate) fn global_inserted() {
+    gauge!(STRINGS).increment(1.0);
+}
+
+/// Called when a string is removed from the global table.
+#[cfg(feature = "global")]
+pub(crate) fn global_removed() {
+    gauge!(STRINGS).decrement(1.0);
+}
+
+/// Called when the global table is reset.
+#[cfg(feature = "global")]
+pub(crate) fn global_cleared() {
+    gauge!(STRINGS).set(0.0);
+}
+
+/// Called when acquiring a shard's write lock has to wait.
+pub(crate) fn contended() {
+    counter!(LOCK_CONTENTION).increment(1);
+}
+
+/// Called when `n` strings are evicted from the `RcIstr` table.
+#[cfg(feature = "lru")]
+pub(crate) fn evicted(n: usize) {
+    counter!(EVICTIONS).increment(n as u64);
+}
@@ -0,0 +1,255 @@
+//! Domain newtypes over [`Istr`](crate::Istr).
+//!
+//! [`interned_newtype!`](crate::interned_newtype) declares a `struct UserName(Istr)`-style
+//! newtype with constructors that intern (optionally validating first), comparison and hashing
+//! by handle, ordering and formatting by string and, with the `serde` feature, serde impls
+//! forwarded to the string.
+//!
+//! ```
+//! lsi::interned_newtype! {
+//!     /// A login name: lowercase ASCII letters only.
+//!     pub struct UserName(lsi::Istr);
+//!     validate = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_lowercase());
+//! }
+//!
+//! let name = UserName::new("alice");
+//! assert_eq!(name, "alice");
+//! assert_eq!(name.to_string(), "alice");
+//! assert!(UserName::try_new("Mallory").is_err());
+//! assert_eq!("bob".parse::<UserName>().unwrap().as_str(), "bob");
+//! ```
+use std::fmt;
+
+/// Returned when a string is rejected by the validation of an
+/// [`interned_newtype!`](crate::interned_newtype), by `from_istr`, or by `try_new` in
+/// [`Error::Invalid`](crate::Error::Invalid). The string is not interned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invalid {
+    pub type_name: &'static str,
+    pub value: Box<str>,
+}
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} is not a valid {}", self.value, self.type_name)
+    }
+}
+
+impl std::error::Error for Invalid {}
+
+/// Declare an interned newtype. See the [`newtype`](crate::newtype) module for an example.
+///
+/// The newtype gets `new` (panicking on invalid input), `try_new`, `from_istr`, `as_str` and
+/// `as_istr`; `Copy`, `Eq` and `Hash` by handle, which agree with comparing the strings as
+/// they are interned into the same table; `Ord` by string; `Debug`, `Display`, `AsRef<str>`,
+/// `FromStr`, comparisons with `str`, and a conversion into `Istr`. The optional `validate`
+/// expression is a `fn(&str) -> bool`, or a closure coercible to one.
+///
+/// `try_new` and `FromStr` fail with [`Error::Invalid`](crate::Error::Invalid) if the string
+/// fails validation, and with the errors of [`Istr::try_new`](crate::Istr::try_new) if it can't
+/// be interned.
+#[macro_export]
+macro_rules! interned_newtype {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident($field_vis:vis $inner:ty);
+        $(validate = $validate:expr;)?
+    ) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone)]
+        $vis struct $name($field_vis $inner);
+
+        #[allow(dead_code)]
+        impl $name {
+            /// Intern `s` as a
+            #[doc = concat!("`", stringify!($name), "`.")]
+            ///
+            /// # Panics
+            ///
+            /// If `s` fails validation, or can't be interned.
+            pub fn new(s: &str) -> Self {
+                match Self::try_new(s) {
+                    Ok(v) => v,
+                    Err(e) => panic!("{}", e),
+                }
+            }
+
+            /// Intern `s` if it passes validation.
+            pub fn try_new(s: &str) -> ::std::result::Result<Self, $crate::Error> {
+                Self::check(s)?;
+                Ok($name($crate::Istr::try_new(s)?))
+            }
+
+            /// Wrap an already interned string if it passes validation.
+            pub fn from_istr(s: $crate::Istr) -> ::std::result::Result<Self, $crate::newtype::Invalid> {
+                Self::check(s.as_str())?;
+                Ok($name(s))
+            }
+
+            pub fn as_str(&self) -> &'static str {
+                self.0.as_str()
+            }
+
+            pub fn as_istr(&self) -> $crate::Istr {
+                self.0
+            }
+
+            #[allow(unused_variables)]
+            fn check(s: &str) -> ::std::result::Result<(), $crate::newtype::Invalid> {
+                $(
+                    let validate: fn(&str) -> bool = $validate;
+                    if !validate(s) {
+                        return Err($crate::newtype::Invalid {
+                            type_name: stringify!($name),
+                            value: s.into(),
+                        });
+                    }
+                )?
+                Ok(())
+            }
+        }
+
+        impl ::std::cmp::PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl ::std::cmp::Eq for $name {}
+
+        impl ::std::cmp::PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.as_str() == other
+            }
+        }
+
+        impl ::std::cmp::PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.as_str() == *other
+            }
+        }
+
+        impl ::std::cmp::PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> ::std::option::Option<::std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl ::std::cmp::Ord for $name {
+            fn cmp(&self, other: &Self) -> ::std::cmp::Ordering {
+                self.as_str().cmp(other.as_str())
+            }
+        }
+
+        impl ::std::hash::Hash for $name {
+            fn hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+                self.0.hash(state)
+            }
+        }
+
+        impl ::std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.debug_tuple(stringify!($name)).field(&self.as_str()).finish()
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl ::std::convert::AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                self.as_str()
+            }
+        }
+
+        impl ::std::str::FromStr for $name {
+            type Err = $crate::Error;
+
+            fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+                Self::try_new(s)
+            }
+        }
+
+        impl ::std::convert::From<$name> for $crate::Istr {
+            fn from(v: $name) -> $crate::Istr {
+                v.0
+            }
+        }
+
+        $crate::__newtype_serde!($name);
+    };
+}
+
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __newtype_serde {
+    ($name:ident) => {
+        impl $crate::__private::serde::Serialize for $name {
+            fn serialize<S: $crate::__private::serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> ::std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> $crate::__private::serde::Deserialize<'de> for $name {
+            fn deserialize<D: $crate::__private::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> ::std::result::Result<Self, D::Error> {
+                let s = <$crate::Istr as $crate::__private::serde::Deserialize>::deserialize(
+                    deserializer,
+                )?;
+                Self::from_istr(s)
+                    .map_err(<D::Error as $crate::__private::serde::de::Error>::custom)
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __newtype_serde {
+    ($name:ident) => {};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Istr;
+
+    crate::interned_newtype! {
+        struct Tag(Istr);
+    }
+
+    crate::interned_newtype! {
+        struct Slug(Istr);
+        validate = |s: &str| s.bytes().all(|b| b.is_ascii_lowercase() || b == b'-');
+    }
+
+    #[test]
+    fn test_newtype() {
+        let tag = Tag::new("Any Thing");
+        assert_eq!(tag.as_istr(), Istr::new("Any Thing"));
+        assert_eq!(format!("{:?}", tag), "Tag(\"Any Thing\")");
+        assert!(Tag::new("a") < Tag::new("b"));
+
+        assert_eq!(Slug::new("hello-world"), "hello-world");
+        let err = Slug::try_new("Hello World").unwrap_err();
+        assert_eq!(err.to_string(), "\"Hello World\" is not a valid Slug");
+        assert!(Slug::from_istr(Istr::new("UPPER")).is_err());
+        assert_eq!(Istr::from(Slug::new("a-b")), Istr::new("a-b"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "serde", feature = "json"))]
+    fn test_newtype_serde() {
+        let slug: Slug = serde_json::from_str("\"ok-slug\"").unwrap();
+        assert_eq!(serde_json::to_string(&slug).unwrap(), "\"ok-slug\"");
+        assert!(serde_json::from_str::<Slug>("\"Not Ok\"").is_err());
+    }
+}
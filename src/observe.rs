@@ -0,0 +1,112 @@
+//! Callbacks on every new string of the global table.
+//!
+//! An observer [registered](register) is called with each string inserted into the
+//! [`GLOBAL_TABLE`](crate::GLOBAL_TABLE) from then on, and its length, so a downstream cache,
+//! debugger or dictionary builder can mirror the table without polling it:
+//!
+//! ```
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use std::sync::Arc;
+//!
+//! let bytes = Arc::new(AtomicUsize::new(0));
+//! let counted = bytes.clone();
+//! let id = lsi::observe::register(move |_, len| {
+//!     counted.fetch_add(len, Ordering::Relaxed);
+//! });
+//! lsi::Istr::new("observed");
+//! assert_eq!(bytes.load(Ordering::Relaxed), 8);
+//! assert!(lsi::observe::unregister(id));
+//! ```
+//!
+//! Observers run on the interning thread, after the table's lock is released, so they may
+//! intern, and are called in turn for the strings they intern for the first time. Strings that
+//! were already interned don't reach them.
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::Istr;
+
+type Observer = Arc<dyn Fn(Istr, usize) + Send + Sync>;
+
+type Observers = Arc<[(ObserverId, Observer)]>;
+
+/// The observers, replaced as a whole when one is registered or unregistered, so that calling
+/// them doesn't hold the lock.
+static OBSERVERS: RwLock<Option<Observers>> = RwLock::new(None);
+
+/// Whether there are any observers, checked before taking the lock.
+static ANY: AtomicBool = AtomicBool::new(false);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Identifies a registered observer, for [`unregister`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ObserverId(u64);
+
+/// Call `f` with every string inserted into the global table from now on, and its length in
+/// bytes.
+pub fn register(f: impl Fn(Istr, usize) + Send + Sync + 'static) -> ObserverId {
+    let id = ObserverId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    update(|observers| observers.push((id, Arc::new(f))));
+    id
+}
+
+/// Stop calling the observer `id`. Returns whether it was registered.
+pub fn unregister(id: ObserverId) -> bool {
+    let mut found = false;
+    update(|observers| {
+        let len = observers.len();
+        observers.retain(|&(other, _)| other != id);
+        found = observers.len() != len;
+    });
+    found
+}
+
+fn update(f: impl FnOnce(&mut Vec<(ObserverId, Observer)>)) {
+    let mut lock = OBSERVERS.write().unwrap();
+    let mut observers = lock.as_deref().map_or_else(Vec::new, <[_]>::to_vec);
+    f(&mut observers);
+    ANY.store(!observers.is_empty(), Ordering::Release);
+    *lock = (!observers.is_empty()).then(|| observers.into());
+}
+
+/// Called once the lock has been released after inserting `s` into the global table.
+pub(crate) fn inserted(s: Istr) {
+    if !ANY.load(Ordering::Acquire) {
+        return;
+    }
+    let observers = OBSERVERS.read().unwrap().clone();
+    for (_, observer) in observers.iter().flat_map(|o| o.iter()) {
+        observer(s, s.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_observers() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let first = register({
+            let seen = seen.clone();
+            move |s, len| {
+                if s.as_str().starts_with("observe-") {
+                    assert_eq!(len, s.len());
+                    seen.lock().unwrap().push(s);
+                    // Observers may intern.
+                    Istr::new("observe-nested");
+                }
+            }
+        });
+        let a = Istr::new("observe-a");
+        Istr::new("observe-a");
+        let batch = Istr::intern_all(&["observe-b", "observe-a"]).unwrap();
+        assert!(unregister(first));
+        assert!(!unregister(first));
+        Istr::new("observe-c");
+        let nested = Istr::get("observe-nested").unwrap();
+        assert_eq!(*seen.lock().unwrap(), [a, nested, batch[0]]);
+    }
+}
@@ -0,0 +1,357 @@
+//! Persisting the active table across restarts.
+//!
+//! [`open_log`] replays an append-only log of interned strings into the
+//! [active table](crate::active_table), then appends every string interned afterwards, and
+//! every string removed by [`remove`](crate::remove), [`retain`](crate::retain), a checkpoint
+//! restore or a [`reset`](crate::reset), so that replaying doesn't bring them back. A process
+//! that adds a trickle of new strings between restarts only writes those strings, rather than a
+//! full snapshot of the table each time. [`AppendLog::compact`] rotates the log, replacing it
+//! with exactly the current contents of the table.
+//!
+//! Like the table's other observers, the log only records the strings interned and removed by
+//! the copy of the crate it runs in, see the [`shared`](crate::shared) module.
+//!
+//! # Format
+//!
+//! A log starts with the 8-byte header `LSILOG\0\x02` (the last byte is the format version),
+//! followed by one record per string: its byte length as a little-endian `u32`, then its UTF-8
+//! bytes. The top bit of the length is set in the record of a removed string, and a removal of
+//! the empty string, which is never removed otherwise, records a reset. A compacted log and a
+//! snapshot written by [`write_snapshot`] are the same format. A truncated final record, e.g.
+//! from a crash mid-write, is discarded on replay. Logs of version 1, which has no removals,
+//! are upgraded when opened.
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::{active_table, Istr};
+
+pub(crate) const MAGIC: &[u8; 8] = b"LSILOG\x00\x02";
+/// The header of the first version of the format, which had no removals.
+const MAGIC_V1: &[u8; 8] = b"LSILOG\x00\x01";
+/// Set in the length of a record to mark a removal.
+const REMOVED: u32 = 1 << 31;
+
+/// Set while a log is open, so that interning doesn't touch `SINK` otherwise.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+
+struct Sink {
+    file: BufWriter<File>,
+    /// The first error hit while appending, reported by the next flush.
+    error: Option<io::Error>,
+}
+
+/// Replay the log at `path` into the active table, creating it if it doesn't exist, and append
+/// every string interned or removed from now on. Only one log can be open at a time.
+///
+/// Strings interned by other threads while the log is being replayed are not recorded.
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if a string can't be interned, e.g. over the
+/// [budget](crate::budget) or missing from the [frozen](crate::frozen) table, wrapping the
+/// error of [`Istr::try_new`]. The strings replayed before it stay interned, and the log isn't
+/// opened.
+pub fn open_log(path: impl AsRef<Path>) -> io::Result<AppendLog> {
+    let path = path.as_ref().to_path_buf();
+    let mut sink = SINK.lock().unwrap();
+    if sink.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "a log is already open",
+        ));
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)?;
+    let mut magic = *MAGIC;
+    let end = if file.metadata()?.len() == 0 {
+        file.write_all(MAGIC)?;
+        MAGIC.len() as u64
+    } else {
+        file.read_exact(&mut magic)?;
+        file.rewind()?;
+        let (strings, end) = replay(BufReader::new(&mut file))?;
+        for s in strings {
+            Istr::try_new(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        end
+    };
+    file.set_len(end)?;
+    file.seek(SeekFrom::Start(end))?;
+    *sink = Some(Sink {
+        file: BufWriter::new(file),
+        error: None,
+    });
+    drop(sink);
+    ACTIVE.store(true, Ordering::Release);
+    let log = AppendLog { path };
+    if &magic == MAGIC_V1 {
+        log.compact()?;
+    }
+    Ok(log)
+}
+
+/// Read every string recorded in the log or snapshot at `path` and not removed since, in log
+/// order.
+pub fn read_log(path: impl AsRef<Path>) -> io::Result<Vec<String>> {
+    Ok(replay(BufReader::new(File::open(path)?))?.0)
+}
+
+/// Write the current contents of the active table to `path`, in the log format.
+pub fn write_snapshot(path: impl AsRef<Path>) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(MAGIC)?;
+    for s in active_table().to_vec() {
+        write_record(&mut file, s.as_str())?;
+    }
+    file.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+/// The handle of the open log. Dropping it flushes and closes the log.
+#[derive(Debug)]
+pub struct AppendLog {
+    path: PathBuf,
+}
+
+impl AppendLog {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Flush buffered records, reporting any error hit while appending since the last flush.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut sink = SINK.lock().unwrap();
+        let sink = sink.as_mut().expect("log is open");
+        if let Some(e) = sink.error.take() {
+            return Err(e);
+        }
+        sink.file.flush()
+    }
+
+    /// Replace the log with a snapshot of the current table.
+    pub fn compact(&self) -> io::Result<()> {
+        let mut sink = SINK.lock().unwrap();
+        let sink = sink.as_mut().expect("log is open");
+        if let Some(e) = sink.error.take() {
+            return Err(e);
+        }
+        sink.file.flush()?;
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        write_snapshot(&tmp)?;
+        fs::rename(&tmp, &self.path)?;
+        let file = OpenOptions::new().append(true).open(&self.path)?;
+        sink.file = BufWriter::new(file);
+        Ok(())
+    }
+
+    /// Flush and close the log, reporting any error.
+    pub fn close(self) -> io::Result<()> {
+        let result = self.flush();
+        drop(self);
+        result
+    }
+}
+
+impl Drop for AppendLog {
+    fn drop(&mut self) {
+        ACTIVE.store(false, Ordering::Release);
+        if let Some(mut sink) = SINK.lock().unwrap().take() {
+            let _ = sink.file.flush();
+        }
+    }
+}
+
+/// Called by the active table after a new string is inserted.
+pub(crate) fn inserted(s: Istr) {
+    append(|w| write_record(w, s.as_str()));
+}
+
+/// Called by the active table after a string is removed, before it is freed.
+pub(crate) fn removed(s: Istr) {
+    append(|w| write_tagged(w, REMOVED, s.as_str()));
+}
+
+/// Called when the active table is reset, before its strings are freed.
+pub(crate) fn cleared() {
+    append(|w| write_tagged(w, REMOVED, ""));
+}
+
+fn append(record: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>) {
+    if !ACTIVE.load(Ordering::Acquire) {
+        return;
+    }
+    if let Some(sink) = SINK.lock().unwrap().as_mut() {
+        if sink.error.is_none() {
+            sink.error = record(&mut sink.file).err();
+        }
+    }
+}
+
+/// A record of a log.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Record<'a> {
+    Interned(&'a str),
+    Removed(&'a str),
+    Reset,
+}
+
+pub(crate) fn write_record(w: &mut impl Write, s: &str) -> io::Result<()> {
+    write_tagged(w, 0, s)
+}
+
+fn write_tagged(w: &mut impl Write, tag: u32, s: &str) -> io::Result<()> {
+    let len = u32::try_from(s.len())
+        .ok()
+        .filter(|&len| len < REMOVED)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "string too long to log"))?;
+    w.write_all(&(tag | len).to_le_bytes())?;
+    w.write_all(s.as_bytes())
+}
+
+/// The strings of a log that haven't been removed since, in log order, and the offset just
+/// past the last complete record.
+fn replay(r: impl Read) -> io::Result<(Vec<String>, u64)> {
+    let mut strings: Vec<Option<String>> = Vec::new();
+    // Where each string is in `strings`, more than once if it was recorded more than once.
+    let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+    let end = read_records(r, |record| match record {
+        Record::Interned(s) => {
+            positions
+                .entry(s.to_owned())
+                .or_default()
+                .push(strings.len());
+            strings.push(Some(s.to_owned()));
+        }
+        Record::Removed(s) => {
+            for i in positions.remove(s).unwrap_or_default() {
+                strings[i] = None;
+            }
+        }
+        Record::Reset => {
+            strings.clear();
+            positions.clear();
+        }
+    })?;
+    Ok((strings.into_iter().flatten().collect(), end))
+}
+
+/// Call `f` for every complete record, returning the offset just past the last one.
+pub(crate) fn read_records(mut r: impl Read, mut f: impl FnMut(Record<'_>)) -> io::Result<u64> {
+    let mut magic = [0; 8];
+    r.read_exact(&mut magic)?;
+    let removals = match &magic {
+        MAGIC => true,
+        MAGIC_V1 => false,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "not an lsi log")),
+    };
+    let mut end = MAGIC.len() as u64;
+    let mut buf = Vec::new();
+    loop {
+        let mut len = [0; 4];
+        if !read_full(&mut r, &mut len)? {
+            return Ok(end);
+        }
+        let mut len = u32::from_le_bytes(len);
+        let removed = removals && len & REMOVED != 0;
+        if removed {
+            len &= !REMOVED;
+        }
+        buf.resize(len as usize, 0);
+        if !read_full(&mut r, &mut buf)? {
+            return Ok(end);
+        }
+        let s =
+            std::str::from_utf8(&buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        f(match (removed, s) {
+            (false, s) => Record::Interned(s),
+            (true, "") => Record::Reset,
+            (true, s) => Record::Removed(s),
+        });
+        end += 4 + buf.len() as u64;
+    }
+}
+
+/// Like `read_exact`, but returns `false` instead of failing at end of file.
+fn read_full(r: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    match r.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_replay_and_compact() {
+        let path = std::env::temp_dir().join(format!("lsi-test-{}.log", std::process::id()));
+        let mut contents = MAGIC.to_vec();
+        write_record(&mut contents, "replayed from log").unwrap();
+        // A torn record at the end is discarded.
+        contents.extend_from_slice(&[9, 0, 0, 0, b'x']);
+        fs::write(&path, &contents).unwrap();
+
+        let log = open_log(&path).unwrap();
+        assert!(active_table()
+            .to_vec()
+            .contains(&Istr::new("replayed from log")));
+        assert!(open_log(&path).is_err());
+        Istr::new("appended to log");
+        log.flush().unwrap();
+        // Other tests may be interning concurrently, so only check for our own records.
+        let records = read_log(&path).unwrap();
+        assert_eq!(records[0], "replayed from log");
+        assert!(records.iter().any(|s| s == "appended to log"));
+
+        log.compact().unwrap();
+        Istr::new("appended after compaction");
+        log.close().unwrap();
+        let records = read_log(&path).unwrap();
+        for s in [
+            "replayed from log",
+            "appended to log",
+            "appended after compaction",
+        ] {
+            assert_eq!(records.iter().filter(|r| *r == s).count(), 1);
+        }
+
+        // A version 1 log is replayed, then rewritten in the current format.
+        let mut contents = MAGIC_V1.to_vec();
+        write_record(&mut contents, "replayed from version 1").unwrap();
+        fs::write(&path, &contents).unwrap();
+        open_log(&path).unwrap().close().unwrap();
+        assert_eq!(&fs::read(&path).unwrap()[..8], MAGIC);
+        assert!(read_log(&path)
+            .unwrap()
+            .iter()
+            .any(|s| s == "replayed from version 1"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_replay_removals() {
+        let mut contents = MAGIC.to_vec();
+        for s in ["a", "b", "a", "c"] {
+            write_record(&mut contents, s).unwrap();
+        }
+        write_tagged(&mut contents, REMOVED, "a").unwrap();
+        write_record(&mut contents, "d").unwrap();
+        let (strings, end) = replay(&contents[..]).unwrap();
+        assert_eq!(
+            (strings, end),
+            (vec!["b".into(), "c".into(), "d".into()], 38)
+        );
+        write_tagged(&mut contents, REMOVED, "").unwrap();
+        write_record(&mut contents, "e").unwrap();
+        assert_eq!(replay(&contents[..]).unwrap().0, ["e"]);
+    }
+}
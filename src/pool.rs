@@ -0,0 +1,208 @@
+//! Named pools: a separate table per subsystem.
+//!
+//! [`Pool::named`] returns the process-wide pool with that name, creating it on first use, so
+//! unrelated strings, like HTTP header names and user content, don't share a table. Each pool
+//! reports its own [`PoolStats`], and can be [prefilled](Pool::prefill) with its known strings
+//! and then [frozen](Pool::freeze), after which it only hands out the strings it already has.
+//!
+//! ```
+//! use lsi::pool::Pool;
+//!
+//! let headers = Pool::named("doc-http-headers");
+//! headers.prefill(["content-type", "content-length", "host"]);
+//! headers.freeze();
+//! assert_eq!(headers.get_or_intern("host"), "host");
+//! assert!(headers.try_get_or_intern("x-custom").is_err());
+//! assert!(std::ptr::eq(headers, Pool::named("doc-http-headers")));
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use crate::{Error, InternTable, InternerBackend, Istr};
+
+static POOLS: RwLock<Option<HashMap<&'static str, &'static Pool>>> = RwLock::new(None);
+
+/// A named table, living for the rest of the process.
+#[derive(Debug)]
+pub struct Pool {
+    name: &'static str,
+    table: InternTable,
+    frozen: AtomicBool,
+}
+
+impl Pool {
+    /// The pool named `name`, created empty the first time it is asked for.
+    pub fn named(name: &str) -> &'static Pool {
+        if let Some(&pool) = POOLS.read().unwrap().as_ref().and_then(|m| m.get(name)) {
+            return pool;
+        }
+        let mut lock = POOLS.write().unwrap();
+        let pools = lock.get_or_insert_with(HashMap::new);
+        if let Some(&pool) = pools.get(name) {
+            return pool;
+        }
+        let name: &'static str = Box::leak(name.into());
+        let pool = Box::leak(Box::new(Pool {
+            name,
+            table: InternTable::new(),
+            frozen: AtomicBool::new(false),
+        }));
+        pools.insert(name, pool);
+        pool
+    }
+
+    /// Every pool created so far, sorted by name.
+    pub fn all() -> Vec<&'static Pool> {
+        let mut pools: Vec<_> = POOLS
+            .read()
+            .unwrap()
+            .as_ref()
+            .map_or_else(Vec::new, |m| m.values().copied().collect());
+        pools.sort_by_key(|pool| pool.name);
+        pools
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The pool's table, for the rest of the table API. Interning into it directly bypasses
+    /// the check for a frozen pool.
+    pub fn table(&self) -> &InternTable {
+        &self.table
+    }
+
+    /// # Panics
+    ///
+    /// If the pool is frozen and doesn't have `s`, or `s` is longer than
+    /// [`MAX_LEN`](crate::MAX_LEN).
+    pub fn get_or_intern(&self, s: &str) -> Istr {
+        match self.try_get_or_intern(s) {
+            Ok(fast_str) => fast_str,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [`Pool::get_or_intern`], but returns [`Error::Frozen`] or [`Error::TooLong`]
+    /// instead of panicking.
+    pub fn try_get_or_intern(&self, s: &str) -> Result<Istr, Error> {
+        if let Some(fast_str) = self.table.get(s) {
+            return Ok(fast_str);
+        }
+        if self.is_frozen() {
+            return Err(Error::Frozen(self.name));
+        }
+        self.table.get_or_intern_checked(s)
+    }
+
+    /// Look `s` up without inserting it.
+    pub fn get(&self, s: &str) -> Option<Istr> {
+        self.table.get(s)
+    }
+
+    /// Intern every string of `strings`, e.g. before freezing the pool.
+    ///
+    /// # Panics
+    ///
+    /// If the pool is frozen.
+    pub fn prefill<S: AsRef<str>>(&self, strings: impl IntoIterator<Item = S>) {
+        assert!(!self.is_frozen(), "{}", Error::Frozen(self.name));
+        self.table.bulk_load_unique(strings);
+    }
+
+    /// Stop interning new strings into the pool. Interns that already found the pool
+    /// unfrozen may still complete.
+    pub fn freeze(&self) {
+        self.frozen.store(true, Ordering::Release);
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::Acquire)
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let (strings, bytes) = self
+            .table
+            .iter()
+            .fold((0, 0), |(n, bytes), s| (n + 1, bytes + s.len()));
+        PoolStats {
+            name: self.name,
+            strings,
+            bytes,
+            frozen: self.is_frozen(),
+        }
+    }
+}
+
+/// Interning a string missing from a frozen pool panics.
+impl InternerBackend for Pool {
+    fn get_or_intern(&self, s: &str) -> Istr {
+        Pool::get_or_intern(self, s)
+    }
+
+    fn get(&self, s: &str) -> Option<Istr> {
+        Pool::get(self, s)
+    }
+
+    fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Istr> + '_> {
+        Box::new(self.table.iter())
+    }
+}
+
+/// What a pool holds, from [`Pool::stats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolStats {
+    pub name: &'static str,
+    /// The number of non-empty strings in the pool.
+    pub strings: usize,
+    /// Their total length.
+    pub bytes: usize,
+    pub frozen: bool,
+}
+
+impl fmt::Display for PoolStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} strings, {} bytes{}",
+            self.name,
+            self.strings,
+            self.bytes,
+            if self.frozen { ", frozen" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pools() {
+        let a = Pool::named("test-a");
+        let b = Pool::named("test-b");
+        assert!(std::ptr::eq(a, Pool::named("test-a")));
+        let x = a.get_or_intern("shared");
+        assert_ne!(x, b.get_or_intern("shared"));
+        assert_eq!(a.get_or_intern("shared"), x);
+        b.prefill(["one", "two"]);
+        b.freeze();
+        assert_eq!(b.get("one"), Some(b.get_or_intern("one")));
+        assert!(matches!(
+            b.try_get_or_intern("three"),
+            Err(Error::Frozen("test-b"))
+        ));
+        assert_eq!(b.try_get_or_intern("").unwrap(), "");
+        assert_eq!(b.stats().to_string(), "test-b: 3 strings, 12 bytes, frozen");
+        assert_eq!(a.stats().strings, 1);
+        let names: Vec<&str> = Pool::all().iter().map(|pool| pool.name()).collect();
+        assert!(names.windows(2).all(|w| w[0] < w[1]));
+        assert!(names.contains(&"test-a") && names.contains(&"test-b"));
+    }
+}
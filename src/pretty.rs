@@ -0,0 +1,199 @@
+//! Debug printing of trees with interned leaves, e.g. ASTs.
+//!
+//! Implement [`Tree`] for your node types and print them with [`pretty`], or configure a
+//! [`Printer`]. The default style resolves every interned symbol to its string; the compact
+//! style prints the whole tree on one line and shows symbols by their handle id only, which
+//! keeps dumps of large trees short and makes identical symbols easy to spot.
+//!
+//! ```
+//! use lsi::pretty::{pretty, Printer, Tree};
+//! use lsi::{InternTable, Istr};
+//!
+//! enum Expr {
+//!     Var(Istr),
+//!     Call(Istr, Vec<Expr>),
+//! }
+//!
+//! impl Tree for Expr {
+//!     fn kind(&self) -> &str {
+//!         match self {
+//!             Expr::Var(_) => "Var",
+//!             Expr::Call(..) => "Call",
+//!         }
+//!     }
+//!
+//!     fn symbol(&self) -> Option<Istr> {
+//!         match self {
+//!             Expr::Var(s) | Expr::Call(s, _) => Some(*s),
+//!         }
+//!     }
+//!
+//!     fn children(&self) -> Vec<&dyn Tree> {
+//!         match self {
+//!             Expr::Var(_) => Vec::new(),
+//!             Expr::Call(_, args) => args.iter().map(|a| a as &dyn Tree).collect(),
+//!         }
+//!     }
+//! }
+//!
+//! let table = InternTable::new();
+//! let expr = Expr::Call(table.get_or_intern("f"), vec![Expr::Var(table.get_or_intern("x"))]);
+//! assert_eq!(pretty(&expr), "Call \"f\"\n  Var \"x\"\n");
+//! ```
+use std::fmt::{self, Write};
+
+use crate::Istr;
+
+/// A node of a tree that can be printed by a [`Printer`].
+pub trait Tree {
+    /// The kind of node, printed verbatim.
+    fn kind(&self) -> &str;
+
+    /// The interned symbol carried by this node, if any.
+    fn symbol(&self) -> Option<Istr> {
+        None
+    }
+
+    fn children(&self) -> Vec<&dyn Tree> {
+        Vec::new()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Style {
+    /// One node per line, indented by depth, with symbols resolved to quoted strings.
+    #[default]
+    Resolved,
+    /// The whole tree on one line as an s-expression, with symbols shown by id.
+    Compact,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Printer {
+    style: Style,
+    indent: usize,
+}
+
+impl Printer {
+    pub const fn new() -> Self {
+        Printer {
+            style: Style::Resolved,
+            indent: 2,
+        }
+    }
+
+    pub const fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// The number of spaces per level in the [`Style::Resolved`] style.
+    pub const fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    pub fn print(&self, tree: &dyn Tree) -> String {
+        let mut out = String::new();
+        self.write(&mut out, tree).expect("writing to a String can't fail");
+        out
+    }
+
+    pub fn write(&self, w: &mut dyn Write, tree: &dyn Tree) -> fmt::Result {
+        match self.style {
+            Style::Resolved => self.write_resolved(w, tree, 0),
+            Style::Compact => self.write_compact(w, tree),
+        }
+    }
+
+    fn write_resolved(&self, w: &mut dyn Write, tree: &dyn Tree, depth: usize) -> fmt::Result {
+        write!(w, "{:1$}{2}", "", depth * self.indent, tree.kind())?;
+        if let Some(s) = tree.symbol() {
+            write!(w, " {:?}", s.as_str())?;
+        }
+        w.write_char('\n')?;
+        for child in tree.children() {
+            self.write_resolved(w, child, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn write_compact(&self, w: &mut dyn Write, tree: &dyn Tree) -> fmt::Result {
+        let children = tree.children();
+        let parens = !children.is_empty();
+        if parens {
+            w.write_char('(')?;
+        }
+        w.write_str(tree.kind())?;
+        if let Some(s) = tree.symbol() {
+            write!(w, " #{:x}", symbol_id(s))?;
+        }
+        for child in children {
+            w.write_char(' ')?;
+            self.write_compact(w, child)?;
+        }
+        if parens {
+            w.write_char(')')?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Print `tree` in the default style.
+pub fn pretty(tree: &dyn Tree) -> String {
+    Printer::new().print(tree)
+}
+
+/// The id printed for a symbol in the compact style. Equal symbols have equal ids.
+pub fn symbol_id(s: Istr) -> usize {
+    s.0.as_ptr() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Node(&'static str, Option<Istr>, Vec<Node>);
+
+    impl Tree for Node {
+        fn kind(&self) -> &str {
+            self.0
+        }
+
+        fn symbol(&self) -> Option<Istr> {
+            self.1
+        }
+
+        fn children(&self) -> Vec<&dyn Tree> {
+            self.2.iter().map(|n| n as &dyn Tree).collect()
+        }
+    }
+
+    #[test]
+    fn test_compact() {
+        let x = crate::InternTable::new().get_or_intern("x");
+        let tree = Node(
+            "Add",
+            None,
+            vec![
+                Node("Var", Some(x), vec![]),
+                Node("Var", Some(x), vec![]),
+            ],
+        );
+        let id = symbol_id(x);
+        assert_eq!(
+            Printer::new().style(Style::Compact).print(&tree),
+            format!("(Add Var #{:x} Var #{:x})", id, id)
+        );
+        assert_eq!(
+            Printer::new().indent(1).print(&tree),
+            "Add\n Var \"x\"\n Var \"x\"\n"
+        );
+    }
+}
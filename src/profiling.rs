@@ -0,0 +1,141 @@
+//! Reporting interning misses to a profiler.
+//!
+//! A miss allocates an entry and grows the table, so it costs far more than a hit. With the
+//! `profiling` feature, every miss is reported to the hook installed with [`set_miss_hook`]
+//! once it is done, with its length, shard and duration.
+//!
+//! The hook runs after the miss, so it can't open a span around it. To separate miss time from
+//! hit time in flamegraphs, enable the `tracing` feature as well: it wraps each miss in an
+//! `intern_miss` span with the length and shard as fields, which a `tracing` subscriber such
+//! as `tracing-flame` turns into flamegraph frames. The hook is for what spans don't give
+//! cheaply, like tallying misses per shard or logging the slow ones:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use lsi::profiling::{set_miss_hook, Miss};
+//!
+//! fn report(miss: &Miss) {
+//!     if miss.elapsed > Duration::from_millis(1) {
+//!         eprintln!("slow miss of {} bytes in shard {}", miss.len, miss.shard);
+//!     }
+//!     assert!(miss.len > 0);
+//! }
+//!
+//! set_miss_hook(Some(report));
+//! lsi::InternTable::new().get_or_intern("cold");
+//! set_miss_hook(None);
+//! ```
+//!
+//! The hook runs after the table lock is released, so it may intern, but strings it interns
+//! for the first time are reported to it in turn.
+//!
+//! Each shard of a table also counts the hits and misses of
+//! [`InternTable::get_or_intern`] and its variants, reported by [`InternTable::stats`]. Hits
+//! then write a counter other threads hitting the same shard write too, so measure before
+//! enabling the feature in production.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::{InternTable, Istr};
+
+/// A function receiving every [`Miss`].
+pub type MissHook = fn(&Miss);
+
+static HOOK: RwLock<Option<MissHook>> = RwLock::new(None);
+
+/// An interning call that inserted a new string.
+#[derive(Copy, Clone, Debug)]
+pub struct Miss<'a> {
+    /// The table the string was inserted into.
+    pub table: &'a InternTable,
+    pub string: Istr,
+    /// The length of the string in bytes.
+    pub len: usize,
+    /// The shard of the table the string was inserted into.
+    pub shard: usize,
+    /// When the interning call started.
+    pub start: Instant,
+    /// How long the interning call took, including waiting for the table lock.
+    pub elapsed: Duration,
+}
+
+/// Install `hook` to receive every miss, or remove the current one with `None`.
+pub fn set_miss_hook(hook: Option<MissHook>) {
+    *HOOK.write().unwrap() = hook;
+}
+
+/// The hits and misses of one shard.
+#[derive(Debug)]
+pub(crate) struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) const fn new() -> Self {
+        Counters {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a call that found the string, or if `inserted`, one that inserted it.
+    pub(crate) fn count(&self, inserted: bool) {
+        let counter = if inserted { &self.misses } else { &self.hits };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The hits and misses so far.
+    pub(crate) fn get(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Report a miss of a call that started at `start`. Called without the table lock held.
+pub(crate) fn missed(table: &InternTable, shard: usize, string: Istr, start: Instant) {
+    let hook = *HOOK.read().unwrap();
+    if let Some(hook) = hook {
+        hook(&Miss {
+            table,
+            string,
+            len: string.len(),
+            shard,
+            start,
+            elapsed: start.elapsed(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static REPORTED: AtomicUsize = AtomicUsize::new(0);
+
+    #[test]
+    fn test_miss_hook() {
+        fn hook(miss: &Miss) {
+            if miss.string.as_str().starts_with("profiled-") {
+                assert_eq!(miss.len, 10);
+                assert!(miss.shard < crate::SHARDS);
+                // The table lock is released, so the hook may intern.
+                miss.table.get_or_intern("profiled-0");
+                REPORTED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let table = InternTable::new();
+        set_miss_hook(Some(hook));
+        table.get_or_intern("profiled-0");
+        table.get_or_intern("profiled-0");
+        table.try_get_or_intern("profiled-1").unwrap();
+        set_miss_hook(None);
+        table.get_or_intern("profiled-2");
+        assert_eq!(REPORTED.load(Ordering::Relaxed), 2);
+    }
+}
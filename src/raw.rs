@@ -0,0 +1,143 @@
+//! A layout-stable representation of handles, for embedding in C-compatible structs.
+//!
+//! [`Istr`] is a Rust type whose layout is an implementation detail. Plugin ecosystems that
+//! pass handles through their own `#[repr(C)]` structs, possibly built against different
+//! versions of this crate, can store a [`RawIstr`] instead, whose meaning is fixed by
+//! [`ABI_VERSION`]:
+//!
+//! - The raw value `0` is the empty string.
+//! - Any other value is the address of the string's UTF-8 bytes, which are preceded by their
+//!   length as a native-endian `usize`, or a `u32` with the `compact-len` feature, which is
+//!   version 2. The bytes are not nul-terminated.
+//!
+//! That is enough for C code to read a string without calling back into Rust. Turning a raw
+//! handle back into an [`Istr`] additionally requires the handle to come from a copy of the
+//! crate with the same [`table_fingerprint`](crate::table_fingerprint), see [`Istr::from_raw`].
+//!
+//! With the `strict-provenance` feature, [`Istr::into_raw`] exposes the provenance of the
+//! address and the functions reading a raw handle recover it, with the exposed-provenance
+//! APIs, so that Miri can follow handles through their integer form. (Its
+//! `-Zmiri-strict-provenance` mode rejects recovering provenance altogether: test code
+//! converting handles to raw ones can't run under it.)
+//!
+//! The version only changes when the representation above does, not with every release of
+//! the crate. Hosts can pass [`abi_version`] to plugins as a C function pointer, for plugins
+//! to compare with their own [`ABI_VERSION`] before reading any handles.
+use std::mem::size_of;
+
+use crate::{widen, Header, Istr, Len, EMPTY_FAST_STR};
+
+// The length is the last field of the header, which the bytes directly follow.
+const _: () = assert!(std::mem::offset_of!(Header, len) == size_of::<Header>() - size_of::<Len>());
+
+/// The version of the [`RawIstr`] representation described in the module documentation.
+#[cfg(not(feature = "compact-len"))]
+pub const ABI_VERSION: u32 = 1;
+/// The version of the [`RawIstr`] representation described in the module documentation.
+#[cfg(feature = "compact-len")]
+pub const ABI_VERSION: u32 = 2;
+
+/// [`ABI_VERSION`], as a C function.
+pub extern "C" fn abi_version() -> u32 {
+    ABI_VERSION
+}
+
+/// A handle as a plain integer, see the module documentation.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "abi_stable", derive(abi_stable::StableAbi))]
+pub struct RawIstr(pub usize);
+
+impl RawIstr {
+    /// The raw handle of the empty string.
+    pub const EMPTY: RawIstr = RawIstr(0);
+
+    pub fn is_empty(self) -> bool {
+        self == RawIstr::EMPTY
+    }
+
+    /// The length of the string, read from the representation.
+    ///
+    /// # Safety
+    ///
+    /// `self` must be [`RawIstr::EMPTY`] or have been returned by [`Istr::into_raw`], possibly in
+    /// another copy of the crate with the same [`ABI_VERSION`].
+    pub unsafe fn len(self) -> usize {
+        if self == RawIstr::EMPTY {
+            return 0;
+        }
+        widen((self.as_ptr().sub(size_of::<Len>()) as *const Len).read_unaligned())
+    }
+
+    /// The string, read from the representation.
+    ///
+    /// # Safety
+    ///
+    /// See [`RawIstr::len`].
+    pub unsafe fn as_str(self) -> &'static str {
+        if self == RawIstr::EMPTY {
+            return "";
+        }
+        let bytes = std::slice::from_raw_parts(self.as_ptr(), self.len());
+        std::str::from_utf8_unchecked(bytes)
+    }
+
+    /// The address of the string's bytes.
+    fn as_ptr(self) -> *const u8 {
+        #[cfg(feature = "strict-provenance")]
+        return std::ptr::with_exposed_provenance(self.0);
+        #[cfg(not(feature = "strict-provenance"))]
+        return self.0 as *const u8;
+    }
+}
+
+impl Istr {
+    pub fn into_raw(self) -> RawIstr {
+        if self.is_empty() {
+            return RawIstr::EMPTY;
+        }
+        #[cfg(feature = "strict-provenance")]
+        return RawIstr(self.data_ptr().expose_provenance());
+        #[cfg(not(feature = "strict-provenance"))]
+        return RawIstr(self.data_ptr() as usize);
+    }
+
+    /// Recover a handle from [`Istr::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be [`RawIstr::EMPTY`] or have been returned by [`Istr::into_raw`] in a copy of
+    /// the crate with the same [`table_fingerprint`](crate::table_fingerprint) as this one.
+    pub unsafe fn from_raw(raw: RawIstr) -> Istr {
+        if raw == RawIstr::EMPTY {
+            return EMPTY_FAST_STR;
+        }
+        Istr::from_data_ptr(raw.as_ptr())
+    }
+}
+
+impl From<Istr> for RawIstr {
+    fn from(s: Istr) -> RawIstr {
+        s.into_raw()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InternTable;
+
+    #[test]
+    fn test_raw_round_trip() {
+        let table = InternTable::new();
+        let s = table.get_or_intern("plugin");
+        let raw = s.into_raw();
+        assert_eq!(unsafe { raw.len() }, 6);
+        assert_eq!(unsafe { raw.as_str() }, "plugin");
+        assert_eq!(unsafe { Istr::from_raw(raw) }, s);
+        let empty = table.get_or_intern("");
+        assert!(empty.into_raw().is_empty());
+        assert_eq!(unsafe { Istr::from_raw(RawIstr::EMPTY) }, empty);
+        assert_eq!(unsafe { RawIstr::EMPTY.as_str() }, "");
+    }
+}
@@ -0,0 +1,344 @@
+//! Reference-counted strings that can be freed, for servers interning user input.
+//!
+//! Every other handle of the crate is a leaked [`Istr`], so a long-running server interning
+//! user-provided strings grows without bound. With the `refcount` feature each entry carries
+//! a count of its handles, and [`RcIstr`]s, kept in a table of their own, count themselves.
+//! [`collect_unused`] frees the strings whose count has dropped to zero: call it every so often,
+//! e.g. from a timer.
+//!
+//! ```
+//! use lsi::rc::{collect_unused, RcIstr};
+//!
+//! let session = RcIstr::new("doc-session-42");
+//! assert_eq!(session, RcIstr::new("doc-session-42"));
+//! drop(session);
+//! assert!(collect_unused() >= 1);
+//! ```
+//!
+//! An `RcIstr` never hands out an [`Istr`], whose string would be `&'static`, so the strings of
+//! this table can't be promoted to the hot tier, flagged or given a cached UTF-16 encoding.
+//! Dropping the last handle only decrements the count: a string is freed by the next collection,
+//! and is found again by [`RcIstr::new`] until then.
+//!
+//! With the `lru` feature the table can also be bounded, for caching layers using it as a cache
+//! of hot keys rather than a permanent symbol table: see [`set_capacity`].
+use std::fmt;
+use std::ops::Deref;
+use std::ptr::slice_from_raw_parts_mut;
+#[cfg(feature = "lru")]
+use std::sync::atomic::{AtomicU64, AtomicUsize};
+use std::sync::atomic::Ordering;
+
+use hashbrown::HashTable;
+
+use crate::lock::RwLock;
+use crate::{check_len, Error, InternTable, InternedData, Istr, EMPTY_FAST_STR, SHARDS};
+
+#[allow(clippy::declare_interior_mutable_const)]
+const SHARD: RwLock<HashTable<Entry>> = RwLock::new(HashTable::new());
+
+/// The strings of every [`RcIstr`], alive or awaiting collection, sharded like a table.
+static TABLE: [RwLock<HashTable<Entry>>; SHARDS] = [SHARD; SHARDS];
+
+/// The most strings to keep in each shard, unless they have handles.
+#[cfg(feature = "lru")]
+static SHARD_CAPACITY: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Counts insertions, which is what recency is measured in: by two, so that a string looked up
+/// after an insertion ranks between it and the next one. A lookup only reads it, so hits don't
+/// contend on it.
+#[cfg(feature = "lru")]
+static CLOCK: AtomicU64 = AtomicU64::new(0);
+
+struct Entry {
+    s: Istr,
+    /// The [`CLOCK`] when the string was last interned.
+    #[cfg(feature = "lru")]
+    used: AtomicU64,
+}
+
+impl Entry {
+    /// Whether the entry is `s`, marking it as used if so.
+    fn is(&self, s: &str) -> bool {
+        if self.s.as_str() != s {
+            return false;
+        }
+        #[cfg(feature = "lru")]
+        {
+            let now = CLOCK.load(Ordering::Relaxed) + 1;
+            // Skip the store, and the cache line it dirties, for strings used repeatedly.
+            if self.used.load(Ordering::Relaxed) != now {
+                self.used.store(now, Ordering::Relaxed);
+            }
+        }
+        true
+    }
+
+    fn is_unused(&self) -> bool {
+        self.s.header().refs.load(Ordering::Acquire) == 0
+    }
+}
+
+/// A handle to a reference-counted string.
+///
+/// Cloning and dropping it is an atomic increment or decrement, like an `Arc`. Equality and
+/// hashing are by address, as for [`Istr`].
+#[derive(PartialEq, Eq, Hash)]
+pub struct RcIstr(Istr);
+
+impl RcIstr {
+    /// # Panics
+    ///
+    /// If `s` is longer than [`MAX_LEN`](crate::MAX_LEN).
+    pub fn new(s: &str) -> Self {
+        match Self::try_new(s) {
+            Ok(fast_str) => fast_str,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [`RcIstr::new`], but returns [`Error::TooLong`] instead of panicking.
+    pub fn try_new(s: &str) -> Result<Self, Error> {
+        if s.is_empty() {
+            return Ok(RcIstr(EMPTY_FAST_STR));
+        }
+        check_len(s)?;
+        let hash = InternTable::hash(s);
+        let shard = &TABLE[crate::shard(hash)];
+        // The count is only raised from zero under a lock, which keeps collections out.
+        if let Some(found) = shard.read().find(hash, |e| e.is(s)) {
+            return Ok(RcIstr::acquire(found.s));
+        }
+        let mut entries = shard.write();
+        if let Some(found) = entries.find(hash, |e| e.is(s)) {
+            return Ok(RcIstr::acquire(found.s));
+        }
+        #[cfg(feature = "counting-alloc")]
+        let _scope = crate::counting::Scope::enter();
+        // Acquired before inserting, so that the eviction below keeps it.
+        let fast_str = RcIstr::acquire(InternedData::construct(s));
+        let entry = Entry {
+            s: fast_str.0,
+            #[cfg(feature = "lru")]
+            used: AtomicU64::new(CLOCK.fetch_add(2, Ordering::Relaxed) + 2),
+        };
+        entries.insert_unique(hash, entry, |e| InternTable::hash(e.s.as_str()));
+        #[cfg(feature = "lru")]
+        {
+            let evicted = evict(&mut entries, SHARD_CAPACITY.load(Ordering::Relaxed));
+            drop(entries);
+            #[cfg(feature = "metrics")]
+            crate::metrics::evicted(evicted.len());
+            for s in evicted {
+                // SAFETY: as in `collect_unused`.
+                unsafe { free(s) };
+            }
+        }
+        Ok(fast_str)
+    }
+
+    fn acquire(fast_str: Istr) -> Self {
+        fast_str.header().refs.fetch_add(1, Ordering::Relaxed);
+        RcIstr(fast_str)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The number of handles to this string, counting this one. Zero for the empty string,
+    /// which isn't counted.
+    pub fn ref_count(this: &Self) -> usize {
+        if this.is_empty() {
+            return 0;
+        }
+        this.0.header().refs.load(Ordering::Relaxed)
+    }
+}
+
+impl Clone for RcIstr {
+    fn clone(&self) -> Self {
+        if self.is_empty() {
+            return RcIstr(self.0);
+        }
+        RcIstr::acquire(self.0)
+    }
+}
+
+impl Drop for RcIstr {
+    fn drop(&mut self) {
+        if !self.is_empty() {
+            self.0.header().refs.fetch_sub(1, Ordering::Release);
+        }
+    }
+}
+
+impl Deref for RcIstr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for RcIstr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq<&str> for RcIstr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for RcIstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for RcIstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Free every string without handles, returning how many were freed. Each shard is locked in
+/// turn, blocking [`RcIstr::new`] for strings of that shard meanwhile.
+pub fn collect_unused() -> usize {
+    let mut freed = 0;
+    for shard in &TABLE {
+        let mut unused = Vec::new();
+        shard.write().retain(|e| {
+            let keep = !e.is_unused();
+            if !keep {
+                unused.push(e.s);
+            }
+            keep
+        });
+        freed += unused.len();
+        #[cfg(feature = "counting-alloc")]
+        let _scope = crate::counting::Scope::enter();
+        for s in unused {
+            // SAFETY: the string had no handles left, and new ones can't be made once it is
+            // out of the table.
+            unsafe { free(s) };
+        }
+    }
+    freed
+}
+
+/// The number of strings in the table, including those awaiting collection.
+pub fn len() -> usize {
+    TABLE.iter().map(|shard| shard.read().len()).sum()
+}
+
+/// Bound the table to about `strings` strings, or lift the bound with `None`.
+///
+/// Each shard keeps at most its share of the capacity. Interning a string into a full shard
+/// evicts the least recently interned strings of the shard that have no handles left, freeing
+/// them at once, until the shard is an eighth below its share. Strings with handles are never
+/// evicted, so a shard whose strings are all in use grows beyond its share.
+///
+/// Recency is when a string was last passed to [`RcIstr::new`]: using, cloning or dropping a
+/// handle doesn't count.
+#[cfg(feature = "lru")]
+pub fn set_capacity(strings: Option<usize>) {
+    let per_shard = strings.map_or(usize::MAX, |n| n.div_ceil(SHARDS).max(1));
+    SHARD_CAPACITY.store(per_shard, Ordering::Relaxed);
+}
+
+/// Remove the least recently used strings without handles from a shard over `capacity`,
+/// returning them to be freed once the lock is released.
+#[cfg(feature = "lru")]
+fn evict(entries: &mut HashTable<Entry>, capacity: usize) -> Vec<Istr> {
+    if entries.len() <= capacity {
+        return Vec::new();
+    }
+    let excess = entries.len() - (capacity - capacity / 8);
+    let mut unused: Vec<(u64, Istr)> = entries
+        .iter()
+        .filter(|e| e.is_unused())
+        .map(|e| (e.used.load(Ordering::Relaxed), e.s))
+        .collect();
+    unused.sort_unstable_by_key(|&(used, _)| used);
+    unused.truncate(excess);
+    for &(_, s) in &unused {
+        let hash = InternTable::hash(s.as_str());
+        if let Ok(entry) = entries.find_entry(hash, |e| e.s == s) {
+            entry.remove();
+        }
+    }
+    unused.into_iter().map(|(_, s)| s).collect()
+}
+
+/// Free the allocation behind `s`, which was created by [`InternedData::construct`].
+unsafe fn free(s: Istr) {
+    let ptr = slice_from_raw_parts_mut(s.0.as_ptr() as *mut u8, s.len()) as *mut InternedData;
+    drop(Box::from_raw(ptr));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rc_istr() {
+        let a = RcIstr::new("rc test");
+        let b = a.clone();
+        assert_eq!(RcIstr::ref_count(&a), 2);
+        assert_eq!(RcIstr::new("rc test"), a);
+        assert_eq!(RcIstr::ref_count(&a), 2);
+        drop(a);
+        collect_unused();
+        // Still referenced by `b`.
+        assert_eq!(RcIstr::new("rc test"), b);
+        drop(b);
+        let before = len();
+        collect_unused();
+        assert!(len() < before);
+        let c = RcIstr::new("rc test");
+        assert_eq!(RcIstr::ref_count(&c), 1);
+        assert_eq!(c, "rc test");
+        let empty = RcIstr::new("");
+        assert_eq!(RcIstr::ref_count(&empty.clone()), 0);
+    }
+
+    #[cfg(feature = "lru")]
+    #[test]
+    fn test_evict() {
+        // A shard of its own, from the least to the most recently used.
+        let strings = ["oldest", "pinned", "older", "recent"].map(InternedData::construct);
+        let mut entries = HashTable::new();
+        for (used, &s) in strings.iter().enumerate() {
+            let entry = Entry {
+                s,
+                used: AtomicU64::new(used as u64),
+            };
+            entries.insert_unique(InternTable::hash(s.as_str()), entry, |e| {
+                InternTable::hash(e.s.as_str())
+            });
+        }
+        let pinned = RcIstr::acquire(strings[1]);
+        assert!(evict(&mut entries, 4).is_empty());
+        // Down to the capacity less an eighth, skipping the string with a handle.
+        let evicted = evict(&mut entries, 2);
+        assert_eq!(evicted, [strings[0], strings[2]]);
+        assert_eq!(entries.len(), 2);
+        drop(pinned);
+        for s in evicted.into_iter().chain(entries.drain().map(|e| e.s)) {
+            // SAFETY: the strings are in no table, and have no handles left.
+            unsafe { free(s) };
+        }
+    }
+}
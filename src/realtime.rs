@@ -0,0 +1,75 @@
+//! The subset of the handle API that is safe to call from code audited for real-time
+//! constraints: it never panics, allocates, locks or blocks.
+//!
+//! Each function is a thin wrapper over the [`Istr`] method of the same meaning and compiles
+//! to the same code; calling it through this module keeps the audited surface explicit. With
+//! the `no-panic` feature, optimized builds fail to link if any of these functions has a
+//! reachable panic, and `tests/realtime.rs` checks that none of them allocates:
+//!
+//! ```text
+//! cargo test --release --features no-panic,flags --test realtime
+//! ```
+//!
+//! Interning is not in the subset: a miss allocates and takes a shard lock. Intern ahead of
+//! time, outside the real-time section, and pass the handles in.
+#[cfg(feature = "flags")]
+use crate::flags::Flag;
+use crate::{Istr, RawIstr};
+
+#[cfg(all(feature = "no-panic", not(debug_assertions)))]
+use no_panic::no_panic;
+
+/// The string of `s`, see [`Istr::as_str`].
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic)]
+pub fn resolve(s: Istr) -> &'static str {
+    s.as_str()
+}
+
+/// The length of `s` in bytes, see [`Istr::len`].
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic)]
+pub fn len(s: Istr) -> usize {
+    s.len()
+}
+
+/// Whether `a` and `b` are the same string, comparing handles. Handles from different tables
+/// are never equal.
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic)]
+pub fn ptr_eq(a: Istr, b: Istr) -> bool {
+    a == b
+}
+
+/// A hash of the handle's identity, the one [`IstrMap`](crate::IstrMap) uses.
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic)]
+pub fn identity_hash(s: Istr) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = crate::IstrHasher::default();
+    s.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The handle as a plain integer, see [`Istr::into_raw`].
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic)]
+pub fn to_raw(s: Istr) -> RawIstr {
+    s.into_raw()
+}
+
+/// See [`Istr::get_flag`].
+#[cfg(feature = "flags")]
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic)]
+pub fn get_flag(s: Istr, flag: Flag) -> bool {
+    s.get_flag(flag)
+}
+
+/// See [`Istr::set_flag`].
+#[cfg(feature = "flags")]
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic)]
+pub fn set_flag(s: Istr, flag: Flag) -> bool {
+    s.set_flag(flag)
+}
+
+/// See [`Istr::clear_flag`].
+#[cfg(feature = "flags")]
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic)]
+pub fn clear_flag(s: Istr, flag: Flag) -> bool {
+    s.clear_flag(flag)
+}
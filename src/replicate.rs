@@ -0,0 +1,265 @@
+//! Streaming a table's id assignments to other processes.
+//!
+//! A [`Publisher`] numbers the strings interned through it and sends each new `(id, string)`
+//! pair as an [`Update`] to its subscribers. A [`Subscriber`], typically in another process
+//! fed by [`Update::write_to`] and [`Update::read_from`] over a socket, applies the updates to
+//! its own table and resolves ids back to handles. Processes can then exchange compact `u32`
+//! ids instead of strings.
+//!
+//! ```
+//! use lsi::replicate::{Publisher, Subscriber};
+//! use lsi::InternTable;
+//!
+//! let (ours, theirs) = (InternTable::new(), InternTable::new());
+//! let publisher = Publisher::new(&ours);
+//! let updates = publisher.subscribe();
+//! let (_, id) = publisher.intern("order.created");
+//!
+//! let mut subscriber = Subscriber::new(&theirs);
+//! for update in updates.try_iter() {
+//!     subscriber.apply(update.id, update.string.as_str()).unwrap();
+//! }
+//! assert_eq!(subscriber.resolve(id).unwrap(), "order.created");
+//! ```
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+use crate::{InternTable, Istr, IstrMap};
+
+/// A newly numbered string.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Update {
+    pub id: u32,
+    pub string: Istr,
+}
+
+impl Update {
+    /// Write this update as a little-endian `u32` id and byte length followed by the string.
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(&self.id.to_le_bytes())?;
+        w.write_all(&(self.string.len() as u32).to_le_bytes())?;
+        w.write_all(self.string.as_str().as_bytes())
+    }
+
+    /// Read an update written by [`Update::write_to`], interning its string into `table`.
+    /// Returns `None` at the end of the stream.
+    pub fn read_from(table: &InternTable, r: &mut impl Read) -> io::Result<Option<Update>> {
+        let mut header = [0; 8];
+        let mut filled = 0;
+        while filled < header.len() {
+            match r.read(&mut header[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let id = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let len = u32::from_le_bytes(header[4..].try_into().unwrap());
+        let mut buf = vec![0; len as usize];
+        r.read_exact(&mut buf)?;
+        let s =
+            std::str::from_utf8(&buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Some(Update {
+            id,
+            string: table.get_or_intern(s),
+        }))
+    }
+}
+
+/// Numbers strings in the order they are first interned through it, and publishes each
+/// numbering to its subscribers.
+#[derive(Debug)]
+pub struct Publisher<'t> {
+    table: &'t InternTable,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    ids: IstrMap<u32>,
+    strings: Vec<Istr>,
+    subscribers: Vec<Sender<Update>>,
+}
+
+impl<'t> Publisher<'t> {
+    pub fn new(table: &'t InternTable) -> Self {
+        Publisher {
+            table,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Intern `s` and return it with its id, publishing the id if it is new.
+    pub fn intern(&self, s: &str) -> (Istr, u32) {
+        let istr = self.table.get_or_intern(s);
+        (istr, self.id(istr))
+    }
+
+    /// The id of `s`, assigning and publishing one if it has none yet.
+    pub fn id(&self, s: Istr) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        if let Some(&id) = state.ids.get(&s) {
+            return id;
+        }
+        let id = u32::try_from(state.strings.len()).expect("more than u32::MAX ids");
+        state.ids.insert(s, id);
+        state.strings.push(s);
+        let update = Update { id, string: s };
+        // Subscribers that hung up are dropped.
+        state.subscribers.retain(|tx| tx.send(update).is_ok());
+        id
+    }
+
+    /// The string numbered `id`, if it has been published.
+    pub fn resolve(&self, id: u32) -> Option<Istr> {
+        self.state.lock().unwrap().strings.get(id as usize).copied()
+    }
+
+    /// A channel receiving every update, starting with those published before subscribing, so
+    /// late subscribers converge on the same mapping.
+    pub fn subscribe(&self) -> Receiver<Update> {
+        let (tx, rx) = mpsc::channel();
+        let mut state = self.state.lock().unwrap();
+        for (id, &string) in state.strings.iter().enumerate() {
+            // The receiver is still in hand, so this can't fail.
+            let _ = tx.send(Update {
+                id: id as u32,
+                string,
+            });
+        }
+        state.subscribers.push(tx);
+        rx
+    }
+
+    /// The number of ids assigned so far.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The receiving side of a [`Publisher`], resolving its ids into a local table.
+#[derive(Debug)]
+pub struct Subscriber<'t> {
+    table: &'t InternTable,
+    strings: Vec<Istr>,
+}
+
+impl<'t> Subscriber<'t> {
+    pub fn new(table: &'t InternTable) -> Self {
+        Subscriber {
+            table,
+            strings: Vec::new(),
+        }
+    }
+
+    /// Apply the update numbering `s` as `id`. Updates must arrive in the order they were
+    /// published; re-applying one that was already applied is a no-op.
+    pub fn apply(&mut self, id: u32, s: &str) -> Result<Istr, ReplicationError> {
+        let expected = self.strings.len() as u32;
+        if let Some(&existing) = self.strings.get(id as usize) {
+            if existing.as_str() != s {
+                return Err(ReplicationError::Conflict { id });
+            }
+            return Ok(existing);
+        }
+        if id != expected {
+            return Err(ReplicationError::OutOfOrder { expected, got: id });
+        }
+        let istr = self.table.get_or_intern(s);
+        self.strings.push(istr);
+        Ok(istr)
+    }
+
+    pub fn resolve(&self, id: u32) -> Option<Istr> {
+        self.strings.get(id as usize).copied()
+    }
+
+    /// The number of ids received so far.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+/// Returned by [`Subscriber::apply`] when an update doesn't follow the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicationError {
+    /// Updates were skipped: `got` arrived while `expected` was the next id.
+    OutOfOrder { expected: u32, got: u32 },
+    /// The id was already applied with a different string.
+    Conflict { id: u32 },
+}
+
+impl fmt::Display for ReplicationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplicationError::OutOfOrder { expected, got } => {
+                write!(f, "expected update {}, got {}", expected, got)
+            }
+            ReplicationError::Conflict { id } => {
+                write!(f, "id {} was already assigned to another string", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplicationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replicate_over_stream() {
+        let (ours, theirs) = (InternTable::new(), InternTable::new());
+        let publisher = Publisher::new(&ours);
+        publisher.intern("a");
+        let updates = publisher.subscribe();
+        assert_eq!(publisher.intern("b").1, 1);
+        assert_eq!(publisher.intern("a").1, 0);
+        assert_eq!(publisher.len(), 2);
+
+        let mut wire = Vec::new();
+        for update in updates.try_iter() {
+            update.write_to(&mut wire).unwrap();
+        }
+        let mut subscriber = Subscriber::new(&theirs);
+        let mut r = &wire[..];
+        while let Some(update) = Update::read_from(&theirs, &mut r).unwrap() {
+            subscriber.apply(update.id, update.string.as_str()).unwrap();
+        }
+        assert_eq!(subscriber.resolve(1).unwrap(), "b");
+        assert_eq!(subscriber.resolve(2), None);
+        assert!(Update::read_from(&theirs, &mut &wire[..5]).is_err());
+    }
+
+    #[test]
+    fn test_apply_errors() {
+        let table = InternTable::new();
+        let mut subscriber = Subscriber::new(&table);
+        subscriber.apply(0, "x").unwrap();
+        subscriber.apply(0, "x").unwrap();
+        assert_eq!(
+            subscriber.apply(2, "z"),
+            Err(ReplicationError::OutOfOrder {
+                expected: 1,
+                got: 2
+            })
+        );
+        assert_eq!(
+            subscriber.apply(0, "y"),
+            Err(ReplicationError::Conflict { id: 0 })
+        );
+    }
+}
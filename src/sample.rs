@@ -0,0 +1,124 @@
+//! Interning only a sample of new strings, to bound the growth of the table while rolling
+//! interning into a pipeline whose cardinality is unknown.
+//!
+//! A [`Sampler`] returns strings that are already interned as they are, interns one in
+//! `rate` of the distinct strings it hasn't seen, and passes the rest through borrowed. The
+//! decision is a function of the string, so a string that was passed through once is passed
+//! through every time, and the [`SamplerStats`] show how much of the input would be interned
+//! at full rate.
+//!
+//! ```
+//! use lsi::sample::Sampler;
+//! use lsi::InternTable;
+//!
+//! let table = InternTable::new();
+//! let sampler = Sampler::new(&table, 100);
+//! for i in 0..10_000 {
+//!     sampler.intern(&format!("request-{}", i));
+//! }
+//! let stats = sampler.stats();
+//! assert_eq!(stats.passed + stats.interned, 10_000);
+//! assert!(table.len() < 200);
+//! ```
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{BuildHasher, BuildHasherDefault};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{InternTable, MaybeInterned};
+
+#[derive(Debug)]
+pub struct Sampler<'t> {
+    table: &'t InternTable,
+    rate: u64,
+    hits: AtomicU64,
+    interned: AtomicU64,
+    passed: AtomicU64,
+}
+
+/// Counts of the strings a [`Sampler`] has seen.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SamplerStats {
+    /// Strings that were already interned.
+    pub hits: u64,
+    /// New strings that were sampled and interned.
+    pub interned: u64,
+    /// New strings that were passed through without interning.
+    pub passed: u64,
+}
+
+impl<'t> Sampler<'t> {
+    /// A sampler interning one in `rate` new strings into `table`. A rate of 1 interns all of
+    /// them.
+    ///
+    /// # Panics
+    ///
+    /// If `rate` is 0.
+    pub fn new(table: &'t InternTable, rate: u64) -> Self {
+        assert!(rate > 0, "sampling rate must be at least 1");
+        Sampler {
+            table,
+            rate,
+            hits: AtomicU64::new(0),
+            interned: AtomicU64::new(0),
+            passed: AtomicU64::new(0),
+        }
+    }
+
+    pub fn intern<'a>(&self, s: &'a str) -> MaybeInterned<'a> {
+        if let Some(fast_str) = self.table.get(s) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return MaybeInterned::Interned(fast_str);
+        }
+        // Hashed with fixed keys, so the decision for a string is the same in every run.
+        let hash = BuildHasherDefault::<DefaultHasher>::default().hash_one(s);
+        if hash % self.rate == 0 {
+            self.interned.fetch_add(1, Ordering::Relaxed);
+            MaybeInterned::Interned(self.table.get_or_intern(s))
+        } else {
+            self.passed.fetch_add(1, Ordering::Relaxed);
+            MaybeInterned::Borrowed(s)
+        }
+    }
+
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    pub fn stats(&self) -> SamplerStats {
+        SamplerStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            interned: self.interned.load(Ordering::Relaxed),
+            passed: self.passed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampler() {
+        let table = InternTable::new();
+        let known = table.get_or_intern("known");
+        let sampler = Sampler::new(&table, 4);
+        assert_eq!(sampler.intern("known").interned(), Some(known));
+        let strings: Vec<String> = (0..1000).map(|i| i.to_string()).collect();
+        let first: Vec<bool> = strings
+            .iter()
+            .map(|s| sampler.intern(s).is_interned())
+            .collect();
+        let again: Vec<bool> = strings
+            .iter()
+            .map(|s| sampler.intern(s).is_interned())
+            .collect();
+        // Sampled strings are hits the second time; passed ones are passed again.
+        assert_eq!(first, again);
+        let stats = sampler.stats();
+        assert_eq!(stats.interned as usize, table.len() - 1);
+        assert_eq!(stats.hits, 1 + stats.interned);
+        assert_eq!(stats.interned + stats.passed / 2, 1000);
+        assert!((150..350).contains(&stats.interned), "{:?}", stats);
+        assert!(Sampler::new(&table, 1).intern("all").is_interned());
+    }
+}
@@ -0,0 +1,182 @@
+//! Validating string-keyed configuration against a preset vocabulary of keys.
+//!
+//! A [`Schema`] is an [`AtomSet`] of allowed keys plus the subset of them that are required.
+//! Validating a map reports every unknown and every missing key at once, so a config loader
+//! can print all problems rather than stopping at the first.
+//!
+//! ```
+//! use lsi::schema::{Diagnostic, Schema};
+//! use lsi::{Istr, IstrMap};
+//!
+//! lsi::literal_set! {
+//!     mod keys = ["host", "port", "timeout"];
+//! }
+//!
+//! let schema = Schema::new(&keys::ATOMS).require("host");
+//! let mut config = IstrMap::default();
+//! config.insert(Istr::new("port"), "8080");
+//! config.insert(Istr::new("prot"), "tcp");
+//! assert_eq!(
+//!     schema.validate(&config),
+//!     [Diagnostic::Unknown(Istr::new("prot")), Diagnostic::Missing(Istr::new("host"))]
+//! );
+//! ```
+use std::fmt;
+
+use crate::atoms::AtomSet;
+use crate::{Error, Istr, IstrMap};
+
+#[derive(Clone, Debug)]
+pub struct Schema {
+    allowed: &'static AtomSet,
+    /// Declaration indices of the required keys, in the order they were required.
+    required: Vec<usize>,
+}
+
+impl Schema {
+    /// A schema allowing the keys of `allowed`, none of them required.
+    pub fn new(allowed: &'static AtomSet) -> Self {
+        Schema {
+            allowed,
+            required: Vec::new(),
+        }
+    }
+
+    /// Require `key` to be present.
+    ///
+    /// # Panics
+    ///
+    /// If `key` isn't one of the allowed keys.
+    pub fn require(self, key: &str) -> Self {
+        match self.try_require(key) {
+            Ok(schema) => schema,
+            Err(e) => panic!("required key {}", e),
+        }
+    }
+
+    /// Like [`Schema::require`], but returns [`Error::UnknownKey`] instead of panicking.
+    pub fn try_require(mut self, key: &str) -> Result<Self, Error> {
+        let index = self
+            .allowed
+            .names()
+            .iter()
+            .position(|&name| name == key)
+            .ok_or_else(|| Error::UnknownKey(key.into()))?;
+        if !self.required.contains(&index) {
+            self.required.push(index);
+        }
+        Ok(self)
+    }
+
+    pub fn allowed(&self) -> &'static AtomSet {
+        self.allowed
+    }
+
+    /// Whether `key` is one of the allowed keys.
+    pub fn allows(&self, key: Istr) -> bool {
+        self.allowed.is_static(key)
+    }
+
+    pub fn validate<V>(&self, map: &IstrMap<V>) -> Vec<Diagnostic> {
+        self.validate_keys(map.keys().copied())
+    }
+
+    /// Validate a set of keys given in any form, e.g. the fields of a parsed document.
+    ///
+    /// Unknown keys are reported first, sorted, followed by missing keys in the order they
+    /// were required. A key given more than once is reported once.
+    pub fn validate_keys(&self, keys: impl IntoIterator<Item = Istr>) -> Vec<Diagnostic> {
+        let mut present = vec![false; self.allowed.len()];
+        let mut unknown = Vec::new();
+        for key in keys {
+            match self.allowed.index_of(key) {
+                Some(i) => present[i] = true,
+                None => unknown.push(key),
+            }
+        }
+        unknown.sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
+        unknown.dedup();
+        let missing = self.required.iter().filter(|&&i| !present[i]);
+        unknown
+            .into_iter()
+            .map(Diagnostic::Unknown)
+            .chain(missing.map(|&i| Diagnostic::Missing(self.allowed.get(i))))
+            .collect()
+    }
+}
+
+/// A problem found by [`Schema::validate`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Diagnostic {
+    /// The key isn't one of the allowed keys.
+    Unknown(Istr),
+    /// The required key is absent.
+    Missing(Istr),
+}
+
+impl Diagnostic {
+    pub fn key(&self) -> Istr {
+        match *self {
+            Diagnostic::Unknown(key) | Diagnostic::Missing(key) => key,
+        }
+    }
+
+    /// The message of this diagnostic, interned, so reporting the same problem for many
+    /// documents stores its text once.
+    pub fn message(&self) -> Istr {
+        Istr::from_display(self)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::Unknown(key) => write!(f, "unknown key {:?}", key.as_str()),
+            Diagnostic::Missing(key) => write!(f, "missing required key {:?}", key.as_str()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static KEYS: AtomSet = AtomSet::new(&["name", "version", "edition", "license"]);
+
+    #[test]
+    fn test_validate_keys() {
+        let schema = Schema::new(&KEYS)
+            .require("version")
+            .require("name")
+            .require("name");
+        assert_eq!(
+            schema.validate_keys(["name", "version", "license"].map(Istr::new)),
+            []
+        );
+        let found = schema.validate_keys(["zeta", "alpha", "zeta", "edition"].map(Istr::new));
+        assert_eq!(
+            found,
+            [
+                Diagnostic::Unknown(Istr::new("alpha")),
+                Diagnostic::Unknown(Istr::new("zeta")),
+                Diagnostic::Missing(Istr::new("version")),
+                Diagnostic::Missing(Istr::new("name")),
+            ]
+        );
+        assert_eq!(found[3].message(), "missing required key \"name\"");
+        assert!(schema.allows(Istr::new("edition")));
+    }
+
+    #[test]
+    #[should_panic(expected = "not an allowed key")]
+    fn test_require_unknown() {
+        Schema::new(&KEYS).require("authors");
+    }
+
+    #[test]
+    fn test_try_require() {
+        let err = Schema::new(&KEYS).try_require("authors").unwrap_err();
+        assert!(matches!(err, Error::UnknownKey(ref key) if &**key == "authors"));
+        assert!(Schema::new(&KEYS).try_require("name").is_ok());
+    }
+}
@@ -0,0 +1,288 @@
+//! Sharing one table between copies of this crate, e.g. a host and the plugins it loads.
+//!
+//! A `cdylib` plugin statically links its own copy of `lsi`, with its own [`GLOBAL_TABLE`], so
+//! a handle interned by the plugin is not equal to the host's handle for the same string. To
+//! share a table, the host passes [`table_handle`] to the plugin through its own plugin API, and
+//! the plugin [`attach`]es to it before interning anything. From then on, [`Istr::new`] and the
+//! other functions working on "the global table" in the plugin use the host's table.
+//!
+//! ```ignore
+//! // In the host:
+//! let init: extern "C" fn(lsi::TableHandle) = load_plugin_symbol("plugin_init");
+//! init(lsi::table_handle());
+//!
+//! // In the plugin:
+//! #[no_mangle]
+//! pub extern "C" fn plugin_init(table: lsi::TableHandle) {
+//!     unsafe { lsi::attach(table) }.expect("failed to share the host's intern table");
+//! }
+//! ```
+//!
+//! Every handle carries the [`table_fingerprint`] of the copy that created it, and attaching
+//! to a table from a copy with a different fingerprint (a different version of this crate, or
+//! different layout-affecting features) fails with [`AttachError::Incompatible`] rather than
+//! producing handles the two copies would interpret differently.
+//!
+//! With the `abi_stable` feature, [`TableHandle`], [`Istr`] and [`RawIstr`] implement
+//! `abi_stable::StableAbi`, so a plugin built with `abi_stable` can take them in its root
+//! module, and `abi_stable` checks their layout when it loads the plugin:
+//!
+//! ```ignore
+//! #[repr(C)]
+//! #[derive(abi_stable::StableAbi)]
+//! #[sabi(kind(Prefix(prefix_ref = PluginRef)))]
+//! pub struct Plugin {
+//!     #[sabi(last_prefix_field)]
+//!     pub init: extern "C" fn(lsi::TableHandle),
+//! }
+//! ```
+//!
+//! The layout check doesn't replace [`attach`]'s: the layout of a handle is a pointer whatever
+//! the crate's version, but what it points to isn't.
+//!
+//! Only [`Istr::new`] and the functions built on it follow the attachment. Using
+//! [`GLOBAL_TABLE`] directly still refers to the plugin's own table, and features that observe
+//! inserts and removals (such as `persist` and `suffix-index`) only see strings interned and
+//! removed by the copy of the crate they run in.
+//!
+//! [`Istr`]: crate::Istr
+//! [`Istr::new`]: crate::Istr::new
+//! [`RawIstr`]: crate::RawIstr
+use std::fmt;
+use std::mem::{align_of, size_of};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::{Header, InternTable, GLOBAL_TABLE};
+
+/// The table attached to with [`attach`], or null to use [`GLOBAL_TABLE`].
+static ATTACHED: AtomicPtr<InternTable> = AtomicPtr::new(ptr::null_mut());
+
+/// The table [`Istr::new`](crate::Istr::new) interns into: [`GLOBAL_TABLE`], unless another
+/// copy's table has been attached.
+pub fn active_table() -> &'static InternTable {
+    let attached = ATTACHED.load(Ordering::Acquire);
+    if attached.is_null() {
+        &GLOBAL_TABLE
+    } else {
+        // SAFETY: `attach` only stores pointers to tables that live for the whole process.
+        unsafe { &*attached }
+    }
+}
+
+/// Identifies the layout of tables and handles in this copy of the crate. Two copies can only
+/// share a table if their fingerprints are equal.
+///
+/// It covers the crate version, the features that change the layout of entries, and the sizes
+/// of the table and entry headers.
+pub const fn table_fingerprint() -> u64 {
+    FINGERPRINT
+}
+
+const FINGERPRINT: u64 = {
+    let parts = [
+        fnv(env!("CARGO_PKG_VERSION").as_bytes()),
+        cfg!(feature = "stable-hash") as u64,
+        cfg!(feature = "stats") as u64,
+        cfg!(feature = "checkpoint") as u64,
+        cfg!(feature = "unicode-ident") as u64,
+        cfg!(feature = "flags") as u64,
+        cfg!(feature = "cached-hash") as u64,
+        cfg!(feature = "hot-tier") as u64,
+        cfg!(feature = "parking_lot") as u64,
+        cfg!(feature = "wide") as u64,
+        cfg!(feature = "refcount") as u64,
+        cfg!(feature = "compact-len") as u64,
+        cfg!(feature = "fxhash") as u64,
+        cfg!(feature = "foldhash") as u64,
+        cfg!(feature = "random-seed") as u64,
+        size_of::<Header>() as u64,
+        align_of::<Header>() as u64,
+        size_of::<InternTable>() as u64,
+    ];
+    let mut h = FNV_OFFSET;
+    let mut i = 0;
+    while i < parts.len() {
+        h = (h ^ parts[i]).wrapping_mul(FNV_PRIME);
+        i += 1;
+    }
+    h
+};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+const fn fnv(bytes: &[u8]) -> u64 {
+    let mut h = FNV_OFFSET;
+    let mut i = 0;
+    while i < bytes.len() {
+        h = (h ^ bytes[i] as u64).wrapping_mul(FNV_PRIME);
+        i += 1;
+    }
+    h
+}
+
+/// An FFI-safe handle to a table, for passing to another copy of this crate.
+///
+/// The fingerprint comes first, so that any version of the crate can read it.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "abi_stable", derive(abi_stable::StableAbi))]
+pub struct TableHandle {
+    fingerprint: u64,
+    #[cfg_attr(feature = "abi_stable", sabi(unsafe_opaque_field))]
+    table: *const InternTable,
+    /// The seed of the table's hasher, see the `hasher` module.
+    #[cfg(feature = "random-seed")]
+    seed: crate::hasher::seeded::Seed,
+}
+
+impl TableHandle {
+    /// The [`table_fingerprint`] of the copy that created the handle.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+}
+
+// SAFETY: the handle only ever points at a `'static`, `Sync` table.
+unsafe impl Send for TableHandle {}
+
+unsafe impl Sync for TableHandle {}
+
+/// A handle to this copy's [`active_table`].
+pub fn table_handle() -> TableHandle {
+    handle_of(active_table())
+}
+
+/// A handle to `table`, which is only meant to be attached to from another copy.
+pub(crate) fn handle_of(table: &'static InternTable) -> TableHandle {
+    TableHandle {
+        fingerprint: FINGERPRINT,
+        table,
+        #[cfg(feature = "random-seed")]
+        seed: crate::hasher::seeded::seed(),
+    }
+}
+
+/// Make this copy of the crate intern into the table behind `handle`.
+///
+/// # Safety
+///
+/// `handle` must come from [`table_handle`] in another copy of this crate, which must stay loaded
+/// for the rest of the process. Both copies must be built by the same compiler: the fingerprint
+/// check catches differing versions and features, but not differing compilers.
+pub unsafe fn attach(handle: TableHandle) -> Result<(), AttachError> {
+    if handle.fingerprint != FINGERPRINT {
+        return Err(AttachError::Incompatible {
+            ours: FINGERPRINT,
+            theirs: handle.fingerprint,
+        });
+    }
+    if ptr::eq(handle.table, &GLOBAL_TABLE) {
+        return Ok(());
+    }
+    let current = ATTACHED.load(Ordering::Acquire);
+    if !current.is_null() && !ptr::eq(current, handle.table) {
+        return Err(AttachError::AlreadyAttached);
+    }
+    if !GLOBAL_TABLE.is_empty() {
+        return Err(AttachError::AlreadyInterning);
+    }
+    #[cfg(feature = "random-seed")]
+    if !crate::hasher::seeded::adopt(handle.seed) {
+        return Err(AttachError::Seeded);
+    }
+    ATTACHED
+        .compare_exchange(
+            ptr::null_mut(),
+            handle.table as *mut InternTable,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        )
+        .map(|_| ())
+        .or_else(|current| {
+            if ptr::eq(current, handle.table) {
+                Ok(())
+            } else {
+                Err(AttachError::AlreadyAttached)
+            }
+        })
+}
+
+/// Returned by [`attach`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachError {
+    /// This copy has already interned strings into its own table, and those handles would not
+    /// match the shared table's.
+    AlreadyInterning,
+    /// This copy is already attached to another table.
+    AlreadyAttached,
+    /// The handle comes from an incompatible copy of this crate.
+    Incompatible { ours: u64, theirs: u64 },
+    /// This copy has already hashed strings, e.g. into a table of its own, with a seed other
+    /// than the shared table's.
+    #[cfg(feature = "random-seed")]
+    Seeded,
+}
+
+impl fmt::Display for AttachError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttachError::AlreadyInterning => {
+                f.write_str("strings were interned before attaching to a shared table")
+            }
+            AttachError::AlreadyAttached => f.write_str("already attached to another table"),
+            #[cfg(feature = "random-seed")]
+            AttachError::Seeded => {
+                f.write_str("strings were hashed with another seed before attaching")
+            }
+            AttachError::Incompatible { ours, theirs } => write!(
+                f,
+                "table fingerprint {:#x} does not match this copy of lsi ({:#x})",
+                theirs, ours
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AttachError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attach_own_table() {
+        assert!(ptr::eq(active_table(), &GLOBAL_TABLE));
+        unsafe { attach(table_handle()) }.unwrap();
+        assert!(ptr::eq(active_table(), &GLOBAL_TABLE));
+        // Other tests intern into the global table, so attaching elsewhere must be refused.
+        crate::Istr::new("interned before attaching");
+        static OTHER: InternTable = InternTable::new();
+        let other = TableHandle {
+            table: &OTHER,
+            ..table_handle()
+        };
+        assert_eq!(unsafe { attach(other) }, Err(AttachError::AlreadyInterning));
+        let newer = TableHandle {
+            fingerprint: FINGERPRINT ^ 1,
+            ..other
+        };
+        assert!(matches!(
+            unsafe { attach(newer) },
+            Err(AttachError::Incompatible { .. })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "abi_stable")]
+    fn test_stable_abi() {
+        use abi_stable::abi_stability::abi_checking::check_layout_compatibility;
+        use abi_stable::StableAbi;
+        use crate::{Istr, RawIstr};
+
+        assert!(check_layout_compatibility(TableHandle::LAYOUT, TableHandle::LAYOUT).is_ok());
+        assert!(check_layout_compatibility(Istr::LAYOUT, Istr::LAYOUT).is_ok());
+        assert!(check_layout_compatibility(RawIstr::LAYOUT, TableHandle::LAYOUT).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::mem::size_of;
+use std::ops::Deref;
+
+use crate::{InternTable, Istr};
+
+/// The longest string a [`SmallIstr`] stores inline: 7 bytes on 64-bit targets.
+pub const INLINE_CAP: usize = size_of::<usize>() - 1;
+
+/// The byte of the handle holding the least significant bits of an address, which are always
+/// zero for an entry, as entries are aligned. An inline string sets its lowest bit.
+#[cfg(target_endian = "little")]
+const TAG: usize = 0;
+#[cfg(target_endian = "big")]
+const TAG: usize = INLINE_CAP;
+
+/// Where the bytes of an inline string start.
+#[cfg(target_endian = "little")]
+const DATA: usize = 1;
+#[cfg(target_endian = "big")]
+const DATA: usize = 0;
+
+/// A pointer-sized string handle that stores strings of up to [`INLINE_CAP`] bytes in itself,
+/// and longer ones as an [`Istr`].
+///
+/// Short strings like `"id"`, `"x"` or `"en"` then never touch a table: creating one copies its
+/// bytes and [`as_str`](SmallIstr::as_str) reads them back, with one branch on a tag bit. The
+/// catch, and the reason `Istr` itself can't do this, is that an inline string lives in the
+/// handle, so `as_str` borrows from `self` instead of returning a `&'static str`.
+///
+/// Every string has exactly one representation, so equality and hashing compare the handle's
+/// bits, as for `Istr`: short strings by their bytes, long ones by entry.
+///
+/// ```
+/// use lsi::{InternTable, SmallIstr};
+///
+/// let table = InternTable::new();
+/// let id = SmallIstr::new_in(&table, "id");
+/// assert!(id.is_inline() && table.is_empty());
+/// let long = SmallIstr::new_in(&table, "identifier");
+/// assert!(!long.is_inline());
+/// assert_eq!([id.as_str(), long.as_str()], ["id", "identifier"]);
+/// ```
+#[derive(Copy, Clone)]
+pub struct SmallIstr(Repr);
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+union Repr {
+    istr: Istr,
+    bytes: [u8; size_of::<usize>()],
+}
+
+impl SmallIstr {
+    /// `s`, inline if it is short enough, otherwise interned into the active table.
+    #[cfg(feature = "global")]
+    pub fn new(s: &str) -> Self {
+        Self::inline(s).unwrap_or_else(|| SmallIstr(Repr { istr: Istr::new(s) }))
+    }
+
+    /// `s`, inline if it is short enough, otherwise interned into `table`.
+    pub fn new_in(table: &InternTable, s: &str) -> Self {
+        Self::inline(s).unwrap_or_else(|| {
+            SmallIstr(Repr {
+                istr: table.get_or_intern(s),
+            })
+        })
+    }
+
+    /// `s` stored inline, if it is at most [`INLINE_CAP`] bytes long.
+    pub fn inline(s: &str) -> Option<Self> {
+        if s.len() > INLINE_CAP {
+            return None;
+        }
+        let mut bytes = [0; size_of::<usize>()];
+        bytes[TAG] = (s.len() as u8) << 1 | 1;
+        bytes[DATA..DATA + s.len()].copy_from_slice(s.as_bytes());
+        Some(SmallIstr(Repr { bytes }))
+    }
+
+    pub fn is_inline(&self) -> bool {
+        self.bytes()[TAG] & 1 != 0
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self.as_istr() {
+            Some(istr) => istr.as_str(),
+            None => {
+                let len = (self.bytes()[TAG] >> 1) as usize;
+                // SAFETY: inline bytes are copied from a `&str` of exactly `len` bytes.
+                unsafe { std::str::from_utf8_unchecked(&self.bytes()[DATA..DATA + len]) }
+            }
+        }
+    }
+
+    /// The interned string, if this one isn't stored inline.
+    pub fn as_istr(&self) -> Option<Istr> {
+        if self.is_inline() {
+            return None;
+        }
+        // SAFETY: handles that aren't inline were created from an `Istr`.
+        Some(unsafe { self.0.istr })
+    }
+
+    /// The string as an [`Istr`], interning it into the active table if it is inline.
+    #[cfg(feature = "global")]
+    pub fn to_istr(&self) -> Istr {
+        self.as_istr().unwrap_or_else(|| Istr::new(self.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_str().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn bytes(&self) -> &[u8; size_of::<usize>()] {
+        // SAFETY: both fields are plain data of the same size, and an entry's address is
+        // never uninitialized.
+        unsafe { &self.0.bytes }
+    }
+}
+
+/// Strings short enough are stored inline, even if `s` is interned.
+impl From<Istr> for SmallIstr {
+    fn from(s: Istr) -> Self {
+        Self::inline(s.as_str()).unwrap_or(SmallIstr(Repr { istr: s }))
+    }
+}
+
+impl PartialEq for SmallIstr {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes() == other.bytes()
+    }
+}
+
+impl Eq for SmallIstr {}
+
+impl PartialEq<str> for SmallIstr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SmallIstr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Hash for SmallIstr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        usize::from_ne_bytes(*self.bytes()).hash(state)
+    }
+}
+
+impl Deref for SmallIstr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for SmallIstr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for SmallIstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for SmallIstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+crate::assert_eq_size!(SmallIstr, usize, Istr);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_istr() {
+        let table = InternTable::new();
+        for s in ["", "x", "en", "1234567", "é🦀"] {
+            let small = SmallIstr::new_in(&table, s);
+            assert!(small.is_inline(), "{:?}", s);
+            assert_eq!(small, s);
+            assert_eq!(small.len(), s.len());
+        }
+        assert!(table.is_empty());
+        let long = SmallIstr::new_in(&table, "12345678");
+        assert!(!long.is_inline());
+        assert_eq!(long.as_istr(), Some(table.get_or_intern("12345678")));
+        assert_eq!(long, "12345678");
+        assert_eq!(
+            SmallIstr::new_in(&table, "en"),
+            SmallIstr::inline("en").unwrap()
+        );
+        assert_ne!(
+            SmallIstr::inline("en").unwrap(),
+            SmallIstr::inline("e").unwrap()
+        );
+        assert_eq!(
+            SmallIstr::from(table.get_or_intern("id")),
+            SmallIstr::inline("id").unwrap()
+        );
+        assert_eq!(SmallIstr::from(long.as_istr().unwrap()), long);
+        let set: std::collections::HashSet<SmallIstr> = [long, SmallIstr::inline("x").unwrap()]
+            .into_iter()
+            .collect();
+        assert!(set.contains(&SmallIstr::new_in(&table, "x")));
+    }
+}
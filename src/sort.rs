@@ -0,0 +1,118 @@
+//! Sorting handles by their strings.
+//!
+//! [`sort`] orders handles like `sort_by(|a, b| a.as_str().cmp(b.as_str()))`, but first
+//! compares an integer key holding each string's first eight bytes, so most comparisons never
+//! touch the strings themselves. Only ties, i.e. strings sharing a prefix, fall back to
+//! comparing the strings, and identical handles compare equal without reading either.
+//!
+//! [`sort_by_cached_collation`] sorts by the key a [`Collator`] derives from each string,
+//! computing it once per distinct handle rather than once per element or comparison.
+use std::cmp::Ordering;
+
+use crate::{Istr, IstrMap};
+
+/// Derives sort keys from strings, e.g. for locale-aware or case-insensitive ordering.
+///
+/// Implemented for closures from `&str` to an `Ord` key.
+pub trait Collator {
+    type Key: Ord;
+
+    fn sort_key(&self, s: &str) -> Self::Key;
+}
+
+impl<K: Ord, F: Fn(&str) -> K> Collator for F {
+    type Key = K;
+
+    fn sort_key(&self, s: &str) -> K {
+        self(s)
+    }
+}
+
+/// Sort `v` by the strings' bytes. The sort is stable.
+pub fn sort(v: &mut [Istr]) {
+    let mut keyed: Vec<(u64, Istr)> = v.iter().map(|&s| (prefix(s.as_str()), s)).collect();
+    keyed.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| compare(a.1, b.1)));
+    for (slot, (_, s)) in v.iter_mut().zip(keyed) {
+        *slot = s;
+    }
+}
+
+/// Sort `v` by `collator`'s key for each string, computed once per distinct handle. The sort
+/// is stable.
+pub fn sort_by_cached_collation<C: Collator + ?Sized>(v: &mut [Istr], collator: &C) {
+    let mut keys: IstrMap<usize> = IstrMap::default();
+    let mut distinct: Vec<C::Key> = Vec::new();
+    let mut indices: Vec<(usize, Istr)> = v
+        .iter()
+        .map(|&s| {
+            let i = *keys.entry(s).or_insert_with(|| {
+                distinct.push(collator.sort_key(s.as_str()));
+                distinct.len() - 1
+            });
+            (i, s)
+        })
+        .collect();
+    indices.sort_by(|a, b| distinct[a.0].cmp(&distinct[b.0]));
+    for (slot, (_, s)) in v.iter_mut().zip(indices) {
+        *slot = s;
+    }
+}
+
+/// The first eight bytes of `s`, big-endian and zero-padded, so that comparing prefixes agrees
+/// with comparing strings whenever the prefixes differ.
+fn prefix(s: &str) -> u64 {
+    let mut bytes = [0; 8];
+    let n = s.len().min(8);
+    bytes[..n].copy_from_slice(&s.as_bytes()[..n]);
+    u64::from_be_bytes(bytes)
+}
+
+fn compare(a: Istr, b: Istr) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    a.as_str().cmp(b.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InternTable;
+
+    #[test]
+    fn test_sort() {
+        let table = InternTable::new();
+        let strings = [
+            "b",
+            "a\0",
+            "abcdefghij",
+            "",
+            "a",
+            "abcdefgh",
+            "abcdefghi",
+            "b",
+        ];
+        let mut v: Vec<Istr> = strings.iter().map(|s| table.get_or_intern(s)).collect();
+        sort(&mut v);
+        let mut expected = strings.to_vec();
+        expected.sort();
+        assert_eq!(v.iter().map(|s| s.as_str()).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_sort_by_cached_collation() {
+        let table = InternTable::new();
+        let mut v: Vec<Istr> = ["beta", "Alpha", "alpha", "Beta", "alpha"]
+            .map(|s| table.get_or_intern(s))
+            .to_vec();
+        let calls = std::cell::Cell::new(0);
+        let lowercase = |s: &str| {
+            calls.set(calls.get() + 1);
+            s.to_lowercase()
+        };
+        sort_by_cached_collation(&mut v, &lowercase);
+        let sorted: Vec<&str> = v.iter().map(|s| s.as_str()).collect();
+        assert_eq!(sorted, ["Alpha", "alpha", "alpha", "beta", "Beta"]);
+        assert_eq!(calls.get(), 4);
+    }
+}
@@ -0,0 +1,105 @@
+//! Table growth statistics.
+//!
+//! With the `stats` feature, every entry records a coarse creation time: the number of whole
+//! seconds between the first use of the table and the moment the string was interned. This is
+//! enough to tell whether the table only grows during startup or keeps growing in production.
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::{active_table, Istr};
+
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// A coarse point in time: whole seconds since the table was first used.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Epoch(u32);
+
+impl Epoch {
+    pub fn now() -> Self {
+        let elapsed = START.get_or_init(Instant::now).elapsed().as_secs();
+        Epoch(elapsed.try_into().unwrap_or(u32::MAX))
+    }
+
+    pub const fn from_secs(secs: u32) -> Self {
+        Epoch(secs)
+    }
+
+    pub const fn as_secs(self) -> u32 {
+        self.0
+    }
+
+    /// Time elapsed between this epoch and now.
+    pub fn age(self) -> Duration {
+        Duration::from_secs(Epoch::now().0.saturating_sub(self.0) as u64)
+    }
+}
+
+/// Every string interned at or after `epoch`.
+pub fn entries_since(epoch: Epoch) -> Vec<Istr> {
+    let mut entries = active_table().to_vec();
+    entries.retain(|s| s.created() >= epoch);
+    entries
+}
+
+/// Entry counts bucketed by age. Bucket 0 holds entries less than a second old, and bucket
+/// `i` holds entries between `2^(i-1)` and `2^i` seconds old.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AgeHistogram {
+    buckets: Vec<usize>,
+}
+
+impl AgeHistogram {
+    pub fn buckets(&self) -> &[usize] {
+        &self.buckets
+    }
+
+    /// The exclusive upper bound on the age of entries in `bucket`, or [`Duration::MAX`] past
+    /// the buckets a `u64` of seconds can bound.
+    pub fn upper_bound(bucket: usize) -> Duration {
+        u32::try_from(bucket)
+            .ok()
+            .and_then(|bucket| 1u64.checked_shl(bucket))
+            .map_or(Duration::MAX, Duration::from_secs)
+    }
+
+    pub fn total(&self) -> usize {
+        self.buckets.iter().sum()
+    }
+}
+
+pub fn age_histogram() -> AgeHistogram {
+    let now = Epoch::now();
+    let mut buckets = Vec::new();
+    for s in active_table().to_vec() {
+        let age = now.0.saturating_sub(s.created().0);
+        let bucket = (u32::BITS - age.leading_zeros()) as usize;
+        if buckets.len() <= bucket {
+            buckets.resize(bucket + 1, 0);
+        }
+        buckets[bucket] += 1;
+    }
+    AgeHistogram { buckets }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_since() {
+        let epoch = Epoch::now();
+        let s = Istr::new("recorded by stats");
+        assert!(s.created() >= epoch);
+        assert!(entries_since(epoch).contains(&s));
+        assert!(age_histogram().total() >= 1);
+        assert_eq!(Istr::new("").created(), Epoch::from_secs(0));
+    }
+
+    #[test]
+    fn test_upper_bound() {
+        assert_eq!(AgeHistogram::upper_bound(0), Duration::from_secs(1));
+        assert_eq!(AgeHistogram::upper_bound(63), Duration::from_secs(1 << 63));
+        assert_eq!(AgeHistogram::upper_bound(64), Duration::MAX);
+        assert_eq!(AgeHistogram::upper_bound(usize::MAX), Duration::MAX);
+    }
+}
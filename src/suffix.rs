@@ -0,0 +1,118 @@
+//! A secondary index over the active table for suffix queries.
+//!
+//! With the `suffix-index` feature, every string interned into the
+//! [active table](crate::active_table) is also inserted into an ordered index keyed by its
+//! reversed bytes, so all strings ending with a given suffix are a contiguous range of the
+//! index. The index stores handles, not copies of the strings. Removing strings from the table
+//! removes them from the index.
+//!
+//! Like the table's other observers, the index only sees the strings interned by the copy of
+//! the crate it runs in: a plugin [attached](crate::attach) to the host's table finds the
+//! strings the plugin interned, not the host's.
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::ops::Bound;
+use std::sync::RwLock;
+
+use crate::Istr;
+
+static INDEX: RwLock<BTreeSet<Rev>> = RwLock::new(BTreeSet::new());
+
+/// Every interned string ending with `suffix`, in no particular order.
+pub fn find_suffix(suffix: &str) -> Vec<Istr> {
+    let index = INDEX.read().unwrap();
+    index
+        .range::<RevStr, _>((Bound::Included(RevStr::new(suffix)), Bound::Unbounded))
+        .take_while(|s| s.0.as_str().ends_with(suffix))
+        .map(|s| s.0)
+        .collect()
+}
+
+/// Called by the active table after a new string is inserted.
+pub(crate) fn inserted(s: Istr) {
+    INDEX.write().unwrap().insert(Rev(s));
+}
+
+/// Called by the active table after a string is removed, before it is freed.
+pub(crate) fn removed(s: Istr) {
+    INDEX.write().unwrap().remove(RevStr::new(s.as_str()));
+}
+
+/// Called when the active table is reset, before its strings are freed.
+pub(crate) fn cleared() {
+    INDEX.write().unwrap().clear();
+}
+
+/// An interned string ordered by its reversed bytes.
+#[derive(PartialEq, Eq)]
+struct Rev(Istr);
+
+/// An unsized view of a string ordered by its reversed bytes, used to query the index
+/// without interning the suffix.
+#[repr(transparent)]
+struct RevStr(str);
+
+impl RevStr {
+    fn new(s: &str) -> &RevStr {
+        // SAFETY: `RevStr` is a `repr(transparent)` wrapper around `str`.
+        unsafe { &*(s as *const str as *const RevStr) }
+    }
+}
+
+impl Borrow<RevStr> for Rev {
+    fn borrow(&self) -> &RevStr {
+        RevStr::new(self.0.as_str())
+    }
+}
+
+impl Ord for RevStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.bytes().rev().cmp(other.0.bytes().rev())
+    }
+}
+
+impl PartialOrd for RevStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for RevStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for RevStr {}
+
+impl Ord for Rev {
+    fn cmp(&self, other: &Self) -> Ordering {
+        Borrow::<RevStr>::borrow(self).cmp(other.borrow())
+    }
+}
+
+impl PartialOrd for Rev {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_suffix() {
+        let json = ["assets/a.json", "assets/b/c.json", ".json"].map(Istr::new);
+        Istr::new("assets/a.jsonl");
+        Istr::new("assets/json");
+        let mut found = find_suffix(".json");
+        found.retain(|s| s.as_str().starts_with("assets/") || s.as_str() == ".json");
+        found.sort_by_key(|s| s.as_str());
+        let mut expected = json.to_vec();
+        expected.sort_by_key(|s| s.as_str());
+        assert_eq!(found, expected);
+        assert!(find_suffix("").contains(&json[0]));
+    }
+}
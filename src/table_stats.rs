@@ -0,0 +1,155 @@
+//! [`TableStats`], the size and use of a table at a glance.
+use std::fmt;
+
+use crate::{InternTable, InternedData, SHARDS};
+
+/// The size of an [`InternTable`] and, with the `profiling` feature, how its interning calls
+/// went. Returned by [`InternTable::stats`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TableStats {
+    /// The number of strings in the table.
+    pub strings: usize,
+    /// The total length of the strings, in bytes.
+    pub bytes: usize,
+    /// The bytes their entries take beyond the strings: headers, and padding.
+    pub header_bytes: usize,
+    /// The number of strings the shards can hold before they grow.
+    pub capacity: usize,
+    /// The calls to [`InternTable::get_or_intern`] and its variants that found their string
+    /// already interned, with the `profiling` feature. Strings served from a thread's
+    /// [cache](crate::thread_cache) don't reach the table, and aren't counted.
+    pub hits: Option<u64>,
+    /// The calls that inserted their string, with the `profiling` feature.
+    pub misses: Option<u64>,
+    /// The bytes the `compact-len` feature saves, with it on: how much larger the entries
+    /// would be if their headers stored the length as a `usize`.
+    pub len_savings: Option<usize>,
+}
+
+impl TableStats {
+    /// The share of the capacity in use, from 0 to 1.
+    pub fn load_factor(&self) -> f64 {
+        if self.capacity == 0 {
+            return 0.0;
+        }
+        self.strings as f64 / self.capacity as f64
+    }
+}
+
+impl fmt::Display for TableStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} strings, {} bytes + {} bytes of headers, {:.0}% of capacity {}",
+            self.strings,
+            self.bytes,
+            self.header_bytes,
+            self.load_factor() * 100.0,
+            self.capacity
+        )?;
+        if let (Some(hits), Some(misses)) = (self.hits, self.misses) {
+            write!(f, ", {} hits, {} misses", hits, misses)?;
+        }
+        if let Some(savings) = self.len_savings {
+            write!(f, ", {} bytes saved by compact lengths", savings)?;
+        }
+        Ok(())
+    }
+}
+
+impl InternTable {
+    /// The size of the table, and its hit and miss counts with the `profiling` feature. Walks
+    /// every entry, taking each shard's lock in turn for reading.
+    ///
+    /// ```
+    /// let table = lsi::InternTable::new();
+    /// table.get_or_intern("a");
+    /// table.get_or_intern("bc");
+    /// let stats = table.stats();
+    /// assert_eq!((stats.strings, stats.bytes), (2, 3));
+    /// assert!(stats.load_factor() > 0.0);
+    /// ```
+    pub fn stats(&self) -> TableStats {
+        let mut stats = TableStats::default();
+        #[cfg(feature = "profiling")]
+        {
+            stats.hits = Some(0);
+            stats.misses = Some(0);
+        }
+        #[cfg(feature = "compact-len")]
+        {
+            stats.len_savings = Some(0);
+        }
+        for i in 0..SHARDS {
+            self.read(i, |entries| {
+                stats.strings += entries.set.len();
+                stats.capacity += entries.set.capacity();
+                for s in entries.set.iter() {
+                    let size = InternedData::layout_of(s.len()).unwrap().size();
+                    stats.bytes += s.len();
+                    stats.header_bytes += size - s.len();
+                    #[cfg(feature = "compact-len")]
+                    {
+                        stats.len_savings = stats
+                            .len_savings
+                            .map(|n| n + usize_len_size(s.len()) - size);
+                    }
+                }
+            });
+            #[cfg(feature = "profiling")]
+            {
+                let (hits, misses) = self.0[i].counters.get();
+                stats.hits = stats.hits.map(|n| n + hits);
+                stats.misses = stats.misses.map(|n| n + misses);
+            }
+        }
+        stats
+    }
+}
+
+/// The size of the entry of a string of `n` bytes if its header stored the length as a `usize`.
+#[cfg(feature = "compact-len")]
+fn usize_len_size(n: usize) -> usize {
+    use std::mem::{align_of, offset_of, size_of};
+    let align = align_of::<crate::Header>().max(align_of::<usize>());
+    let offset = offset_of!(crate::HeaderWith<0>, len).next_multiple_of(align_of::<usize>());
+    let header = (offset + size_of::<usize>()).next_multiple_of(align);
+    (header + n).next_multiple_of(align)
+}
+
+/// The [stats](InternTable::stats) of the active table.
+#[cfg(feature = "global")]
+pub fn stats() -> TableStats {
+    crate::active_table().stats()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let table = InternTable::new();
+        assert_eq!(table.stats().load_factor(), 0.0);
+        for i in 0..100 {
+            table.get_or_intern(&format!("{:03}", i));
+        }
+        table.get_or_intern("000");
+        let stats = table.stats();
+        assert_eq!((stats.strings, stats.bytes), (100, 300));
+        assert!(stats.header_bytes >= 100 * std::mem::size_of::<crate::Header>());
+        assert!(stats.capacity >= 100);
+        #[cfg(feature = "profiling")]
+        assert_eq!((stats.hits, stats.misses), (Some(1), Some(100)));
+        #[cfg(not(feature = "profiling"))]
+        assert_eq!((stats.hits, stats.misses), (None, None));
+        #[cfg(feature = "compact-len")]
+        if std::mem::size_of::<crate::Header>() == std::mem::size_of::<u32>() {
+            // 4 + 3 bytes of entry rounded up to 8, instead of 8 + 3 rounded up to 16.
+            assert_eq!(stats.len_savings, Some(800));
+        }
+        #[cfg(not(feature = "compact-len"))]
+        assert_eq!(stats.len_savings, None);
+        assert!(stats.to_string().starts_with("100 strings, 300 bytes + "));
+    }
+}
@@ -0,0 +1,147 @@
+//! Typed side tables hanging values off interned strings.
+//!
+//! [`tag::<T>()`](tag) is the process-wide table of `T` values keyed by handle, created on first
+//! use. A compiler can attach a kind, a span or an id to each identifier without keeping a map
+//! of its own, and any part of the program asking for the same `T` finds the same table:
+//!
+//! ```
+//! use lsi::{tag, InternTable};
+//!
+//! #[derive(Clone, Debug, PartialEq)]
+//! enum Kind {
+//!     Keyword,
+//!     Ident,
+//! }
+//!
+//! let table = InternTable::new();
+//! let kw = table.get_or_intern("fn");
+//! tag::<Kind>().set(kw, Kind::Keyword);
+//! assert_eq!(tag::<Kind>().get(kw), Some(Kind::Keyword));
+//! assert_eq!(tag::<Kind>().get(table.get_or_intern("main")), None);
+//! ```
+//!
+//! Values are keyed by the handle's address, so looking one up never touches the string. Use
+//! a newtype for `T` to keep apart two tables of the same type. Removing a string from its
+//! table, or resetting the table, leaves its values behind: remove them first.
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::{Istr, IstrMap};
+
+/// Every tag created so far, each a leaked `Tag<T>` keyed by the `TypeId` of `T`.
+static TAGS: RwLock<Option<HashMap<TypeId, &'static (dyn Any + Send + Sync)>>> =
+    RwLock::new(None);
+
+/// A table of `T` values keyed by interned strings, see [`tag`].
+#[derive(Debug)]
+pub struct Tag<T> {
+    values: RwLock<IstrMap<T>>,
+}
+
+/// The tag of `T` values, created on first use and never freed.
+pub fn tag<T: Send + Sync + 'static>() -> &'static Tag<T> {
+    let key = TypeId::of::<T>();
+    let found = TAGS.read().unwrap().as_ref().and_then(|m| m.get(&key).copied());
+    let tag = match found {
+        Some(tag) => tag,
+        None => *TAGS
+            .write()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .entry(key)
+            .or_insert_with(|| {
+                Box::leak(Box::new(Tag::<T> {
+                    values: RwLock::new(IstrMap::default()),
+                }))
+            }),
+    };
+    tag.downcast_ref().unwrap()
+}
+
+impl<T> Tag<T> {
+    /// Associate `value` with `s`, returning the value it replaces.
+    pub fn set(&self, s: Istr, value: T) -> Option<T> {
+        self.values.write().unwrap().insert(s, value)
+    }
+
+    /// A copy of the value associated with `s`. Wrap large values in an `Arc` to make this
+    /// cheap.
+    pub fn get(&self, s: Istr) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.values.read().unwrap().get(&s).cloned()
+    }
+
+    /// The value associated with `s`, associating `f()` with it first if there is none.
+    pub fn get_or_insert_with(&self, s: Istr, f: impl FnOnce() -> T) -> T
+    where
+        T: Clone,
+    {
+        if let Some(value) = self.get(s) {
+            return value;
+        }
+        let value = f();
+        self.values
+            .write()
+            .unwrap()
+            .entry(s)
+            .or_insert(value)
+            .clone()
+    }
+
+    pub fn contains(&self, s: Istr) -> bool {
+        self.values.read().unwrap().contains_key(&s)
+    }
+
+    pub fn remove(&self, s: Istr) -> Option<T> {
+        self.values.write().unwrap().remove(&s)
+    }
+
+    /// The number of strings with a value.
+    pub fn len(&self) -> usize {
+        self.values.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every value.
+    pub fn clear(&self) {
+        self.values.write().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InternTable;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct SymbolId(u32);
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Span(u32, u32);
+
+    #[test]
+    fn test_tags() {
+        let table = InternTable::new();
+        let [main, x] = ["main", "x"].map(|s| table.get_or_intern(s));
+        assert!(std::ptr::eq(tag::<SymbolId>(), tag::<SymbolId>()));
+        assert_eq!(tag::<SymbolId>().set(main, SymbolId(1)), None);
+        assert_eq!(tag::<SymbolId>().set(main, SymbolId(2)), Some(SymbolId(1)));
+        tag::<Span>().set(main, Span(0, 4));
+        assert_eq!(tag::<SymbolId>().get(main), Some(SymbolId(2)));
+        assert_eq!(tag::<Span>().get(main), Some(Span(0, 4)));
+        assert!(!tag::<Span>().contains(x));
+        assert_eq!(tag::<Span>().get_or_insert_with(x, || Span(5, 6)), Span(5, 6));
+        assert_eq!(tag::<Span>().get_or_insert_with(x, || unreachable!()), Span(5, 6));
+        assert_eq!(tag::<Span>().len(), 2);
+        assert_eq!(tag::<Span>().remove(x), Some(Span(5, 6)));
+        tag::<Span>().clear();
+        assert!(tag::<Span>().is_empty());
+        assert_eq!(tag::<SymbolId>().len(), 1);
+    }
+}
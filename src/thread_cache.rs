@@ -0,0 +1,166 @@
+//! A small per-thread cache in front of the global table.
+//!
+//! [`Istr::new`] first probes a direct-mapped cache of the strings the current thread interned
+//! recently, keyed by their table hash, and only goes to the [`active_table`] on a miss. A
+//! tokenizer that sees the same identifiers over and over on one thread then resolves them
+//! from memory no other thread writes to, without touching the table's shared cache lines.
+//!
+//! Each thread's cache has [`DEFAULT_SLOTS`] slots unless [`set_default_size`] was called
+//! before the thread first interned, and can be resized or disabled per thread with
+//! [`set_size`]. A cache costs 16 bytes per slot.
+//!
+//! Restoring a checkpoint, or resetting the table, empties every cache before freeing
+//! anything. Restores and resets done by another copy of the crate, on a table this copy is
+//! [attached](crate::attach) to, don't reach this copy's caches: disable them with
+//! [`set_default_size`]`(0)` in that setup.
+//!
+//! [`Istr::new`]: crate::Istr::new
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{active_table, check, Checked, Error, InternTable, Istr};
+
+/// The number of slots of a thread's cache, unless changed with [`set_default_size`].
+pub const DEFAULT_SLOTS: usize = 256;
+
+static DEFAULT_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_SLOTS);
+
+/// Bumped by every restore of a checkpoint or reset, which may free cached handles. Caches
+/// tagged with an older generation are emptied before they are used.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// `None` until the thread first interns.
+    static CACHE: RefCell<Option<Cache>> = const { RefCell::new(None) };
+}
+
+/// No slots if the cache is disabled.
+struct Cache {
+    slots: Box<[Option<(u64, Istr)>]>,
+    generation: usize,
+}
+
+impl Cache {
+    fn new(slots: usize) -> Self {
+        let slots = if slots == 0 {
+            0
+        } else {
+            slots.next_power_of_two()
+        };
+        Cache {
+            slots: vec![None; slots].into_boxed_slice(),
+            generation: GENERATION.load(Ordering::Acquire),
+        }
+    }
+
+    fn slot(&mut self, hash: u64) -> Option<&mut Option<(u64, Istr)>> {
+        if self.slots.is_empty() {
+            return None;
+        }
+        let generation = GENERATION.load(Ordering::Acquire);
+        if self.generation != generation {
+            self.slots.fill(None);
+            self.generation = generation;
+        }
+        let mask = self.slots.len() - 1;
+        // The low bits pick the shard's index slot, so use the high ones here.
+        Some(&mut self.slots[(hash >> 40) as usize & mask])
+    }
+}
+
+/// Resize the current thread's cache to `slots`, rounded up to a power of two, emptying it.
+/// Zero disables the cache on this thread.
+pub fn set_size(slots: usize) {
+    let _ = CACHE.try_with(|cache| {
+        *cache.borrow_mut() = Some(Cache::new(slots));
+    });
+}
+
+/// The number of slots of the caches of threads that haven't interned yet. Zero disables
+/// them. Caches that already exist keep their size.
+pub fn set_default_size(slots: usize) {
+    DEFAULT_SIZE.store(slots, Ordering::Relaxed);
+}
+
+/// Intern the non-empty `s` into the active table through the current thread's cache, once it
+/// passes the [checks](crate::check) of every function interning into the active table.
+/// Returns whether it was newly inserted.
+pub(crate) fn get_or_intern(s: &str) -> Result<(Istr, bool), Error> {
+    let hash = InternTable::hash(s);
+    let cached = CACHE.try_with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let cache = cache.get_or_insert_with(|| Cache::new(DEFAULT_SIZE.load(Ordering::Relaxed)));
+        cache
+            .slot(hash)?
+            .filter(|&(h, fast_str)| h == hash && fast_str.as_str() == s)
+            .map(|(_, fast_str)| fast_str)
+    });
+    if let Ok(Some(fast_str)) = cached {
+        return Ok((fast_str, false));
+    }
+    let table = active_table();
+    match check(table, s, hash, true)? {
+        Checked::Found(fast_str) => {
+            if fast_str.as_str() == s {
+                remember(hash, fast_str);
+            }
+            Ok((fast_str, false))
+        }
+        Checked::Insert(s, hash) => {
+            // The cache isn't borrowed while interning, which may run hooks that intern.
+            let (fast_str, inserted) = table.intern_hashed(&s, hash);
+            remember(hash, fast_str);
+            Ok((fast_str, inserted))
+        }
+        Checked::Contended => unreachable!("waited for the lock"),
+    }
+}
+
+/// Cache `fast_str`, whose table hash is `hash`, on the current thread.
+fn remember(hash: u64, fast_str: Istr) {
+    let _ = CACHE.try_with(|cache| {
+        if let Some(slot) = cache.borrow_mut().as_mut().and_then(|c| c.slot(hash)) {
+            *slot = Some((hash, fast_str));
+        }
+    });
+}
+
+/// Invalidate every thread's cache. Called by restores and resets of the active table, before
+/// they free entries.
+pub(crate) fn invalidate() {
+    GENERATION.fetch_add(1, Ordering::Release);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(s: &str) -> Option<Istr> {
+        let hash = InternTable::hash(s);
+        CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            let slot = *cache.as_mut()?.slot(hash)?;
+            slot.filter(|&(_, fast_str)| fast_str.as_str() == s)
+                .map(|(_, fast_str)| fast_str)
+        })
+    }
+
+    #[test]
+    fn test_thread_cache() {
+        let s = Istr::new("thread cached");
+        assert_eq!(cached("thread cached"), Some(s));
+        assert_eq!(Istr::new("thread cached"), s);
+        std::thread::spawn(move || {
+            assert_eq!(cached("thread cached"), None);
+            set_size(0);
+            assert_eq!(Istr::new("thread cached"), s);
+            assert_eq!(cached("thread cached"), None);
+            set_size(3);
+            assert_eq!(Istr::new("thread cached"), s);
+            assert_eq!(cached("thread cached"), Some(s));
+            CACHE.with(|cache| assert_eq!(cache.borrow().as_ref().unwrap().slots.len(), 4));
+        })
+        .join()
+        .unwrap();
+    }
+}
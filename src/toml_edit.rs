@@ -0,0 +1,154 @@
+//! Interned keys for [`toml_edit`](::toml_edit) documents, for tools that rewrite config files.
+//!
+//! `toml_edit` stores each key's text together with its formatting: quoting, whitespace and
+//! comments. That is what lets a document be edited and written back byte for byte, so the
+//! keys stay owned by the document. [`InternedDocument`] wraps a document and speaks
+//! [`Istr`] at its edges instead: it hands out the keys of a table interned, and walks paths
+//! of interned keys. A tool matching keys against a known set compares handles, while every
+//! edit still goes through the document and keeps its formatting.
+//!
+//! ```
+//! use lsi::toml_edit::InternedDocument;
+//! use lsi::Istr;
+//!
+//! let mut doc: InternedDocument = "[server]  # the API\nport = 8080\n".parse().unwrap();
+//! let [server, port] = ["server", "port"].map(Istr::new);
+//! assert_eq!(doc.keys(&[server]).unwrap(), [port]);
+//! *doc.get_mut(&[server, port]).unwrap() = toml_edit::value(9090);
+//! assert_eq!(doc.to_string(), "[server]  # the API\nport = 9090\n");
+//! ```
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+
+use ::toml_edit::{DocumentMut, Item, TomlError};
+
+use crate::{Error, Istr};
+
+/// A `toml_edit` document addressed by interned keys. Derefs to the document for everything
+/// else.
+#[derive(Clone, Debug, Default)]
+pub struct InternedDocument(DocumentMut);
+
+impl InternedDocument {
+    pub fn new(doc: DocumentMut) -> Self {
+        InternedDocument(doc)
+    }
+
+    pub fn into_inner(self) -> DocumentMut {
+        self.0
+    }
+
+    /// The item at `path`, a key per level of nested tables. The empty path is the root table.
+    pub fn get(&self, path: &[Istr]) -> Option<&Item> {
+        path.iter().try_fold(self.0.as_item(), |item, key| {
+            item.as_table_like()?.get(key.as_str())
+        })
+    }
+
+    pub fn get_mut(&mut self, path: &[Istr]) -> Option<&mut Item> {
+        path.iter().try_fold(self.0.as_item_mut(), |item, key| {
+            item.as_table_like_mut()?.get_mut(key.as_str())
+        })
+    }
+
+    /// The keys of the table at `path`, interned, in document order. `None` if there is no
+    /// table there.
+    ///
+    /// # Panics
+    ///
+    /// If a key can't be interned, see [`InternedDocument::try_keys`].
+    pub fn keys(&self, path: &[Istr]) -> Option<Vec<Istr>> {
+        self.entries(path)
+            .map(|entries| entries.into_iter().map(|(key, _)| key).collect())
+    }
+
+    /// Like [`InternedDocument::keys`], but returns the error of [`Istr::try_new`] for a key
+    /// that can't be interned instead of panicking.
+    pub fn try_keys(&self, path: &[Istr]) -> Result<Option<Vec<Istr>>, Error> {
+        Ok(self
+            .try_entries(path)?
+            .map(|entries| entries.into_iter().map(|(key, _)| key).collect()))
+    }
+
+    /// The entries of the table at `path`, with interned keys, in document order.
+    ///
+    /// # Panics
+    ///
+    /// If a key can't be interned, see [`InternedDocument::try_entries`].
+    pub fn entries(&self, path: &[Istr]) -> Option<Vec<(Istr, &Item)>> {
+        match self.try_entries(path) {
+            Ok(entries) => entries,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [`InternedDocument::entries`], but returns the error of [`Istr::try_new`] for a
+    /// key that can't be interned instead of panicking.
+    pub fn try_entries(&self, path: &[Istr]) -> Result<Option<Vec<(Istr, &Item)>>, Error> {
+        let Some(table) = self.get(path).and_then(Item::as_table_like) else {
+            return Ok(None);
+        };
+        table
+            .iter()
+            .map(|(key, item)| Ok((Istr::try_new(key)?, item)))
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+}
+
+impl Deref for InternedDocument {
+    type Target = DocumentMut;
+
+    fn deref(&self) -> &DocumentMut {
+        &self.0
+    }
+}
+
+impl DerefMut for InternedDocument {
+    fn deref_mut(&mut self) -> &mut DocumentMut {
+        &mut self.0
+    }
+}
+
+impl From<DocumentMut> for InternedDocument {
+    fn from(doc: DocumentMut) -> Self {
+        InternedDocument(doc)
+    }
+}
+
+impl FromStr for InternedDocument {
+    type Err = TomlError;
+
+    fn from_str(s: &str) -> Result<Self, TomlError> {
+        s.parse().map(InternedDocument)
+    }
+}
+
+/// Writes the document back with its formatting preserved.
+impl fmt::Display for InternedDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = "# settings\nname = \"api\"   # trailing\n\n[server]\nport = 8080\n\"quoted key\" = { a = 1 }\n";
+        let mut doc: InternedDocument = text.parse().unwrap();
+        assert_eq!(doc.to_string(), text);
+        let [name, server, port, quoted, a] =
+            ["name", "server", "port", "quoted key", "a"].map(Istr::new);
+        assert_eq!(doc.keys(&[]).unwrap(), [name, server]);
+        assert_eq!(doc.keys(&[server]).unwrap(), [port, quoted]);
+        assert_eq!(doc.get(&[server, quoted, a]).unwrap().as_integer(), Some(1));
+        assert!(doc.keys(&[name]).is_none());
+        assert!(doc.get(&[port]).is_none());
+        *doc.get_mut(&[server, port]).unwrap() = ::toml_edit::value(9090);
+        assert_eq!(doc.to_string(), text.replace("8080", "9090"));
+    }
+}
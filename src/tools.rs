@@ -0,0 +1,207 @@
+//! Inspecting logs and snapshots written by the [`persist`](crate::persist) module, for
+//! operators looking at a production snapshot without writing code against the format.
+//!
+//! Each function reads the file at a path and writes a plain-text report, one string per line
+//! with control characters and quotes escaped as in a Rust string literal. Nothing is interned,
+//! so the tools don't grow the table of the process running them. A 20-line bin is enough to
+//! expose them on the command line:
+//!
+//! ```no_run
+//! use std::io::{self, Write};
+//!
+//! fn main() -> io::Result<()> {
+//!     let args: Vec<String> = std::env::args().skip(1).collect();
+//!     let mut out = io::stdout().lock();
+//!     match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+//!         ["print", path] => lsi::tools::print(path, &mut out),
+//!         ["grep", pattern, path] => lsi::tools::grep(path, pattern, &mut out).map(drop),
+//!         ["diff", old, new] => lsi::tools::diff(old, new, &mut out).map(drop),
+//!         ["stat", path] => writeln!(out, "{}", lsi::tools::stat(path)?),
+//!         _ => {
+//!             eprintln!("usage: lsi-tool print|stat FILE | grep PATTERN FILE | diff OLD NEW");
+//!             std::process::exit(2);
+//!         }
+//!     }
+//! }
+//! ```
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::path::Path;
+
+use crate::persist::{read_records, Record};
+
+/// Write every string of the file not removed since, in file order.
+pub fn print(path: impl AsRef<Path>, out: &mut impl Write) -> io::Result<()> {
+    for s in crate::persist::read_log(path)? {
+        writeln!(out, "{}", s.escape_debug())?;
+    }
+    Ok(())
+}
+
+/// Write the strings of the file that contain `pattern`, in file order, returning how many
+/// there were.
+pub fn grep(path: impl AsRef<Path>, pattern: &str, out: &mut impl Write) -> io::Result<usize> {
+    let mut matches = 0;
+    for s in crate::persist::read_log(path)? {
+        if s.contains(pattern) {
+            writeln!(out, "{}", s.escape_debug())?;
+            matches += 1;
+        }
+    }
+    Ok(matches)
+}
+
+/// The strings added and removed between two files, as counted by [`diff`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Write the strings only in `old`, prefixed with `- `, then those only in `new`, prefixed with
+/// `+ `, each sorted. Files are compared as sets, as snapshots list the table in no particular
+/// order.
+pub fn diff(
+    old: impl AsRef<Path>,
+    new: impl AsRef<Path>,
+    out: &mut impl Write,
+) -> io::Result<DiffStats> {
+    let (old, new) = (
+        crate::persist::read_log(old)?,
+        crate::persist::read_log(new)?,
+    );
+    let old_set: HashSet<&str> = old.iter().map(String::as_str).collect();
+    let new_set: HashSet<&str> = new.iter().map(String::as_str).collect();
+    let mut removed: Vec<&str> = old_set.difference(&new_set).copied().collect();
+    let mut added: Vec<&str> = new_set.difference(&old_set).copied().collect();
+    removed.sort_unstable();
+    added.sort_unstable();
+    for s in &removed {
+        writeln!(out, "- {}", s.escape_debug())?;
+    }
+    for s in &added {
+        writeln!(out, "+ {}", s.escape_debug())?;
+    }
+    Ok(DiffStats {
+        added: added.len(),
+        removed: removed.len(),
+    })
+}
+
+/// A summary of a file, from [`stat`]. Its `Display` is a short report.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The size of the file.
+    pub file_bytes: u64,
+    /// The number of records of interned strings.
+    pub records: usize,
+    /// The number of records of removed strings and resets.
+    pub removals: usize,
+    /// The number of distinct strings. Less than `records` for a log that recorded a string
+    /// more than once.
+    pub distinct: usize,
+    /// The total length of the strings.
+    pub string_bytes: u64,
+    pub longest: usize,
+    /// The bytes of a truncated final record, which replay discards.
+    pub torn_bytes: u64,
+}
+
+/// Summarize the file at `path`.
+pub fn stat(path: impl AsRef<Path>) -> io::Result<Stats> {
+    let file = File::open(path)?;
+    let file_bytes = file.metadata()?.len();
+    let mut stats = Stats {
+        file_bytes,
+        ..Stats::default()
+    };
+    let mut distinct = HashSet::new();
+    let end = read_records(BufReader::new(file), |record| match record {
+        Record::Interned(s) => {
+            stats.records += 1;
+            stats.string_bytes += s.len() as u64;
+            stats.longest = stats.longest.max(s.len());
+            distinct.insert(s.to_owned());
+        }
+        Record::Removed(_) | Record::Reset => stats.removals += 1,
+    })?;
+    stats.distinct = distinct.len();
+    stats.torn_bytes = file_bytes - end;
+    Ok(stats)
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "file bytes:   {}", self.file_bytes)?;
+        writeln!(f, "records:      {}", self.records)?;
+        writeln!(f, "removals:     {}", self.removals)?;
+        writeln!(f, "distinct:     {}", self.distinct)?;
+        writeln!(f, "string bytes: {}", self.string_bytes)?;
+        let mean = self.string_bytes as f64 / self.records.max(1) as f64;
+        writeln!(f, "mean length:  {:.1}", mean)?;
+        writeln!(f, "longest:      {}", self.longest)?;
+        write!(f, "torn bytes:   {}", self.torn_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::{write_record, MAGIC};
+
+    fn write_file(name: &str, strings: &[&str], torn: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("lsi-tools-{}-{}", std::process::id(), name));
+        let mut contents = MAGIC.to_vec();
+        for s in strings {
+            write_record(&mut contents, s).unwrap();
+        }
+        contents.extend_from_slice(torn);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_tools() {
+        let old = write_file("old", &["alpha", "beta", "tab\there"], &[]);
+        let new = write_file(
+            "new",
+            &["beta", "gamma", "alphabet", "beta"],
+            &[7, 0, 0, 0, b'x'],
+        );
+
+        let mut out = Vec::new();
+        print(&old, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "alpha\nbeta\ntab\\there\n");
+
+        let mut out = Vec::new();
+        assert_eq!(grep(&new, "alpha", &mut out).unwrap(), 1);
+        assert_eq!(out, b"alphabet\n");
+
+        let mut out = Vec::new();
+        let stats = diff(&old, &new, &mut out).unwrap();
+        assert_eq!(
+            stats,
+            DiffStats {
+                added: 2,
+                removed: 2
+            }
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "- alpha\n- tab\\there\n+ alphabet\n+ gamma\n"
+        );
+
+        let stats = stat(&new).unwrap();
+        assert_eq!((stats.records, stats.distinct), (4, 3));
+        assert_eq!(
+            (stats.string_bytes, stats.longest, stats.torn_bytes),
+            (21, 8, 5)
+        );
+        assert!(stats.to_string().contains("distinct:     3\n"));
+
+        std::fs::remove_file(old).unwrap();
+        std::fs::remove_file(new).unwrap();
+    }
+}
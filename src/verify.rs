@@ -0,0 +1,236 @@
+//! Consistency checks of a table's entries, e.g. for canary builds after an upgrade.
+//!
+//! [`InternTable::verify`] walks every entry and checks that its bytes are valid UTF-8, that it
+//! is reachable by looking its string up, that no string is stored twice and, with the
+//! `stable-hash` feature, that its stored hash matches its string. With the `persist`
+//! feature, [`verify_snapshot`] also checks the table against a log on disk.
+//!
+//! ```
+//! let table = lsi::InternTable::new();
+//! table.get_or_intern("checked");
+//! let report = table.verify();
+//! assert!(report.is_ok(), "{}", report);
+//! assert_eq!(report.entries, 1);
+//! ```
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::{Error, InternTable};
+
+/// The result of [`InternTable::verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The number of entries checked.
+    pub entries: usize,
+    pub problems: Vec<Problem>,
+}
+
+/// An inconsistency found by a [`Report`]. Strings are included lossily converted, since an
+/// entry may not hold valid UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The bytes of the entry at `address` are not valid UTF-8.
+    InvalidUtf8 { address: usize },
+    /// The empty string is stored as an entry instead of using the shared sentinel.
+    EmptyEntry,
+    /// Looking the string up doesn't find this entry: it is stored under the wrong hash.
+    Unreachable(String),
+    /// The string is stored in more than one entry.
+    Duplicate(String),
+    /// The stored stable hash doesn't match the string.
+    StableHash(String),
+    /// The stored table hash doesn't match the string.
+    TableHash(String),
+    /// The insertion order kept for checkpoints disagrees with the table.
+    Order { entries: usize, order: usize },
+    /// The string is in the log on disk but not in the table.
+    NotInTable(String),
+    /// The string is in the table but not in the log on disk.
+    NotOnDisk(String),
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// `Err(Error::Verify)` with this report if it found problems.
+    pub fn into_result(self) -> Result<(), Error> {
+        if self.is_ok() {
+            Ok(())
+        } else {
+            Err(Error::Verify(self))
+        }
+    }
+}
+
+impl InternTable {
+    /// Check every entry of the table, see the [`verify`](crate::verify) module.
+    pub fn verify(&self) -> Report {
+        let entries = self.to_vec();
+        let mut problems = Vec::new();
+        let mut seen = HashSet::with_capacity(entries.len());
+        for &entry in &entries {
+            // Check the bytes before treating them as a `str`.
+            let bytes = unsafe { std::slice::from_raw_parts(entry.data_ptr(), entry.len()) };
+            let Ok(s) = std::str::from_utf8(bytes) else {
+                problems.push(Problem::InvalidUtf8 {
+                    address: entry.0.as_ptr() as usize,
+                });
+                continue;
+            };
+            if entry.is_empty() || s.is_empty() {
+                problems.push(Problem::EmptyEntry);
+            }
+            if !seen.insert(s) {
+                problems.push(Problem::Duplicate(s.to_owned()));
+            }
+            if self.get(s) != Some(entry) {
+                problems.push(Problem::Unreachable(s.to_owned()));
+            }
+            #[cfg(feature = "cached-hash")]
+            if entry.table_hash() != Self::hash(s) {
+                problems.push(Problem::TableHash(s.to_owned()));
+            }
+            #[cfg(feature = "stable-hash")]
+            if entry.stable_hash() != crate::stable_hash(s) {
+                problems.push(Problem::StableHash(s.to_owned()));
+            }
+        }
+        #[cfg(feature = "checkpoint")]
+        {
+            let (entries, order) = (0..crate::SHARDS)
+                .map(|i| self.read(i, |e| (e.set.len(), e.order.len())))
+                .fold((0, 0), |(n, m), (a, b)| (n + a, m + b));
+            if entries != order {
+                problems.push(Problem::Order { entries, order });
+            }
+        }
+        Report {
+            entries: entries.len(),
+            problems,
+        }
+    }
+}
+
+/// Check the active table, see [`InternTable::verify`].
+#[cfg(feature = "global")]
+pub fn verify() -> Report {
+    crate::active_table().verify()
+}
+
+/// Like [`verify`], but fails with [`Error::Verify`] if there are problems.
+#[cfg(feature = "global")]
+pub fn try_verify() -> Result<(), Error> {
+    verify().into_result()
+}
+
+/// Like [`verify_snapshot`], but also fails with [`Error::Verify`] if there are problems.
+#[cfg(feature = "persist")]
+pub fn try_verify_snapshot(path: impl AsRef<std::path::Path>) -> Result<(), Error> {
+    verify_snapshot(path)?.into_result()
+}
+
+/// Check the active table, which the [`persist`](crate::persist) log records, and that it
+/// holds exactly the strings of the log or snapshot at `path`. Flush an open log before
+/// checking it.
+#[cfg(feature = "persist")]
+pub fn verify_snapshot(path: impl AsRef<std::path::Path>) -> std::io::Result<Report> {
+    let table = crate::active_table();
+    let mut report = table.verify();
+    let on_disk = crate::persist::read_log(path)?;
+    let on_disk: HashSet<&str> = on_disk.iter().map(|s| s.as_str()).collect();
+    let in_table = table.to_vec();
+    let in_table: HashSet<&str> = in_table.iter().map(|s| s.as_str()).collect();
+    let mut missing: Vec<Problem> = on_disk
+        .difference(&in_table)
+        .map(|s| Problem::NotInTable(s.to_string()))
+        .chain(
+            in_table
+                .difference(&on_disk)
+                .map(|s| Problem::NotOnDisk(s.to_string())),
+        )
+        .collect();
+    missing.sort_unstable_by(|a, b| format!("{:?}", a).cmp(&format!("{:?}", b)));
+    report.problems.extend(missing);
+    Ok(report)
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::InvalidUtf8 { address } => {
+                write!(f, "entry at {:#x} is not valid UTF-8", address)
+            }
+            Problem::EmptyEntry => f.write_str("the empty string is stored as an entry"),
+            Problem::Unreachable(s) => write!(f, "{:?} is not found by lookup", s),
+            Problem::Duplicate(s) => write!(f, "{:?} is stored more than once", s),
+            Problem::StableHash(s) => write!(f, "stable hash of {:?} does not match", s),
+            Problem::TableHash(s) => write!(f, "table hash of {:?} does not match", s),
+            Problem::Order { entries, order } => write!(
+                f,
+                "{} entries but {} in the checkpoint order",
+                entries, order
+            ),
+            Problem::NotInTable(s) => write!(f, "{:?} is on disk but not in the table", s),
+            Problem::NotOnDisk(s) => write!(f, "{:?} is in the table but not on disk", s),
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} entries, {} problems",
+            self.entries,
+            self.problems.len()
+        )?;
+        for problem in &self.problems {
+            write!(f, "\n  {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let table = InternTable::new();
+        for i in 0..100 {
+            table.get_or_intern(&format!("entry-{}", i));
+        }
+        table.get_or_intern("");
+        let report = table.verify();
+        assert_eq!(report.entries, 100);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.to_string(), "100 entries, 0 problems");
+        assert!(report.into_result().is_ok());
+    }
+
+    #[test]
+    fn test_unreachable() {
+        // An entry inserted under the hash of another string can't be found by lookup.
+        let table = InternTable::new();
+        let hash = InternTable::hash("elsewhere");
+        table.write(crate::shard(hash), |entries| {
+            let fast_str = crate::InternedData::construct("misplaced");
+            entries
+                .set
+                .raw_table_mut()
+                .insert(hash, (fast_str, ()), |_| hash);
+            #[cfg(feature = "checkpoint")]
+            entries.order.push(fast_str);
+        });
+        let report = table.verify();
+        assert_eq!(report.problems, [Problem::Unreachable("misplaced".into())]);
+        assert_eq!(
+            report.to_string(),
+            "1 entries, 1 problems\n  \"misplaced\" is not found by lookup"
+        );
+        assert!(matches!(report.into_result(), Err(Error::Verify(_))));
+    }
+}
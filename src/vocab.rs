@@ -0,0 +1,196 @@
+//! Exporting interned strings as a tokenizer vocabulary with stable integer ids.
+//!
+//! A [`Vocab`] assigns each token a `u32` id in insertion order and can be written and read as
+//! a HuggingFace-style `vocab.txt` (one token per line, the id being the line number) or, with
+//! the `json` feature, a `vocab.json` object mapping tokens to ids. Reading a vocabulary back and
+//! inserting new tokens keeps the existing ids, so a preprocessing pipeline and the training code
+//! on the other side of the file agree on them.
+//!
+//! ```
+//! use lsi::vocab::Vocab;
+//! use lsi::InternTable;
+//!
+//! let table = InternTable::new();
+//! let mut vocab = Vocab::new();
+//! vocab.insert(table.get_or_intern("[PAD]"));
+//! vocab.insert(table.get_or_intern("hello"));
+//!
+//! let mut txt = Vec::new();
+//! vocab.write_txt(&mut txt).unwrap();
+//! assert_eq!(txt, b"[PAD]\nhello\n");
+//! let vocab = Vocab::read_txt(&table, &txt[..]).unwrap();
+//! assert_eq!(vocab.id(table.get_or_intern("hello")), Some(1));
+//! ```
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::{InternTable, Istr};
+
+/// An ordered set of interned tokens, each with a `u32` id.
+#[derive(Clone, Debug, Default)]
+pub struct Vocab {
+    tokens: Vec<Istr>,
+    ids: HashMap<Istr, u32>,
+}
+
+impl Vocab {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A vocabulary of every string in `table`, with ids assigned in sorted order so that the
+    /// same table contents always give the same ids.
+    pub fn from_table(table: &InternTable) -> Self {
+        let mut tokens = table.to_vec();
+        tokens.sort_unstable_by_key(|s| s.as_str());
+        let mut vocab = Vocab::new();
+        for s in tokens {
+            vocab.insert(s);
+        }
+        vocab
+    }
+
+    /// The id of `token`, assigning the next free id if it has none yet.
+    pub fn insert(&mut self, token: Istr) -> u32 {
+        if let Some(&id) = self.ids.get(&token) {
+            return id;
+        }
+        let id =
+            u32::try_from(self.tokens.len()).expect("vocabulary has more than u32::MAX tokens");
+        self.tokens.push(token);
+        self.ids.insert(token, id);
+        id
+    }
+
+    pub fn id(&self, token: Istr) -> Option<u32> {
+        self.ids.get(&token).copied()
+    }
+
+    pub fn token(&self, id: u32) -> Option<Istr> {
+        self.tokens.get(id as usize).copied()
+    }
+
+    /// The tokens, in id order.
+    pub fn tokens(&self) -> &[Istr] {
+        &self.tokens
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Read a `vocab.txt`, interning its tokens into `table`.
+    pub fn read_txt(table: &InternTable, r: impl BufRead) -> io::Result<Self> {
+        let mut vocab = Vocab::new();
+        for line in r.lines() {
+            let token = table.get_or_intern(&line?);
+            if vocab.ids.contains_key(&token) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("token {:?} appears more than once", token.as_str()),
+                ));
+            }
+            vocab.insert(token);
+        }
+        Ok(vocab)
+    }
+
+    /// Write the vocabulary as a `vocab.txt`. Fails for tokens containing a line break, which
+    /// the format can't represent.
+    pub fn write_txt(&self, mut w: impl Write) -> io::Result<()> {
+        for token in &self.tokens {
+            if token.as_str().contains(['\n', '\r']) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("token {:?} contains a line break", token.as_str()),
+                ));
+            }
+            w.write_all(token.as_str().as_bytes())?;
+            w.write_all(b"\n")?;
+        }
+        w.flush()
+    }
+
+    /// Read a `vocab.json` object mapping tokens to ids, interning its tokens into `table`.
+    /// The ids must be exactly `0..n`, in any order.
+    #[cfg(feature = "json")]
+    pub fn read_json(table: &InternTable, r: impl io::Read) -> io::Result<Self> {
+        let map: HashMap<String, u32> = serde_json::from_reader(r)?;
+        let mut tokens = vec![None; map.len()];
+        for (token, id) in map {
+            let slot = tokens.get_mut(id as usize).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("id {} of {:?} is out of range", id, token),
+                )
+            })?;
+            if slot.is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("id {} is used more than once", id),
+                ));
+            }
+            *slot = Some(table.get_or_intern(&token));
+        }
+        let mut vocab = Vocab::new();
+        for token in tokens.into_iter().flatten() {
+            vocab.insert(token);
+        }
+        Ok(vocab)
+    }
+
+    /// Write the vocabulary as a `vocab.json` object, with entries in id order.
+    #[cfg(feature = "json")]
+    pub fn write_json(&self, mut w: impl Write) -> io::Result<()> {
+        w.write_all(b"{")?;
+        for (id, token) in self.tokens.iter().enumerate() {
+            if id > 0 {
+                w.write_all(b",")?;
+            }
+            serde_json::to_writer(&mut w, token.as_str())?;
+            write!(w, ":{}", id)?;
+        }
+        w.write_all(b"}")?;
+        w.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_txt_round_trip() {
+        let table = InternTable::new();
+        for s in ["b", "a", "ü"] {
+            table.get_or_intern(s);
+        }
+        let vocab = Vocab::from_table(&table);
+        assert_eq!(vocab.id(table.get_or_intern("a")), Some(0));
+        let mut txt = Vec::new();
+        vocab.write_txt(&mut txt).unwrap();
+        let mut read = Vocab::read_txt(&table, &txt[..]).unwrap();
+        assert_eq!(read.tokens(), vocab.tokens());
+        assert_eq!(read.insert(table.get_or_intern("new")), 3);
+        assert!(Vocab::read_txt(&table, &b"a\nb\na\n"[..]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_json_round_trip() {
+        let table = InternTable::new();
+        let mut vocab = Vocab::new();
+        for s in ["[CLS]", "quote\"d", "x"] {
+            vocab.insert(table.get_or_intern(s));
+        }
+        let mut json = Vec::new();
+        vocab.write_json(&mut json).unwrap();
+        let read = Vocab::read_json(&table, &json[..]).unwrap();
+        assert_eq!(read.tokens(), vocab.tokens());
+        assert!(Vocab::read_json(&table, &br#"{"a":0,"b":2}"#[..]).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+//! UTF-16 interop for Windows APIs, which take and return strings as `u16` units.
+//!
+//! [`Istr::from_wide`] and [`Istr::from_wide_lossy`] intern a string returned by an API, with
+//! `try_` variants returning an [error](crate::Error) if it can't be interned, and
+//! [`Istr::to_wide_cached`] encodes one to pass to an API. The encoding is computed on first
+//! use and cached in the entry, so passing the same interned names to the OS over and over,
+//! e.g. registry keys or window class names, encodes each of them once:
+//!
+//! ```
+//! let table = lsi::InternTable::new();
+//! let class = table.get_or_intern("MainWindow");
+//! let wide = class.to_wide_cached();
+//! assert_eq!(wide.last(), Some(&0));
+//! assert_eq!(String::from_utf16(&wide[..wide.len() - 1]).unwrap(), "MainWindow");
+//! assert!(std::ptr::eq(wide, class.to_wide_cached()));
+//! ```
+use std::ptr::null_mut;
+use std::sync::atomic::Ordering;
+
+#[cfg(feature = "global")]
+use crate::Error;
+use crate::Istr;
+
+/// The encoding of the empty string, which has no entry to cache it in.
+static EMPTY_WIDE: [u16; 1] = [0];
+
+impl Istr {
+    /// Intern the UTF-16 string `wide`, failing if it contains an unpaired surrogate.
+    ///
+    /// # Panics
+    ///
+    /// If the string can't be interned, see [`Istr::try_from_wide`].
+    #[cfg(feature = "global")]
+    pub fn from_wide(wide: &[u16]) -> Result<Istr, std::string::FromUtf16Error> {
+        match Istr::try_from_wide(wide) {
+            Ok(s) => Ok(s),
+            Err(Error::Utf16(e)) => Err(e),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [`Istr::from_wide`], but returns the errors of [`Istr::try_new`] instead of
+    /// panicking, and an unpaired surrogate as [`Error::Utf16`].
+    #[cfg(feature = "global")]
+    pub fn try_from_wide(wide: &[u16]) -> Result<Istr, Error> {
+        Istr::try_new(&String::from_utf16(wide)?)
+    }
+
+    /// Intern the UTF-16 string `wide`, replacing unpaired surrogates with U+FFFD.
+    ///
+    /// # Panics
+    ///
+    /// If the string can't be interned, see [`Istr::try_new`].
+    #[cfg(feature = "global")]
+    pub fn from_wide_lossy(wide: &[u16]) -> Istr {
+        match Istr::try_from_wide_lossy(wide) {
+            Ok(s) => s,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [`Istr::from_wide_lossy`], but returns the errors of [`Istr::try_new`] instead of
+    /// panicking.
+    #[cfg(feature = "global")]
+    pub fn try_from_wide_lossy(wide: &[u16]) -> Result<Istr, Error> {
+        Istr::try_new(&String::from_utf16_lossy(wide))
+    }
+
+    /// The UTF-16 encoding of this string followed by a NUL, ready to pass to Windows APIs as
+    /// a `PCWSTR` with `as_ptr()`. Computed on first use and leaked with the entry.
+    pub fn to_wide_cached(&self) -> &'static [u16] {
+        if self.is_empty() {
+            return &EMPTY_WIDE;
+        }
+        let cache = &self.header().wide;
+        // SAFETY: published encodings are leaked, and never replaced.
+        if let Some(&wide) = unsafe { cache.load(Ordering::Acquire).as_ref() } {
+            return wide;
+        }
+        #[cfg(feature = "counting-alloc")]
+        let _scope = crate::counting::Scope::enter();
+        let wide: Vec<u16> = self.as_str().encode_utf16().chain([0]).collect();
+        let wide = Box::into_raw(wide.into_boxed_slice());
+        // SAFETY: `wide` was just leaked.
+        let new = Box::into_raw(Box::new(unsafe { &*wide }));
+        match cache.compare_exchange(null_mut(), new, Ordering::AcqRel, Ordering::Acquire) {
+            // SAFETY: as above.
+            Ok(_) => unsafe { &*wide },
+            // SAFETY: another thread published first, so ours were never shared.
+            Err(published) => unsafe {
+                drop(Box::from_raw(new));
+                drop(Box::from_raw(wide));
+                *published
+            },
+        }
+    }
+}
+
+/// Free the cached encoding of `s`, if any, before `s` itself is freed.
+///
+/// # Safety
+///
+/// The encoding, which [`Istr::to_wide_cached`] returned as `&'static`, may not be used
+/// afterwards.
+pub(crate) unsafe fn free_cached(s: Istr) {
+    let cached = s.header().wide.load(Ordering::Acquire);
+    if let Some(&wide) = cached.as_ref() {
+        drop(Box::from_raw(wide as *const [u16] as *mut [u16]));
+        drop(Box::from_raw(cached));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::InternTable;
+
+    #[test]
+    fn test_to_wide_cached() {
+        let table = InternTable::new();
+        for s in ["C:\\Windows", "naïve 🦀", "a\0b"] {
+            let wide = table.get_or_intern(s).to_wide_cached();
+            let expected: Vec<u16> = s.encode_utf16().chain([0]).collect();
+            assert_eq!(wide, expected);
+        }
+        assert_eq!(table.get_or_intern("").to_wide_cached(), [0]);
+        let s = table.get_or_intern("shared");
+        let handles: Vec<_> = (0..4)
+            .map(|_| std::thread::spawn(move || s.to_wide_cached().as_ptr() as usize))
+            .collect();
+        let addrs: Vec<usize> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert!(addrs
+            .iter()
+            .all(|&a| a == s.to_wide_cached().as_ptr() as usize));
+    }
+
+    #[test]
+    #[cfg(feature = "global")]
+    fn test_from_wide() {
+        use crate::Istr;
+
+        let wide: Vec<u16> = "Program Files".encode_utf16().collect();
+        assert_eq!(Istr::from_wide(&wide).unwrap(), Istr::new("Program Files"));
+        let unpaired = [0x61, 0xD800, 0x62];
+        assert!(Istr::from_wide(&unpaired).is_err());
+        assert_eq!(Istr::from_wide_lossy(&unpaired), "a\u{FFFD}b");
+        assert!(matches!(
+            Istr::try_from_wide(&unpaired),
+            Err(crate::Error::Utf16(_))
+        ));
+        assert_eq!(Istr::try_from_wide_lossy(&unpaired).unwrap(), "a\u{FFFD}b");
+    }
+}
@@ -0,0 +1,102 @@
+//! A dedicated interner thread, used by callers that must never block on the table lock.
+//!
+//! Jobs carry an owned copy of the string and a [`Slot`] that the worker fills in once the
+//! string has been interned, or with the error of [`Istr::try_new`] if it can't be. Waiters
+//! may either register a [`Waker`] or block on the slot.
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::task::Waker;
+use std::thread;
+
+use crate::{Error, Istr};
+
+struct Job {
+    s: Box<str>,
+    slot: Arc<Slot>,
+}
+
+#[derive(Default)]
+pub(crate) struct Slot {
+    state: Mutex<SlotState>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct SlotState {
+    result: Option<Result<Istr, Error>>,
+    waker: Option<Waker>,
+}
+
+impl Slot {
+    #[cfg(feature = "background")]
+    pub(crate) fn get(&self) -> Option<Result<Istr, Error>> {
+        self.state.lock().unwrap().result.as_ref().map(copy)
+    }
+
+    /// Return the result if it's ready, otherwise register `waker` to be woken once it is.
+    #[cfg(feature = "async")]
+    pub(crate) fn poll(&self, waker: &Waker) -> Option<Result<Istr, Error>> {
+        let mut state = self.state.lock().unwrap();
+        if state.result.is_none() {
+            match &state.waker {
+                Some(w) if w.will_wake(waker) => {}
+                _ => state.waker = Some(waker.clone()),
+            }
+        }
+        state.result.as_ref().map(copy)
+    }
+
+    #[cfg(feature = "background")]
+    pub(crate) fn wait(&self) -> Result<Istr, Error> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(result) = &state.result {
+                return copy(result);
+            }
+            state = self.ready.wait(state).unwrap();
+        }
+    }
+
+    fn complete(&self, result: Result<Istr, Error>) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            state.result = Some(result);
+            state.waker.take()
+        };
+        self.ready.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+static SENDER: OnceLock<Sender<Job>> = OnceLock::new();
+
+/// Queue `s` to be interned on the worker thread, spawning the thread on first use.
+pub(crate) fn submit(s: &str) -> Arc<Slot> {
+    let slot = Arc::new(Slot::default());
+    let sender = SENDER.get_or_init(|| {
+        let (tx, rx) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("lsi-interner".into())
+            .spawn(move || {
+                for job in rx {
+                    job.slot.complete(Istr::try_new(&job.s));
+                }
+            })
+            .expect("failed to spawn interner thread");
+        tx
+    });
+    sender
+        .send(Job {
+            s: s.into(),
+            slot: slot.clone(),
+        })
+        .expect("interner thread exited");
+    slot
+}
+
+/// A copy of a result for one of the waiters of a slot.
+pub(crate) fn copy(result: &Result<Istr, Error>) -> Result<Istr, Error> {
+    result.as_ref().copied().map_err(Error::duplicate)
+}
@@ -0,0 +1,142 @@
+//! Sets a budget on the global table, so it runs in a process of its own.
+#![cfg(feature = "global")]
+
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll, Wake, Waker};
+
+use lsi::domain::IDomain;
+use lsi::json_pointer::JsonPointer;
+use lsi::{budget, CowIstr, Error, InternExt, Istr};
+
+/// Poll `future` on the current thread until it is ready.
+#[cfg(feature = "async")]
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unpark(std::thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::park();
+    }
+}
+
+#[test]
+fn test_budget() {
+    let known = Istr::new("known");
+    let before = budget::used();
+    Istr::new("one more");
+    assert!(budget::used() > before);
+    budget::set_limit(Some(budget::used() + 100));
+    assert_eq!(budget::limit(), Some(budget::used() + 100));
+    // Fits.
+    Istr::new("short");
+    let long = "x".repeat(200);
+    match Istr::try_new(&long) {
+        Err(Error::OverBudget(overrun)) => assert_eq!(overrun.len, 200),
+        other => panic!("{:?}", other),
+    }
+    // So is every other way of interning into the global table.
+    assert_eq!(lsi::try_intern(&long), None);
+    assert!(!lsi::intern_or_borrow(&long).is_interned());
+    assert!(matches!(lsi::intern_vec(vec![long.clone()]), Err(Error::OverBudget(_))));
+    assert!(matches!(lsi::bulk_load_unique([&long]), Err(Error::OverBudget(_))));
+    assert!(matches!(Istr::intern_all(&[&long]), Err(Error::OverBudget(_))));
+    assert!(matches!(Istr::intern_iter([&long]), Err(Error::OverBudget(_))));
+    assert!(std::panic::catch_unwind(|| [long.as_str()].into_iter().interned().count()).is_err());
+    #[cfg(feature = "async")]
+    assert!(matches!(block_on(lsi::intern_async(&long)), Err(Error::OverBudget(_))));
+    // A batch is admitted string by string, not against the bytes in use before it.
+    let batch: Vec<String> = ["a", "b", "c"].map(|c| c.repeat(40)).into();
+    assert!(matches!(Istr::intern_all(&batch), Err(Error::OverBudget(_))));
+    assert_eq!(Istr::get(&batch[2]), None);
+    assert_eq!(Istr::get(&long), None);
+    // The background worker reports the error instead of dying.
+    #[cfg(feature = "background")]
+    {
+        let (_, later) = lsi::intern_deferred(&long);
+        match later.unwrap().wait() {
+            Err(Error::OverBudget(overrun)) => assert_eq!(overrun.len, 200),
+            other => panic!("{:?}", other),
+        }
+    }
+    // Strings already interned are still found.
+    assert_eq!(Istr::try_new("known").unwrap(), known);
+    assert!(!CowIstr::new(&long).is_interned());
+    assert!(CowIstr::new("known").is_interned());
+    budget::set_on_exceeded(Some(|overrun| overrun.len == 200));
+    assert_eq!(Istr::try_new(&long).unwrap(), long.as_str());
+    assert!(Istr::try_new(&"y".repeat(300)).is_err());
+    budget::set_on_exceeded(None);
+    budget::set_limit(None);
+    assert_eq!(budget::limit(), None);
+    // And keeps serving.
+    #[cfg(feature = "background")]
+    {
+        let (_, later) = lsi::intern_deferred("fits");
+        assert_eq!(later.unwrap().wait().unwrap(), "fits");
+    }
+    Istr::new(&"y".repeat(300));
+    // Parsers and loaders return the error rather than panicking.
+    budget::set_limit(Some(budget::used()));
+    assert!(IDomain::try_parse("new.example.org").is_err());
+    assert!(JsonPointer::try_parse("/new/pointer").is_err());
+    lsi::interned_newtype! {
+        struct Name(Istr);
+    }
+    assert!(matches!(Name::try_new("new name"), Err(Error::OverBudget(_))));
+    assert!("new name".parse::<Name>().is_err());
+    lsi::define_atoms! {
+        mod atoms {
+            NEW = "new atom",
+        }
+    }
+    assert!(matches!(atoms::ATOMS.verify(), Err(Error::OverBudget(_))));
+    #[cfg(feature = "wide")]
+    {
+        let wide: Vec<u16> = "new from UTF-16".encode_utf16().collect();
+        assert!(matches!(Istr::try_from_wide(&wide), Err(Error::OverBudget(_))));
+        assert!(Istr::try_from_wide_lossy(&wide).is_err());
+    }
+    #[cfg(feature = "serde")]
+    {
+        use serde::de::value::{Error as DeError, StrDeserializer};
+        use serde::Deserialize;
+
+        assert!(matches!(
+            lsi::dict::to_vec("new in a dict"),
+            Err(lsi::dict::Error::Intern(_))
+        ));
+        let value = StrDeserializer::<DeError>::new("new in a config");
+        assert!(lsi::config::Value::deserialize(value).is_err());
+        assert!(Istr::deserialize(StrDeserializer::<DeError>::new("new by serde")).is_err());
+    }
+    #[cfg(feature = "toml-edit")]
+    {
+        let doc: lsi::toml_edit::InternedDocument = "new_key = 1\n".parse().unwrap();
+        assert!(doc.try_keys(&[]).is_err());
+    }
+    budget::set_limit(None);
+    budget::set_max_len(Some(100));
+    assert_eq!(budget::max_len(), Some(100));
+    assert!(matches!(Istr::try_new(&long), Err(Error::TooLong(200))));
+    // Even if it was interned before.
+    assert!(Istr::try_new(&"y".repeat(300)).is_err());
+    assert!(!CowIstr::new(&long).is_interned());
+    Istr::new(&"z".repeat(100));
+    budget::set_max_len(None);
+    assert_eq!(budget::max_len(), None);
+}
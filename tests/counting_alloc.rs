@@ -0,0 +1,33 @@
+//! Installs `LsiCountingAlloc` as this test binary's global allocator.
+#![cfg(feature = "counting-alloc")]
+
+use std::alloc::System;
+
+use lsi::counting::LsiCountingAlloc;
+use lsi::InternTable;
+
+#[global_allocator]
+static ALLOC: LsiCountingAlloc = LsiCountingAlloc::new(System);
+
+#[test]
+fn test_counts_interned_bytes() {
+    let table = InternTable::new();
+    let before = ALLOC.stats();
+    // Allocations outside interning calls are not attributed to the interner.
+    let owned: Vec<String> = (0..1000).map(|i| format!("counted-{:04}", i)).collect();
+    assert_eq!(ALLOC.stats(), before);
+    for s in &owned {
+        table.get_or_intern(s);
+    }
+    let after = ALLOC.stats();
+    // Each entry holds at least its 12 bytes of string data.
+    assert!(after.live() - before.live() >= 12 * 1000, "{:?}", after);
+    // Entries are bump-allocated from chunks rather than allocated one by one, except with the
+    // `checkpoint` feature.
+    let allocations = after.allocations - before.allocations;
+    if cfg!(feature = "checkpoint") {
+        assert!(allocations >= 1000, "{:?}", after);
+    } else {
+        assert!(allocations < 1000, "{:?}", after);
+    }
+}
@@ -0,0 +1,158 @@
+//! Randomized operation sequences run against both an `InternTable` and a simple reference
+//! model, checking that everything observable about the table agrees with the model.
+use std::collections::HashMap;
+
+use lsi::{InternTable, Istr, XorFilter};
+
+/// A small xorshift generator, so failures reproduce from the printed seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// A string from a small vocabulary, so operations often hit existing entries, with an
+    /// occasional long or non-ASCII one.
+    fn string(&mut self) -> String {
+        const PARTS: &[&str] = &["a", "b", "foo", "bär", "🦀", " ", "\0"];
+        let len = match self.below(10) {
+            0 => 0,
+            1 => 40 + self.below(200),
+            _ => 1 + self.below(3),
+        };
+        (0..len).map(|_| PARTS[self.below(PARTS.len())]).collect()
+    }
+}
+
+/// The reference interner: each distinct string gets the id of its first insertion.
+#[derive(Default)]
+struct Model {
+    ids: HashMap<String, usize>,
+    strings: Vec<String>,
+}
+
+impl Model {
+    fn intern(&mut self, s: &str) -> usize {
+        if let Some(&id) = self.ids.get(s) {
+            return id;
+        }
+        self.ids.insert(s.to_owned(), self.strings.len());
+        self.strings.push(s.to_owned());
+        self.strings.len() - 1
+    }
+
+    /// The number of strings the table should hold. The empty string is never stored.
+    fn len(&self) -> usize {
+        self.ids.keys().filter(|s| !s.is_empty()).count()
+    }
+
+    fn sorted(&self) -> Vec<&str> {
+        let mut v: Vec<&str> = self
+            .ids
+            .keys()
+            .map(|s| s.as_str())
+            .filter(|s| !s.is_empty())
+            .collect();
+        v.sort_unstable();
+        v
+    }
+}
+
+fn run(seed: u64, ops: usize) {
+    let mut rng = Rng(seed);
+    let table = InternTable::new();
+    let mut model = Model::default();
+    // The handle first returned for each model id.
+    let mut handles: Vec<Istr> = Vec::new();
+    // Checkpoints taken so far, with the number of model strings at the time.
+    #[cfg(feature = "checkpoint")]
+    let mut checkpoints = vec![(0, table.checkpoint())];
+    for step in 0..ops {
+        let ctx = format!("seed {} step {}", seed, step);
+        match rng.below(10) {
+            0..=5 => {
+                let s = rng.string();
+                let id = model.intern(&s);
+                let handle = table.get_or_intern(&s);
+                check_handle(&model, &mut handles, id, handle, &ctx);
+            }
+            6 => {
+                let s = rng.string();
+                let id = model.intern(&s);
+                // Nothing else holds the lock, so this never gives up.
+                let handle = table.try_get_or_intern(&s).expect(&ctx);
+                check_handle(&model, &mut handles, id, handle, &ctx);
+            }
+            #[cfg(feature = "checkpoint")]
+            7 if rng.below(4) == 0 => {
+                // Roll back a random number of the most recent insertions.
+                let keep = model.strings.len() - rng.below(model.strings.len().min(5) + 1);
+                let checkpoint = checkpoints.iter().rev().find(|(n, _)| *n <= keep).copied();
+                if let Some((n, checkpoint)) = checkpoint {
+                    // SAFETY: handles to the removed strings are dropped below and never used.
+                    unsafe { table.restore(checkpoint) };
+                    for s in model.strings.drain(n..) {
+                        model.ids.remove(&s);
+                    }
+                    handles.truncate(n);
+                    checkpoints.retain(|(m, _)| *m <= n);
+                }
+                checkpoints.push((model.strings.len(), table.checkpoint()));
+            }
+            7 => {
+                assert_eq!(table.len(), model.len(), "{}", ctx);
+                assert_eq!(table.is_empty(), model.len() == 0, "{}", ctx);
+            }
+            8 => {
+                let mut contents: Vec<&str> = table.to_vec().iter().map(|s| s.as_str()).collect();
+                contents.sort_unstable();
+                assert_eq!(contents, model.sorted(), "{}", ctx);
+            }
+            _ => {
+                let snapshot = table.to_vec();
+                let filter = XorFilter::new(snapshot.iter().map(|s| s.as_str()));
+                for s in model.sorted() {
+                    assert!(filter.contains(s), "{}: {:?} missing from snapshot", ctx, s);
+                }
+            }
+        }
+    }
+}
+
+fn check_handle(model: &Model, handles: &mut Vec<Istr>, id: usize, handle: Istr, ctx: &str) {
+    let s = &model.strings[id];
+    assert_eq!(handle.as_str(), s, "{}", ctx);
+    assert_eq!(handle.len(), s.len(), "{}", ctx);
+    assert_eq!(handle.is_empty(), s.is_empty(), "{}", ctx);
+    if id == handles.len() {
+        handles.push(handle);
+    }
+    // Interning the same string again must return the very same entry.
+    assert_eq!(
+        handles[id].as_str().as_ptr(),
+        handle.as_str().as_ptr(),
+        "{}: {:?} was interned twice",
+        ctx,
+        s
+    );
+}
+
+#[test]
+fn test_differential() {
+    for seed in 1..=200 {
+        run(seed, 500);
+    }
+}
+
+#[test]
+fn test_differential_long() {
+    run(0x5eed, 20_000);
+}
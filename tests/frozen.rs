@@ -0,0 +1,31 @@
+//! Freezes the global table, so it runs in a process of its own.
+#![cfg(feature = "global")]
+
+use lsi::frozen::{self, OnMiss};
+use lsi::{Error, Istr};
+
+#[test]
+fn test_freeze() {
+    let words: Vec<Istr> = (0..1000).map(|i| Istr::new(&format!("word {}", i))).collect();
+    let frozen = lsi::freeze(OnMiss::Panic);
+    assert_eq!(frozen.len(), 1000);
+    assert_eq!(frozen::on_miss(), OnMiss::Panic);
+    assert!(words.iter().all(|&s| Istr::new(s.as_str()) == s));
+    assert_eq!(Istr::get("word 7"), Some(words[7]));
+    assert_eq!(Istr::get("word 1000"), None);
+    assert!(matches!(Istr::try_new("word 1000"), Err(Error::TableFrozen)));
+    assert!(std::panic::catch_unwind(|| Istr::new("word 1000")).is_err());
+    assert_eq!(lsi::try_intern("word 1000"), None);
+    assert!(std::panic::catch_unwind(|| Istr::intern_all(&["word 1000"])).is_err());
+    lsi::freeze(OnMiss::Error);
+    assert_eq!(lsi::try_intern("word 1000"), None);
+    assert_eq!(lsi::try_intern("word 7"), Some(words[7]));
+    assert!(matches!(Istr::intern_all(&["word 7", "word 1000"]), Err(Error::TableFrozen)));
+    assert!(matches!(lsi::intern_vec(vec!["word 1001".into()]), Err(Error::TableFrozen)));
+    assert_eq!(Istr::intern_iter(["word 7", "word 8"]).unwrap(), words[7..9]);
+    assert_eq!(lsi::active_table().len(), 1000);
+    // Removing a string thaws the table.
+    assert!(unsafe { lsi::remove("word 999") });
+    assert!(frozen::table().is_none());
+    assert_eq!(Istr::new("word 1000"), "word 1000");
+}
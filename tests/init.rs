@@ -0,0 +1,20 @@
+//! Initializes the global table, so it runs in a process of its own.
+#![cfg(feature = "global")]
+
+use lsi::{Config, Error, InitError, Istr};
+
+#[test]
+fn test_init() {
+    let prefill: Vec<String> = (0..1000).map(|i| format!("word {}", i)).collect();
+    let prefill: Vec<&str> = prefill.iter().map(String::as_str).collect();
+    lsi::init(Config {
+        capacity: 50_000,
+        prefill: &prefill,
+    })
+    .unwrap();
+    assert_eq!(lsi::active_table().len(), 1000);
+    assert!(lsi::contains("word 999"));
+    assert_eq!(Istr::new("word 7"), "word 7");
+    let e = Error::from(lsi::init(Config::default()).unwrap_err());
+    assert!(matches!(e, Error::Init(InitError::AlreadyInitialized)));
+}
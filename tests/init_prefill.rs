@@ -0,0 +1,17 @@
+//! Initializes the global table under a budget, so it runs in a process of its own.
+#![cfg(feature = "global")]
+
+use lsi::{budget, Config, Error, InitError};
+
+#[test]
+fn test_prefill_over_budget() {
+    budget::set_limit(Some(0));
+    let e = lsi::init(Config {
+        prefill: &["fn", "let"],
+        ..Default::default()
+    })
+    .unwrap_err();
+    assert!(matches!(e, InitError::Prefill(ref e) if matches!(**e, Error::OverBudget(_))));
+    assert!(!lsi::contains("fn"));
+    budget::set_limit(None);
+}
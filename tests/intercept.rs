@@ -0,0 +1,85 @@
+//! Installs an intercept hook on the global table, so it runs in a process of its own.
+#![cfg(feature = "global")]
+
+use std::borrow::Cow;
+
+use lsi::atoms::AtomMismatch;
+use lsi::{intercept, Error, Istr};
+
+fn canonical(s: &str) -> Result<Cow<'_, str>, &'static str> {
+    if s.contains('\0') {
+        return Err("no NUL bytes");
+    }
+    Ok(match s.trim() {
+        trimmed if trimmed.len() == s.len() => Cow::Borrowed(s),
+        trimmed => Cow::Owned(trimmed.to_string()),
+    })
+}
+
+#[test]
+fn test_intercept() {
+    let untouched = Istr::new(" interned before ");
+    intercept::set_hook(Some(canonical));
+    assert!(intercept::hook().is_some());
+    let trimmed = Istr::new("  padded\t");
+    assert_eq!(trimmed, "padded");
+    assert_eq!(Istr::new("padded"), trimmed);
+    assert_eq!(Istr::new(" padded"), trimmed);
+    assert!(Istr::new("   ").is_empty());
+    match Istr::try_new("nul\0") {
+        Err(Error::Rejected(reason)) => assert_eq!(reason, "no NUL bytes"),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(Istr::get("nul\0"), None);
+    // Every way of interning gets the canonical form.
+    assert_eq!(lsi::try_intern("\tpadded"), Some(trimmed));
+    assert_eq!(lsi::try_intern(" new by try_intern "), Some(Istr::new("new by try_intern")));
+    let batch = Istr::intern_all(&[" padded ", " new in a batch", "new in a batch"]).unwrap();
+    assert_eq!(batch[0], trimmed);
+    assert_eq!(batch[1], batch[2]);
+    assert_eq!(batch[1], "new in a batch");
+    assert_eq!(lsi::try_intern("nul\0"), None);
+    assert!(matches!(
+        Istr::intern_all(&["nul\0"]),
+        Err(Error::Rejected("no NUL bytes"))
+    ));
+    // The dict format writes and reads the strings as they were given.
+    #[cfg(feature = "serde")]
+    {
+        let strings = [" in a dict ", "in a dict", " in a dict "];
+        let bytes = lsi::dict::to_vec(&strings).unwrap();
+        let decoded: Vec<&str> = lsi::dict::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, strings);
+    }
+    // An atom whose name the hook changes fails verification, rather than panicking.
+    lsi::define_atoms! {
+        mod atoms {
+            PADDED = " padded atom ",
+        }
+    }
+    assert!(matches!(
+        atoms::ATOMS.verify(),
+        Err(Error::Atoms(AtomMismatch::Interned(" padded atom ")))
+    ));
+    // Strings interned before the hook are still found as they are.
+    assert_eq!(Istr::new(" interned before "), untouched);
+    intercept::set_hook(None);
+    assert_eq!(Istr::new(" padded"), " padded");
+
+    // A hook can intern strings itself, as it runs without a lock held.
+    intercept::set_hook(Some(alias));
+    let aliased = Istr::new("alias:target");
+    assert_eq!(Istr::get("target"), Some(Istr::new("target")));
+    assert_eq!(lsi::try_intern("alias:target"), Some(aliased));
+    assert_eq!(Istr::intern_all(&["alias:in a batch"]).unwrap()[0], "alias:in a batch");
+    assert!(Istr::get("in a batch").is_some());
+    intercept::set_hook(None);
+}
+
+/// Interns what follows `alias:` as well as the string itself.
+fn alias(s: &str) -> Result<Cow<'_, str>, &'static str> {
+    if let Some(target) = s.strip_prefix("alias:") {
+        Istr::new(target);
+    }
+    Ok(Cow::Borrowed(s))
+}
@@ -0,0 +1,24 @@
+//! Bounds the table of `RcIstr`s, so it runs in a process of its own.
+#![cfg(feature = "lru")]
+
+use lsi::rc::{self, RcIstr};
+
+#[test]
+fn test_lru() {
+    // Two strings per shard.
+    rc::set_capacity(Some(32));
+    let pinned = RcIstr::new("pinned");
+    let pinned_ptr = pinned.as_str().as_ptr();
+    for i in 0..1000 {
+        RcIstr::new(&format!("key {}", i));
+    }
+    assert!(rc::len() <= 32 + 16, "{}", rc::len());
+    // Strings with handles are never evicted: `pinned` keeps its entry, and its address, alive.
+    assert_eq!(RcIstr::new("pinned").as_str().as_ptr(), pinned_ptr);
+    assert_eq!(RcIstr::ref_count(&pinned), 1);
+    assert_eq!(pinned, "pinned");
+    rc::set_capacity(None);
+    let kept: Vec<RcIstr> = (0..1000).map(|i| RcIstr::new(&format!("kept {}", i))).collect();
+    assert!(rc::len() >= 1000);
+    drop(kept);
+}
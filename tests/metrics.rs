@@ -0,0 +1,65 @@
+//! Records the metrics of interning with a minimal recorder.
+#![cfg(feature = "metrics")]
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use lsi::metrics::{BYTES_TOTAL, INTERNED_TOTAL};
+use lsi::InternTable;
+use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+
+/// Every counter and gauge registered, by name.
+#[derive(Default)]
+struct Values(Mutex<HashMap<String, Arc<AtomicU64>>>);
+
+impl Values {
+    fn get(&self, name: &str) -> Arc<AtomicU64> {
+        self.0
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default()
+            .clone()
+    }
+}
+
+impl Recorder for Values {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+        Counter::from_arc(self.get(key.name()))
+    }
+
+    fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+        Gauge::from_arc(self.get(key.name()))
+    }
+
+    fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+        Histogram::noop()
+    }
+}
+
+#[test]
+fn test_metrics() {
+    let values = Values::default();
+    metrics::with_local_recorder(&values, || {
+        lsi::metrics::describe();
+        let table = InternTable::new();
+        for s in ["a", "bb", "ccc", "a"] {
+            table.get_or_intern(s);
+        }
+    });
+    assert_eq!(values.get(INTERNED_TOTAL).load(Ordering::Relaxed), 3);
+    assert_eq!(values.get(BYTES_TOTAL).load(Ordering::Relaxed), 6);
+    #[cfg(feature = "global")]
+    {
+        metrics::with_local_recorder(&values, || lsi::Istr::new("global"));
+        let strings = values.get(lsi::metrics::STRINGS).load(Ordering::Relaxed);
+        assert_eq!(f64::from_bits(strings), 1.0);
+    }
+}
@@ -0,0 +1,32 @@
+//! Removes strings from the global table, so it runs in a process of its own.
+#![cfg(feature = "persist")]
+
+use std::fs;
+
+use lsi::persist::{open_log, read_log};
+use lsi::{active_table, Istr};
+
+#[test]
+fn test_log_removal() {
+    let path = std::env::temp_dir().join(format!("lsi-removal-{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let log = open_log(&path).unwrap();
+    Istr::new("kept in log");
+    Istr::new("removed from log");
+    // SAFETY: nothing else in this process holds the handle or looks it up concurrently.
+    assert!(unsafe { lsi::remove("removed from log") });
+    log.close().unwrap();
+
+    assert_eq!(read_log(&path).unwrap(), ["kept in log"]);
+    // The removal is a record of its own, with the top bit of the length set.
+    let mut removal = (16u32 | 1 << 31).to_le_bytes().to_vec();
+    removal.extend_from_slice(b"removed from log");
+    assert!(fs::read(&path).unwrap().ends_with(&removal));
+
+    // Replaying the log doesn't bring the string back.
+    open_log(&path).unwrap().close().unwrap();
+    let contents = active_table().to_vec();
+    assert!(contents.contains(&Istr::new("kept in log")));
+    assert!(!contents.iter().any(|s| *s == "removed from log"));
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,31 @@
+//! Replays a log under a budget on the global table, so it runs in a process of its own.
+#![cfg(feature = "persist")]
+
+use std::fs;
+use std::io;
+
+use lsi::persist::open_log;
+use lsi::{budget, Error};
+
+#[test]
+fn test_replay_over_budget() {
+    let path = std::env::temp_dir().join(format!("lsi-budget-{}.log", std::process::id()));
+    let s = "replayed over budget";
+    let mut contents = b"LSILOG\x00\x02".to_vec();
+    contents.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    contents.extend_from_slice(s.as_bytes());
+    fs::write(&path, &contents).unwrap();
+
+    budget::set_limit(Some(budget::used()));
+    let e = open_log(&path).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    let source = e.get_ref().and_then(|e| e.downcast_ref::<Error>());
+    assert!(matches!(source, Some(Error::OverBudget(_))), "{:?}", e);
+    assert!(!lsi::contains(s));
+    budget::set_limit(None);
+
+    // The log wasn't opened, so it can be opened once the string fits.
+    open_log(&path).unwrap().close().unwrap();
+    assert!(lsi::contains(s));
+    fs::remove_file(&path).unwrap();
+}
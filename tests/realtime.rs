@@ -0,0 +1,78 @@
+//! Checks that the `realtime` subset doesn't allocate, with an allocator that counts the
+//! allocations of the current thread. Run in release with `--features no-panic` to also check
+//! that it can't panic.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use lsi::{realtime, InternTable};
+
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+fn allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.with(|n| n.get());
+    let result = f();
+    (result, ALLOCATIONS.with(|n| n.get()) - before)
+}
+
+#[test]
+fn test_realtime_subset_does_not_allocate() {
+    let table = InternTable::new();
+    let [a, b, empty, a2] = ["frame", "deadline", "", "frame"].map(|s| table.get_or_intern(s));
+    let (checks, n) = allocations(|| {
+        [
+            realtime::resolve(a) == "frame",
+            realtime::resolve(empty).is_empty(),
+            realtime::len(b) == 8,
+            realtime::ptr_eq(a, a2),
+            !realtime::ptr_eq(a, b),
+            realtime::identity_hash(a) != realtime::identity_hash(b),
+            realtime::to_raw(a) == a.into_raw(),
+        ]
+    });
+    assert_eq!(checks, [true; 7]);
+    assert_eq!(n, 0, "the realtime subset allocated");
+    let mut map = lsi::IstrMap::default();
+    map.insert(a, ());
+    assert_eq!(
+        std::hash::BuildHasher::hash_one(map.hasher(), a),
+        realtime::identity_hash(a)
+    );
+}
+
+#[cfg(feature = "flags")]
+#[test]
+fn test_realtime_flags_do_not_allocate() {
+    use lsi::flags::Flag;
+    const MARK: Flag = Flag::new(3);
+
+    let table = InternTable::new();
+    let s = table.get_or_intern("flagged");
+    let (checks, n) = allocations(|| {
+        [
+            !realtime::set_flag(s, MARK),
+            realtime::get_flag(s, MARK),
+            realtime::clear_flag(s, MARK),
+            !realtime::get_flag(s, MARK),
+        ]
+    });
+    assert_eq!(checks, [true; 4]);
+    assert_eq!(n, 0, "the realtime flag functions allocated");
+}
@@ -0,0 +1,14 @@
+//! Removes strings from the global table, so it runs in a process of its own.
+#![cfg(feature = "suffix-index")]
+
+use lsi::suffix::find_suffix;
+use lsi::Istr;
+
+#[test]
+fn test_removed_from_index() {
+    Istr::new("removed from suffix index");
+    assert_eq!(find_suffix(" suffix index").len(), 1);
+    // SAFETY: nothing else in this process holds the handle or looks it up concurrently.
+    assert!(unsafe { lsi::remove("removed from suffix index") });
+    assert!(find_suffix(" suffix index").is_empty());
+}
@@ -0,0 +1,67 @@
+//! Records the spans and events of interning with a minimal subscriber.
+#![cfg(feature = "tracing")]
+
+use std::sync::{Arc, Mutex};
+
+use lsi::InternTable;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// The names of the spans created and the messages of the events recorded.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<String>>>);
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut names = self.0.lock().unwrap();
+        names.push(span.metadata().name().to_string());
+        Id::from_u64(names.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        struct Message(String);
+        impl tracing::field::Visit for Message {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "message" {
+                    self.0 = format!("{:?}", value);
+                }
+            }
+        }
+        let mut message = Message(String::new());
+        event.record(&mut message);
+        self.0.lock().unwrap().push(message.0);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn test_tracing() {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let table = InternTable::new();
+        for i in 0..100 {
+            table.get_or_intern(&i.to_string());
+        }
+        table.get_or_intern("7");
+    });
+    let recorded = recorder.0.lock().unwrap();
+    let count = |name: &str| recorded.iter().filter(|r| *r == name).count();
+    assert_eq!(count("intern_miss"), 100);
+    // Only acquisitions that have to wait for another thread get a span.
+    assert_eq!(count("lock_wait"), 0);
+    assert!(count("rehash") > 0);
+    assert!(count("growing index") > 0);
+    #[cfg(not(feature = "checkpoint"))]
+    assert!(count("allocating arena chunk") > 0);
+}
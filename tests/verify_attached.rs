@@ -0,0 +1,33 @@
+//! Attaches to a table other than the global one, so it runs in a process of its own.
+#![cfg(feature = "persist")]
+
+use std::fs;
+
+use lsi::persist::write_snapshot;
+use lsi::verify::{verify_snapshot, Problem};
+use lsi::{InternTable, Istr, GLOBAL_TABLE};
+
+static HOST: InternTable = InternTable::new();
+
+#[test]
+fn test_verify_snapshot_of_attached_table() {
+    HOST.get_or_intern("interned by the host");
+    unsafe { lsi::attach(lsi::__private::table_handle_of(&HOST)) }.unwrap();
+    Istr::new("interned after attaching");
+
+    let path = std::env::temp_dir().join(format!("lsi-attached-{}.log", std::process::id()));
+    write_snapshot(&path).unwrap();
+    let report = verify_snapshot(&path).unwrap();
+    assert_eq!(report.entries, 2);
+    assert!(report.is_ok(), "{}", report);
+
+    // A string missing from the attached table is reported, whatever the global table holds.
+    GLOBAL_TABLE.get_or_intern("only in the global table");
+    HOST.get_or_intern("only in the host's table");
+    let report = verify_snapshot(&path).unwrap();
+    assert_eq!(
+        report.problems,
+        [Problem::NotOnDisk("only in the host's table".into())]
+    );
+    fs::remove_file(&path).unwrap();
+}